@@ -0,0 +1,226 @@
+//! Helpers for ACH payments from accounts that were verified with `Auth`.
+//!
+//! An [`OriginatorRecord`][OriginatorRecord] holds everything about a bank
+//! account that is needed to debit or credit it over ACH, and is only created
+//! once the numbers returned by Plaid have been validated. Records can be
+//! debited or credited in a NACHA file, see [`NachaFile`][NachaFile].
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! use plaid::api::product::AuthData;
+//! use plaid::ach::{ self, AchAccountType };
+//!
+//! let data: AuthData = json::decode(include_str!("fixtures/post_auth_success.json")).unwrap();
+//! let record = ach::from_auth(&data, &data.accounts[0].id).unwrap();
+//!
+//! assert_eq!(record.account_type, AchAccountType::Checking);
+//! assert_eq!(record.routing_number.expose(), "021000021");
+//! assert_eq!(record.debit_code(), 27);
+//! # }
+//! ```
+//!
+//! [OriginatorRecord]: ./struct.OriginatorRecord.html
+//! [NachaFile]: ./struct.NachaFile.html
+
+mod nacha;
+
+pub use self::nacha::*;
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use api::data::numbers::NumberError;
+use api::product::AuthData;
+use api::types::{ Account, AccountNumber, RoutingNumber, UID };
+
+/// The type of a bank account, as far as ACH is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AchAccountType {
+    /// A checking (demand deposit) account.
+    Checking,
+    /// A savings account.
+    Savings
+}
+
+impl AchAccountType {
+
+    /// The type of the account with the given Plaid `subtype`, e.g `checking`.
+    /// Certificates of deposit and prepaid accounts can not be used for ACH transfers.
+    pub fn from_subtype(subtype: &str) -> Option<AchAccountType> {
+        match subtype {
+            "checking" => Some(AchAccountType::Checking),
+            "savings" | "money market" => Some(AchAccountType::Savings),
+            _ => None
+        }
+    }
+
+}
+
+/// # RecordError
+/// The reasons that an `OriginatorRecord` can not be created for an account.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordError {
+    /// No account with the given id.
+    UnknownAccount(UID),
+    /// The account does not have its numbers, which are only returned by `Auth`.
+    MissingNumbers,
+    /// The routing number is invalid.
+    InvalidRoutingNumber(NumberError),
+    /// The account number is invalid.
+    InvalidAccountNumber(NumberError),
+    /// The account is not a checking or savings account, with the given subtype.
+    UnsupportedAccount(Option<String>)
+}
+
+impl fmt::Display for RecordError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.description())
+    }
+
+}
+
+impl StdError for RecordError {
+
+    fn description(&self) -> &str {
+        match *self {
+            RecordError::UnknownAccount(_) => "No account with the given id",
+            RecordError::MissingNumbers => "The account does not have an account and routing number",
+            RecordError::InvalidRoutingNumber(ref err) => err.description(),
+            RecordError::InvalidAccountNumber(ref err) => err.description(),
+            RecordError::UnsupportedAccount(_) => "Only checking and savings accounts are supported"
+        }
+    }
+
+}
+
+/// # OriginatorRecord
+/// A validated bank account that can be debited or credited over ACH.
+/// The numbers are masked when printed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OriginatorRecord {
+    /// The id of the Plaid account.
+    pub account_id: UID,
+    /// The name of the account holder or account, if it is known.
+    pub name: Option<String>,
+    /// The valid routing number of the institution.
+    pub routing_number: RoutingNumber,
+    /// The valid account number.
+    pub account_number: AccountNumber,
+    /// The type of the account.
+    pub account_type: AchAccountType
+}
+
+impl OriginatorRecord {
+
+    /// Create a record for the given account, validating its numbers. Depository
+    /// accounts without a subtype are assumed to be checking accounts.
+    pub fn from_account(account: &Account) -> Result<OriginatorRecord, RecordError> {
+        let (routing, number) = match (account.routing_number.as_ref(), account.account_number.as_ref()) {
+            (Some(r), Some(n)) => (r, n),
+            _ => return Err(RecordError::MissingNumbers)
+        };
+        try!(routing.validate().map_err(RecordError::InvalidRoutingNumber));
+        try!(number.validate().map_err(RecordError::InvalidAccountNumber));
+
+        let account_type = match account.account_subtype {
+            Some(ref subtype) => AchAccountType::from_subtype(subtype),
+            None if account.account_type == "depository" => Some(AchAccountType::Checking),
+            None => None
+        };
+        let account_type = match account_type {
+            Some(t) => t,
+            None => return Err(RecordError::UnsupportedAccount(account.account_subtype.clone()))
+        };
+
+        Ok(OriginatorRecord {
+            account_id: account.id.clone(),
+            name: account.meta.as_ref().and_then(|m| m.name.clone()),
+            routing_number: routing.clone(),
+            account_number: number.clone(),
+            account_type: account_type
+        })
+    }
+
+    /// The NACHA transaction code for a credit (deposit) to this account.
+    pub fn credit_code(&self) -> u8 {
+        match self.account_type {
+            AchAccountType::Checking => 22,
+            AchAccountType::Savings => 32
+        }
+    }
+
+    /// The NACHA transaction code for a debit (withdrawal) from this account.
+    pub fn debit_code(&self) -> u8 {
+        match self.account_type {
+            AchAccountType::Checking => 27,
+            AchAccountType::Savings => 37
+        }
+    }
+
+}
+
+/// Create a record for the account with the given id in `Auth` data.
+pub fn from_auth(data: &AuthData, account_id: &str) -> Result<OriginatorRecord, RecordError> {
+    match data.accounts.iter().find(|a| a.id == account_id) {
+        Some(account) => OriginatorRecord::from_account(account),
+        None => Err(RecordError::UnknownAccount(account_id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use ach::*;
+    use api::data::numbers::NumberError;
+    use api::product::AuthData;
+    use rustc_serialize::json;
+
+    fn data() -> AuthData {
+        json::decode(include_str!("../../fixtures/post_auth_success.json")).unwrap()
+    }
+
+    #[test]
+    fn test_record_from_auth() {
+        let data = data();
+        let record = from_auth(&data, &data.accounts[0].id).unwrap();
+        assert_eq!(record.account_number.expose(), "9900009606");
+        assert_eq!(record.credit_code(), 22);
+        assert!(!format!("{:?}", record).contains("9900009606"));
+        assert_eq!(from_auth(&data, "nope"), Err(RecordError::UnknownAccount("nope".to_string())));
+    }
+
+    #[test]
+    fn test_record_requires_valid_numbers() {
+        let mut account = data().accounts.remove(0);
+        account.routing_number = Some("021000022".into());
+        assert_eq!(OriginatorRecord::from_account(&account), Err(RecordError::InvalidRoutingNumber(NumberError::Checksum)));
+
+        account.routing_number = None;
+        assert_eq!(OriginatorRecord::from_account(&account), Err(RecordError::MissingNumbers));
+
+        let mut account = data().accounts.remove(0);
+        account.account_subtype = Some("credit card".to_string());
+        assert_eq!(OriginatorRecord::from_account(&account),
+                   Err(RecordError::UnsupportedAccount(Some("credit card".to_string()))));
+    }
+
+    #[test]
+    fn test_account_type_from_subtype() {
+        assert_eq!(AchAccountType::from_subtype("checking"), Some(AchAccountType::Checking));
+        assert_eq!(AchAccountType::from_subtype("money market"), Some(AchAccountType::Savings));
+        assert_eq!(AchAccountType::from_subtype("cd"), None);
+        assert_eq!(AchAccountType::from_subtype("prepaid"), None);
+
+        let mut account = data().accounts.remove(0);
+        account.account_subtype = Some("cd".to_string());
+        assert_eq!(OriginatorRecord::from_account(&account),
+                   Err(RecordError::UnsupportedAccount(Some("cd".to_string()))));
+    }
+
+}
@@ -0,0 +1,901 @@
+//! Generation and parsing of NACHA formatted ACH files.
+//!
+//! A file is made of fixed width, 94 character records: a file header, one or
+//! more batches (a batch header, entries with optional addenda, and a batch
+//! control), a file control, and lines of `9`s that pad the file to a multiple
+//! of ten records. Every field is validated when a file is built, rather than
+//! truncated, so that a file is either correct or not produced at all.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! use plaid::api::product::AuthData;
+//! use plaid::api::types::RoutingNumber;
+//! use plaid::api::data::date::Day;
+//! use plaid::ach::{ self, SecCode, FileHeader, BatchHeader, Batch, Entry, NachaFile };
+//!
+//! let data: AuthData = json::decode(include_str!("fixtures/post_auth_success.json")).unwrap();
+//! let record = ach::from_auth(&data, &data.accounts[0].id).unwrap();
+//!
+//! let header = FileHeader::new(RoutingNumber::from("021000021"), "JPMORGAN CHASE",
+//!                              "1234567890", "ACME INC", Day::new(2016, 3, 20).unwrap());
+//! let batch = Batch::new(BatchHeader::new("ACME INC", "1234567890", SecCode::WEB, "ONLINE PMT",
+//!                                         Day::new(2016, 3, 21).unwrap(), RoutingNumber::from("021000021")))
+//!     .entry(Entry::debit(&record, 12550, "INV-1001", "Kelly Walters"));
+//!
+//! let file = NachaFile::new(header).batch(batch).build().unwrap();
+//! assert!(file.lines().all(|l| l.len() == 94));
+//!
+//! let parsed = ach::parse(&file).unwrap();
+//! assert_eq!(parsed.batches[0].entries[0].amount, 12550);
+//! assert_eq!(parsed.total_debits(), 12550);
+//! # }
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use api::data::date::Day;
+use api::types::{ AccountNumber, RoutingNumber };
+use ach::OriginatorRecord;
+
+/// The length of every record in a NACHA file.
+pub const RECORD_LENGTH: usize = 94;
+
+/// The number of records in a block. Files are padded to a whole number of blocks.
+pub const BLOCKING_FACTOR: usize = 10;
+
+/// The largest amount of a single entry, in cents.
+pub const MAX_ENTRY_AMOUNT: u64 = 9_999_999_999;
+
+/// # AchError
+/// The reasons that a NACHA file can not be built or parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AchError {
+    /// The named field is longer than the space it has in its record.
+    TooLong(&'static str),
+    /// The named field contains characters that are not allowed.
+    InvalidCharacters(&'static str),
+    /// The named field is empty, or has an invalid value.
+    InvalidField(&'static str),
+    /// An entry has an amount of zero, or more than `MAX_ENTRY_AMOUNT`.
+    InvalidAmount(u64),
+    /// A batch has no entries, or a file has no batches.
+    Empty,
+    /// A file could not be parsed, with the line number (starting at 1) and reason.
+    Malformed(usize, String)
+}
+
+impl fmt::Display for AchError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AchError::TooLong(field) => write!(f, "The {} is too long", field),
+            AchError::InvalidCharacters(field) => write!(f, "The {} contains invalid characters", field),
+            AchError::InvalidField(field) => write!(f, "The {} is invalid", field),
+            AchError::InvalidAmount(amount) => write!(f, "The amount {} is invalid", amount),
+            AchError::Empty => f.write_str("A file needs at least one batch, and each batch at least one entry"),
+            AchError::Malformed(line, ref reason) => write!(f, "Line {}: {}", line, reason)
+        }
+    }
+
+}
+
+impl StdError for AchError {
+
+    fn description(&self) -> &str {
+        match *self {
+            AchError::TooLong(_) => "A field is too long",
+            AchError::InvalidCharacters(_) => "A field contains invalid characters",
+            AchError::InvalidField(_) => "A field is invalid",
+            AchError::InvalidAmount(_) => "An amount is invalid",
+            AchError::Empty => "A file or batch is empty",
+            AchError::Malformed(..) => "The file is malformed"
+        }
+    }
+
+}
+
+/// The Standard Entry Class of a batch, which describes how the payments were authorized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SecCode {
+    /// Prearranged payment and deposit, authorized in writing by a consumer.
+    PPD,
+    /// Corporate credit or debit.
+    CCD,
+    /// Authorized by a consumer over the internet.
+    WEB,
+    /// Authorized by a consumer over the phone.
+    TEL
+}
+
+impl SecCode {
+
+    /// The three letter code.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SecCode::PPD => "PPD",
+            SecCode::CCD => "CCD",
+            SecCode::WEB => "WEB",
+            SecCode::TEL => "TEL"
+        }
+    }
+
+    /// The `SecCode` with the given three letter code.
+    pub fn parse(code: &str) -> Option<SecCode> {
+        match code {
+            "PPD" => Some(SecCode::PPD),
+            "CCD" => Some(SecCode::CCD),
+            "WEB" => Some(SecCode::WEB),
+            "TEL" => Some(SecCode::TEL),
+            _ => None
+        }
+    }
+
+}
+
+/// # FileHeader
+/// Describes who a file is sent to, and who it is from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHeader {
+    /// The routing number of the bank that the file is sent to, usually your ODFI.
+    pub immediate_destination: RoutingNumber,
+    /// The name of that bank.
+    pub immediate_destination_name: String,
+    /// Nine or ten digits identifying the sender, as agreed with the bank.
+    pub immediate_origin: String,
+    /// The name of the sender.
+    pub immediate_origin_name: String,
+    /// The date the file is created.
+    pub creation_date: Day,
+    /// The time the file is created, as `HHMM`.
+    pub creation_time: Option<String>,
+    /// Distinguishes files created on the same day, `A` to `Z` or `0` to `9`.
+    pub file_id_modifier: char,
+    /// Optional, for your own use.
+    pub reference_code: String
+}
+
+impl FileHeader {
+
+    /// Create a `FileHeader` with file id modifier `A`, and no creation time or reference code.
+    pub fn new<D, O, N>(immediate_destination: RoutingNumber, immediate_destination_name: D,
+                        immediate_origin: O, immediate_origin_name: N, creation_date: Day) -> FileHeader
+        where D: Into<String>, O: Into<String>, N: Into<String> {
+        FileHeader {
+            immediate_destination: immediate_destination,
+            immediate_destination_name: immediate_destination_name.into(),
+            immediate_origin: immediate_origin.into(),
+            immediate_origin_name: immediate_origin_name.into(),
+            creation_date: creation_date,
+            creation_time: None,
+            file_id_modifier: 'A',
+            reference_code: String::new()
+        }
+    }
+
+    /// Set the creation time, as `HHMM`.
+    pub fn creation_time<S: Into<String>>(mut self, time: S) -> FileHeader {
+        self.creation_time = Some(time.into());
+        self
+    }
+
+    /// Set the file id modifier.
+    pub fn file_id_modifier(mut self, modifier: char) -> FileHeader {
+        self.file_id_modifier = modifier;
+        self
+    }
+
+    /// Set the reference code.
+    pub fn reference_code<S: Into<String>>(mut self, code: S) -> FileHeader {
+        self.reference_code = code.into();
+        self
+    }
+
+}
+
+/// # BatchHeader
+/// Describes the company that originates the entries of a batch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchHeader {
+    /// The name of the company, as it appears on the receiver's statement.
+    pub company_name: String,
+    /// Optional, for your own use.
+    pub company_discretionary_data: String,
+    /// Ten characters identifying the company, usually `1` followed by its tax id.
+    pub company_id: String,
+    /// How the entries were authorized.
+    pub sec_code: SecCode,
+    /// Describes the purpose of the entries, e.g `PAYROLL`.
+    pub entry_description: String,
+    /// Optional, a date that is shown to the receiver as given.
+    pub descriptive_date: String,
+    /// The date that the entries should settle.
+    pub effective_date: Day,
+    /// The routing number of the bank that originates the entries.
+    pub originating_dfi: RoutingNumber
+}
+
+impl BatchHeader {
+
+    /// Create a `BatchHeader` with no discretionary data or descriptive date.
+    pub fn new<N, I, D>(company_name: N, company_id: I, sec_code: SecCode, entry_description: D,
+                        effective_date: Day, originating_dfi: RoutingNumber) -> BatchHeader
+        where N: Into<String>, I: Into<String>, D: Into<String> {
+        BatchHeader {
+            company_name: company_name.into(),
+            company_discretionary_data: String::new(),
+            company_id: company_id.into(),
+            sec_code: sec_code,
+            entry_description: entry_description.into(),
+            descriptive_date: String::new(),
+            effective_date: effective_date,
+            originating_dfi: originating_dfi
+        }
+    }
+
+}
+
+/// Whether an entry takes money from, or gives money to, the receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Money is taken from the receiver's account.
+    Debit,
+    /// Money is deposited into the receiver's account.
+    Credit
+}
+
+/// # Entry
+/// A single debit or credit to a validated account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// The account of the receiver.
+    pub record: OriginatorRecord,
+    /// Whether this is a debit or credit.
+    pub direction: Direction,
+    /// The amount, in cents.
+    pub amount: u64,
+    /// Your identifier for the receiver or payment, e.g an invoice number.
+    pub individual_id: String,
+    /// The name of the receiver.
+    pub individual_name: String,
+    /// Payment related information, sent in an addenda record.
+    pub addenda: Option<String>
+}
+
+impl Entry {
+
+    /// Debit `amount` cents from the given account.
+    pub fn debit<I: Into<String>, N: Into<String>>(record: &OriginatorRecord, amount: u64, individual_id: I, individual_name: N) -> Entry {
+        Entry::new(record, Direction::Debit, amount, individual_id.into(), individual_name.into())
+    }
+
+    /// Credit `amount` cents to the given account.
+    pub fn credit<I: Into<String>, N: Into<String>>(record: &OriginatorRecord, amount: u64, individual_id: I, individual_name: N) -> Entry {
+        Entry::new(record, Direction::Credit, amount, individual_id.into(), individual_name.into())
+    }
+
+    fn new(record: &OriginatorRecord, direction: Direction, amount: u64, individual_id: String, individual_name: String) -> Entry {
+        Entry {
+            record: record.clone(),
+            direction: direction,
+            amount: amount,
+            individual_id: individual_id,
+            individual_name: individual_name,
+            addenda: None
+        }
+    }
+
+    /// Attach payment related information, of at most 80 characters.
+    pub fn addenda<S: Into<String>>(mut self, info: S) -> Entry {
+        self.addenda = Some(info.into());
+        self
+    }
+
+    /// The NACHA transaction code of this entry.
+    pub fn transaction_code(&self) -> u8 {
+        match self.direction {
+            Direction::Debit => self.record.debit_code(),
+            Direction::Credit => self.record.credit_code()
+        }
+    }
+
+}
+
+/// # Batch
+/// Entries that share a `BatchHeader`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batch {
+    /// The header of the batch.
+    pub header: BatchHeader,
+    /// The entries of the batch.
+    pub entries: Vec<Entry>
+}
+
+impl Batch {
+
+    /// Create an empty batch.
+    pub fn new(header: BatchHeader) -> Batch {
+        Batch { header: header, entries: Vec::new() }
+    }
+
+    /// Add an entry.
+    pub fn entry(mut self, entry: Entry) -> Batch {
+        self.entries.push(entry);
+        self
+    }
+
+    /// The service class code, `220` for credits only, `225` for debits only, or `200` for both.
+    pub fn service_class(&self) -> u16 {
+        service_class(self.entries.iter().map(|e| e.direction))
+    }
+
+}
+
+fn service_class<I: Iterator<Item = Direction>>(directions: I) -> u16 {
+    let (mut debits, mut credits) = (false, false);
+    for d in directions {
+        match d { Direction::Debit => debits = true, Direction::Credit => credits = true }
+    }
+    match (debits, credits) {
+        (true, false) => 225,
+        (false, true) => 220,
+        _ => 200
+    }
+}
+
+/// # NachaFile
+/// Builds a NACHA file out of batches of entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NachaFile {
+    /// The header of the file.
+    pub header: FileHeader,
+    /// The batches of the file.
+    pub batches: Vec<Batch>
+}
+
+impl NachaFile {
+
+    /// Create a file without any batches.
+    pub fn new(header: FileHeader) -> NachaFile {
+        NachaFile { header: header, batches: Vec::new() }
+    }
+
+    /// Add a batch.
+    pub fn batch(mut self, batch: Batch) -> NachaFile {
+        self.batches.push(batch);
+        self
+    }
+
+    /// Validate every field and build the file, with each record on its own line.
+    pub fn build(&self) -> Result<String, AchError> {
+        if self.batches.is_empty() { return Err(AchError::Empty) }
+
+        let mut records = vec![try!(file_header(&self.header))];
+        let mut totals = Totals::default();
+        let mut sequence = 0;
+
+        for (i, batch) in self.batches.iter().enumerate() {
+            if batch.entries.is_empty() { return Err(AchError::Empty) }
+            let number = i as u64 + 1;
+            let header = &batch.header;
+            let odfi = try!(dfi(&header.originating_dfi, "originating DFI"));
+            records.push(try!(batch_header(header, batch.service_class(), &odfi, number)));
+
+            let mut batch_totals = Totals::default();
+            for entry in &batch.entries {
+                sequence += 1;
+                let trace = format!("{}{}", odfi, try!(numeric(sequence, 7, "trace number")));
+                records.push(try!(entry_detail(entry, &trace)));
+                batch_totals.add(entry.direction, entry.amount, &entry.record.routing_number, entry.addenda.is_some());
+                if let Some(ref info) = entry.addenda {
+                    records.push(format!("705{}0001{}", try!(alpha(info, 80, "addenda")), &trace[8..]));
+                }
+            }
+
+            records.push(format!("8{}{}{}{}{}{}{:19}{:6}{}{}",
+                                 batch.service_class(),
+                                 try!(numeric(batch_totals.count, 6, "entry count")),
+                                 try!(numeric(batch_totals.hash % 10_000_000_000, 10, "entry hash")),
+                                 try!(numeric(batch_totals.debits, 12, "total debits")),
+                                 try!(numeric(batch_totals.credits, 12, "total credits")),
+                                 try!(alpha(&header.company_id, 10, "company id")),
+                                 "", "", odfi,
+                                 try!(numeric(number, 7, "batch number"))));
+            totals.merge(&batch_totals);
+        }
+
+        let padding = (BLOCKING_FACTOR - (records.len() + 1) % BLOCKING_FACTOR) % BLOCKING_FACTOR;
+        let blocks = (records.len() + 1 + padding) / BLOCKING_FACTOR;
+        records.push(format!("9{}{}{}{}{}{}{:39}",
+                             try!(numeric(self.batches.len() as u64, 6, "batch count")),
+                             try!(numeric(blocks as u64, 6, "block count")),
+                             try!(numeric(totals.count, 8, "entry count")),
+                             try!(numeric(totals.hash % 10_000_000_000, 10, "entry hash")),
+                             try!(numeric(totals.debits, 12, "total debits")),
+                             try!(numeric(totals.credits, 12, "total credits")),
+                             ""));
+        for _ in 0..padding {
+            records.push("9".repeat(RECORD_LENGTH));
+        }
+
+        debug_assert!(records.iter().all(|r| r.len() == RECORD_LENGTH));
+        let mut file = records.join("\n");
+        file.push('\n');
+        Ok(file)
+    }
+
+}
+
+/// Running totals of the entries of a batch or file.
+#[derive(Debug, Default)]
+struct Totals {
+    count: u64,
+    hash: u64,
+    debits: u64,
+    credits: u64
+}
+
+impl Totals {
+
+    fn add(&mut self, direction: Direction, amount: u64, routing: &RoutingNumber, addenda: bool) {
+        self.count += if addenda { 2 } else { 1 };
+        self.hash += routing.expose()[..8].parse::<u64>().unwrap_or(0);
+        match direction {
+            Direction::Debit => self.debits += amount,
+            Direction::Credit => self.credits += amount
+        }
+    }
+
+    fn merge(&mut self, other: &Totals) {
+        self.count += other.count;
+        self.hash += other.hash;
+        self.debits += other.debits;
+        self.credits += other.credits;
+    }
+
+}
+
+fn file_header(h: &FileHeader) -> Result<String, AchError> {
+    try!(h.immediate_destination.validate().map_err(|_| AchError::InvalidField("immediate destination")));
+    let origin = &h.immediate_origin;
+    if !(origin.len() == 9 || origin.len() == 10) || !origin.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AchError::InvalidField("immediate origin"))
+    }
+    let time = match h.creation_time {
+        Some(ref t) if t.len() == 4 && t.chars().all(|c| c.is_ascii_digit()) => t.clone(),
+        Some(_) => return Err(AchError::InvalidField("creation time")),
+        None => "    ".to_string()
+    };
+    if !h.file_id_modifier.is_ascii_uppercase() && !h.file_id_modifier.is_ascii_digit() {
+        return Err(AchError::InvalidField("file id modifier"))
+    }
+
+    Ok(format!("101 {}{:>10}{}{}{}094101{}{}{}",
+               h.immediate_destination.expose(), origin,
+               yymmdd(&h.creation_date), time, h.file_id_modifier,
+               try!(alpha(&h.immediate_destination_name, 23, "immediate destination name")),
+               try!(alpha(&h.immediate_origin_name, 23, "immediate origin name")),
+               try!(alpha(&h.reference_code, 8, "reference code"))))
+}
+
+fn batch_header(h: &BatchHeader, service_class: u16, odfi: &str, number: u64) -> Result<String, AchError> {
+    if h.company_name.trim().is_empty() { return Err(AchError::InvalidField("company name")) }
+    if h.company_id.trim().is_empty() { return Err(AchError::InvalidField("company id")) }
+    if h.entry_description.trim().is_empty() { return Err(AchError::InvalidField("entry description")) }
+
+    Ok(format!("5{}{}{}{}{}{}{}{}   1{}{}",
+               service_class,
+               try!(alpha(&h.company_name, 16, "company name")),
+               try!(alpha(&h.company_discretionary_data, 20, "company discretionary data")),
+               try!(alpha(&h.company_id, 10, "company id")),
+               h.sec_code.as_str(),
+               try!(alpha(&h.entry_description, 10, "entry description")),
+               try!(alpha(&h.descriptive_date, 6, "descriptive date")),
+               yymmdd(&h.effective_date),
+               odfi,
+               try!(numeric(number, 7, "batch number"))))
+}
+
+fn entry_detail(e: &Entry, trace: &str) -> Result<String, AchError> {
+    if e.amount == 0 || e.amount > MAX_ENTRY_AMOUNT { return Err(AchError::InvalidAmount(e.amount)) }
+    if e.individual_name.trim().is_empty() { return Err(AchError::InvalidField("individual name")) }
+    try!(e.record.routing_number.validate().map_err(|_| AchError::InvalidField("routing number")));
+    try!(e.record.account_number.validate().map_err(|_| AchError::InvalidField("account number")));
+
+    let routing = e.record.routing_number.expose();
+    Ok(format!("6{}{}{}{}{}{}  {}{}",
+               e.transaction_code(),
+               routing,
+               try!(alpha(e.record.account_number.expose(), 17, "account number")),
+               try!(numeric(e.amount, 10, "amount")),
+               try!(alpha(&e.individual_id, 15, "individual id")),
+               try!(alpha(&e.individual_name, 22, "individual name")),
+               if e.addenda.is_some() { 1 } else { 0 },
+               trace))
+}
+
+/// The first eight digits of a valid routing number.
+fn dfi(routing: &RoutingNumber, field: &'static str) -> Result<String, AchError> {
+    try!(routing.validate().map_err(|_| AchError::InvalidField(field)));
+    Ok(routing.expose()[..8].to_string())
+}
+
+/// An alphanumeric field, which is upper cased and padded with spaces on the right.
+fn alpha(value: &str, len: usize, field: &'static str) -> Result<String, AchError> {
+    if !value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        return Err(AchError::InvalidCharacters(field))
+    }
+    if value.len() > len { return Err(AchError::TooLong(field)) }
+    Ok(format!("{:<width$}", value.to_ascii_uppercase(), width = len))
+}
+
+/// A numeric field, which is padded with zeros on the left.
+fn numeric(value: u64, len: usize, field: &'static str) -> Result<String, AchError> {
+    let s = format!("{:0width$}", value, width = len);
+    if s.len() > len { Err(AchError::TooLong(field)) } else { Ok(s) }
+}
+
+fn yymmdd(day: &Day) -> String {
+    format!("{:02}{:02}{:02}", day.year % 100, day.month, day.day)
+}
+
+/// # ParsedEntry
+/// An entry that was read from a NACHA file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedEntry {
+    /// The NACHA transaction code, e.g `27` for a checking account debit.
+    pub transaction_code: u8,
+    /// The routing number of the receiver's bank.
+    pub routing_number: RoutingNumber,
+    /// The receiver's account number.
+    pub account_number: AccountNumber,
+    /// The amount, in cents.
+    pub amount: u64,
+    /// The identifier of the receiver or payment.
+    pub individual_id: String,
+    /// The name of the receiver.
+    pub individual_name: String,
+    /// The trace number of the entry.
+    pub trace_number: String,
+    /// The payment related information of the addenda record, if any.
+    pub addenda: Option<String>
+}
+
+impl ParsedEntry {
+
+    /// Whether this entry is a debit or credit.
+    pub fn direction(&self) -> Direction {
+        if self.transaction_code % 10 >= 5 { Direction::Debit } else { Direction::Credit }
+    }
+
+}
+
+/// # ParsedBatch
+/// A batch that was read from a NACHA file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedBatch {
+    /// The header of the batch.
+    pub header: BatchHeader,
+    /// The service class code of the batch.
+    pub service_class: u16,
+    /// The entries of the batch.
+    pub entries: Vec<ParsedEntry>
+}
+
+/// # ParsedFile
+/// A NACHA file that was read and checked by [`parse`](./fn.parse.html).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedFile {
+    /// The header of the file.
+    pub header: FileHeader,
+    /// The batches of the file.
+    pub batches: Vec<ParsedBatch>
+}
+
+impl ParsedFile {
+
+    fn total(&self, direction: Direction) -> u64 {
+        self.batches.iter()
+            .flat_map(|b| b.entries.iter())
+            .filter(|e| e.direction() == direction)
+            .map(|e| e.amount)
+            .sum()
+    }
+
+    /// The sum of every debit, in cents.
+    pub fn total_debits(&self) -> u64 {
+        self.total(Direction::Debit)
+    }
+
+    /// The sum of every credit, in cents.
+    pub fn total_credits(&self) -> u64 {
+        self.total(Direction::Credit)
+    }
+
+}
+
+/// Parse a NACHA file, checking the length of every record, the order of the
+/// records, and that the counts, hashes and totals of every control record match.
+pub fn parse(file: &str) -> Result<ParsedFile, AchError> {
+    let lines: Vec<&str> = file.lines().map(|l| l.trim_end_matches('\r')).collect();
+    for (i, line) in lines.iter().enumerate() {
+        if line.len() != RECORD_LENGTH || !line.is_ascii() {
+            return Err(malformed(i, format!("expected {} ASCII characters", RECORD_LENGTH)))
+        }
+    }
+    if lines.chunks(BLOCKING_FACTOR).any(|block| block.len() != BLOCKING_FACTOR) {
+        return Err(malformed(lines.len().saturating_sub(1), "the file is not padded to a whole block".to_string()))
+    }
+
+    let mut parser = Parser { lines: &lines, next: 0 };
+    let header = try!(parser.file_header());
+    let mut batches = Vec::new();
+    let mut totals = Totals::default();
+    while parser.peek() == Some('5') {
+        let (batch, batch_totals) = try!(parser.batch());
+        totals.merge(&batch_totals);
+        batches.push(batch);
+    }
+    if batches.is_empty() { return Err(AchError::Empty) }
+
+    let (i, line) = try!(parser.expect('9', "a file control record"));
+    try!(check(i, line, 1, 7, batches.len() as u64, "batch count"));
+    try!(check(i, line, 7, 13, (lines.len() / BLOCKING_FACTOR) as u64, "block count"));
+    try!(check(i, line, 13, 21, totals.count, "entry count"));
+    try!(check(i, line, 21, 31, totals.hash % 10_000_000_000, "entry hash"));
+    try!(check(i, line, 31, 43, totals.debits, "total debits"));
+    try!(check(i, line, 43, 55, totals.credits, "total credits"));
+
+    for (i, line) in lines.iter().enumerate().skip(parser.next) {
+        if line.chars().any(|c| c != '9') { return Err(malformed(i, "expected padding".to_string())) }
+    }
+
+    Ok(ParsedFile { header: header, batches: batches })
+}
+
+fn malformed(index: usize, reason: String) -> AchError {
+    AchError::Malformed(index + 1, reason)
+}
+
+fn check(i: usize, line: &str, from: usize, to: usize, expected: u64, field: &str) -> Result<(), AchError> {
+    match line[from..to].parse::<u64>() {
+        Ok(n) if n == expected => Ok(()),
+        _ => Err(malformed(i, format!("the {} does not match, expected {}", field, expected)))
+    }
+}
+
+fn day(i: usize, s: &str) -> Result<Day, AchError> {
+    let n = |r: ::std::ops::Range<usize>| s[r].parse::<u32>().ok();
+    match (n(0..2), n(2..4), n(4..6)) {
+        (Some(y), Some(m), Some(d)) => Day::new(2000 + y as i32, m, d),
+        _ => None
+    }.ok_or_else(|| malformed(i, format!("invalid date {}", s)))
+}
+
+struct Parser<'a> {
+    lines: &'a [&'a str],
+    next: usize
+}
+
+impl<'a> Parser<'a> {
+
+    fn peek(&self) -> Option<char> {
+        self.lines.get(self.next).and_then(|l| l.chars().next())
+    }
+
+    fn expect(&mut self, record: char, description: &str) -> Result<(usize, &'a str), AchError> {
+        let i = self.next;
+        match self.lines.get(i) {
+            Some(line) if line.starts_with(record) => {
+                self.next += 1;
+                Ok((i, line))
+            },
+            _ => Err(malformed(i, format!("expected {}", description)))
+        }
+    }
+
+    fn file_header(&mut self) -> Result<FileHeader, AchError> {
+        let (i, line) = try!(self.expect('1', "a file header record"));
+        if &line[1..3] != "01" || &line[34..40] != "094101" {
+            return Err(malformed(i, "unsupported priority, record size, blocking factor or format".to_string()))
+        }
+        let time = line[29..33].trim();
+        Ok(FileHeader {
+            immediate_destination: RoutingNumber::from(line[3..13].trim()),
+            immediate_destination_name: line[40..63].trim_end().to_string(),
+            immediate_origin: line[13..23].trim().to_string(),
+            immediate_origin_name: line[63..86].trim_end().to_string(),
+            creation_date: try!(day(i, &line[23..29])),
+            creation_time: if time.is_empty() { None } else { Some(time.to_string()) },
+            file_id_modifier: line[33..34].chars().next().unwrap_or(' '),
+            reference_code: line[86..94].trim_end().to_string()
+        })
+    }
+
+    fn batch(&mut self) -> Result<(ParsedBatch, Totals), AchError> {
+        let (i, line) = try!(self.expect('5', "a batch header record"));
+        let sec_code = match SecCode::parse(&line[50..53]) {
+            Some(code) => code,
+            None => return Err(malformed(i, format!("unsupported SEC code {}", &line[50..53])))
+        };
+        let service_class = try!(line[1..4].parse::<u16>().map_err(|_| malformed(i, "invalid service class".to_string())));
+        let odfi = &line[79..87];
+        let header = BatchHeader {
+            company_name: line[4..20].trim_end().to_string(),
+            company_discretionary_data: line[20..40].trim_end().to_string(),
+            company_id: line[40..50].trim_end().to_string(),
+            sec_code: sec_code,
+            entry_description: line[53..63].trim_end().to_string(),
+            descriptive_date: line[63..69].trim_end().to_string(),
+            effective_date: try!(day(i, &line[69..75])),
+            originating_dfi: RoutingNumber::from(odfi)
+        };
+        let number = &line[87..94];
+
+        let mut entries = Vec::new();
+        let mut totals = Totals::default();
+        while self.peek() == Some('6') {
+            let (i, line) = try!(self.expect('6', "an entry detail record"));
+            let mut entry = ParsedEntry {
+                transaction_code: try!(line[1..3].parse().map_err(|_| malformed(i, "invalid transaction code".to_string()))),
+                routing_number: RoutingNumber::from(&line[3..12]),
+                account_number: AccountNumber::from(line[12..29].trim_end()),
+                amount: try!(line[29..39].parse().map_err(|_| malformed(i, "invalid amount".to_string()))),
+                individual_id: line[39..54].trim_end().to_string(),
+                individual_name: line[54..76].trim_end().to_string(),
+                trace_number: line[79..94].to_string(),
+                addenda: None
+            };
+            if !entry.trace_number.starts_with(odfi) {
+                return Err(malformed(i, "the trace number does not start with the originating DFI".to_string()))
+            }
+            match &line[78..79] {
+                "0" => {},
+                "1" => {
+                    let (j, addenda) = try!(self.expect('7', "an addenda record"));
+                    if &addenda[1..3] != "05" || addenda[87..94] != entry.trace_number[8..] {
+                        return Err(malformed(j, "the addenda does not belong to the previous entry".to_string()))
+                    }
+                    entry.addenda = Some(addenda[3..83].trim_end().to_string());
+                },
+                _ => return Err(malformed(i, "invalid addenda indicator".to_string()))
+            }
+            totals.add(entry.direction(), entry.amount, &entry.routing_number, entry.addenda.is_some());
+            entries.push(entry);
+        }
+        if entries.is_empty() { return Err(malformed(self.next, "a batch needs at least one entry".to_string())) }
+
+        let (i, line) = try!(self.expect('8', "a batch control record"));
+        if line[1..4].parse::<u16>().ok() != Some(service_class) || &line[79..87] != odfi || &line[87..94] != number {
+            return Err(malformed(i, "the batch control does not match its header".to_string()))
+        }
+        if service_class != 200 && service_class != service_class_of(&entries) {
+            return Err(malformed(i, "the service class does not match the entries".to_string()))
+        }
+        try!(check(i, line, 4, 10, totals.count, "entry count"));
+        try!(check(i, line, 10, 20, totals.hash % 10_000_000_000, "entry hash"));
+        try!(check(i, line, 20, 32, totals.debits, "total debits"));
+        try!(check(i, line, 32, 44, totals.credits, "total credits"));
+
+        Ok((ParsedBatch { header: header, service_class: service_class, entries: entries }, totals))
+    }
+
+}
+
+fn service_class_of(entries: &[ParsedEntry]) -> u16 {
+    service_class(entries.iter().map(|e| e.direction()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use ach::*;
+    use api::data::date::Day;
+    use api::product::AuthData;
+    use api::types::RoutingNumber;
+    use rustc_serialize::json;
+
+    fn record() -> OriginatorRecord {
+        let data: AuthData = json::decode(include_str!("../../fixtures/post_auth_success.json")).unwrap();
+        from_auth(&data, &data.accounts[0].id).unwrap()
+    }
+
+    fn file() -> NachaFile {
+        let header = FileHeader::new(RoutingNumber::from("021000021"), "JPMorgan Chase",
+                                     "1234567890", "Acme Inc", Day::new(2016, 3, 20).unwrap())
+            .creation_time("0930")
+            .reference_code("REF1");
+        let batch = Batch::new(BatchHeader::new("Acme Inc", "1234567890", SecCode::PPD, "Payroll",
+                                                Day::new(2016, 3, 21).unwrap(), RoutingNumber::from("011000138")))
+            .entry(Entry::debit(&record(), 12550, "INV-1001", "Kelly Walters").addenda("March invoice"))
+            .entry(Entry::credit(&record(), 500, "REFUND-7", "Kelly Walters"));
+        NachaFile::new(header).batch(batch)
+    }
+
+    #[test]
+    fn test_build_records() {
+        let built = file().build().unwrap();
+        let lines: Vec<&str> = built.lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert!(lines.iter().all(|l| l.len() == RECORD_LENGTH));
+
+        assert_eq!(lines[0], "101 0210000211234567890160320093\
+                              0A094101JPMORGAN CHASE         ACME INC               REF1    ");
+        assert_eq!(lines[1], "5200ACME INC                            1234567890PPDPAYROLL         \
+                              160321   1011000130000001");
+        assert_eq!(lines[2], "6270210000219900009606       0000012550INV-1001       KELLY WALTERS           \
+                              1011000130000001");
+        assert_eq!(&lines[3][..21], "705MARCH INVOICE     ");
+        assert_eq!(&lines[3][83..], "00010000001");
+        assert_eq!(&lines[4][..3], "622");
+        assert_eq!(lines[5], format!("8200000003{:010}{:012}{:012}1234567890{:25}011000130000001",
+                                     2 * 2100002, 12550, 500, ""));
+        assert_eq!(lines[6], format!("9000001000001{:08}{:010}{:012}{:012}{:39}", 3, 2 * 2100002, 12550, 500, ""));
+        assert!(lines[7..].iter().all(|l| l.chars().all(|c| c == '9')));
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let original = file();
+        let parsed = parse(&original.build().unwrap()).unwrap();
+        assert_eq!(parsed.header, FileHeader { immediate_destination_name: "JPMORGAN CHASE".to_string(),
+                                               immediate_origin_name: "ACME INC".to_string(),
+                                               .. original.header.clone() });
+        assert_eq!(parsed.batches.len(), 1);
+        let batch = &parsed.batches[0];
+        assert_eq!(batch.service_class, 200);
+        assert_eq!(batch.header.effective_date, Day::new(2016, 3, 21).unwrap());
+        assert_eq!(batch.entries[0].addenda, Some("MARCH INVOICE".to_string()));
+        assert_eq!(batch.entries[0].account_number.expose(), "9900009606");
+        assert_eq!(batch.entries[1].direction(), Direction::Credit);
+        assert_eq!(parsed.total_debits(), 12550);
+        assert_eq!(parsed.total_credits(), 500);
+    }
+
+    #[test]
+    fn test_parse_rejects_tampering() {
+        let built = file().build().unwrap();
+
+        let tampered = built.replacen("0000012550", "0000099999", 1);
+        assert!(match parse(&tampered) { Err(AchError::Malformed(6, _)) => true, _ => false });
+
+        let truncated: String = built.lines().take(7).map(|l| format!("{}\n", l)).collect();
+        assert!(match parse(&truncated) { Err(AchError::Malformed(..)) => true, _ => false });
+    }
+
+    #[test]
+    fn test_build_validation() {
+        let invalid = |f: NachaFile| f.build().unwrap_err();
+
+        let mut f = file();
+        f.batches[0].entries[0].amount = 0;
+        assert_eq!(invalid(f), AchError::InvalidAmount(0));
+
+        let mut f = file();
+        f.batches[0].entries[0].individual_name = "A name that is far too long for NACHA".to_string();
+        assert_eq!(invalid(f), AchError::TooLong("individual name"));
+
+        let mut f = file();
+        f.batches[0].header.company_name = "Caf\u{e9}".to_string();
+        assert_eq!(invalid(f), AchError::InvalidCharacters("company name"));
+
+        let mut f = file();
+        f.batches[0].header.originating_dfi = RoutingNumber::from("011000139");
+        assert_eq!(invalid(f), AchError::InvalidField("originating DFI"));
+
+        let mut f = file();
+        f.header.immediate_origin = "12345".to_string();
+        assert_eq!(invalid(f), AchError::InvalidField("immediate origin"));
+
+        let mut f = file();
+        f.batches[0].entries.clear();
+        assert_eq!(invalid(f), AchError::Empty);
+        assert_eq!(invalid(NachaFile::new(file().header)), AchError::Empty);
+    }
+
+}
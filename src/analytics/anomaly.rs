@@ -0,0 +1,356 @@
+//! Detection of unusual transactions in a user's history, e.g for fraud review.
+//!
+//! [`detect`][detect] goes through a user's posted transactions from oldest to
+//! newest, and compares each one with the transactions before it. It returns an
+//! [`Anomaly`][Anomaly] for every transaction that:
+//!
+//! + takes out an amount far outside the history of its merchant, or of its
+//!   category when the merchant has too little history,
+//! + is the first at a merchant, for an amount above a threshold,
+//! + charges the same amount at the same merchant as another transaction on the
+//!   same day, unless the merchant's history already has such repeats, e.g two
+//!   coffees bought together every morning,
+//! + happens in a state, or city when the state is unknown, where none of the
+//!   located transactions before it did.
+//!
+//! Every anomaly has a score from `0` to `1`, which is `0.5` at the threshold of
+//! its kind and grows towards `1` the further the transaction is past it.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! # use plaid::api::types::Transaction;
+//! # let transaction = |id: &str, amount: f64, date: &str| -> Transaction { json::decode(&format!(r#"
+//! #     {{ "_id": "{}", "_account": "acc", "amount": {}, "date": "{}", "name": "Coffee Shop",
+//! #        "pending": false, "type": {{ "primary": "place" }}, "category": [], "category_id": "13005000" }}
+//! # "#, id, amount, date)).unwrap() };
+//! use plaid::analytics::anomaly::{ self, Kind, Options };
+//!
+//! let mut transactions: Vec<Transaction> = (1..9)
+//!     .map(|d| transaction(&format!("c{}", d), 4.0 + d as f64 / 10.0, &format!("2016-03-0{}", d)))
+//!     .collect();
+//! transactions.push(transaction("big", 450.0, "2016-03-10"));
+//!
+//! let anomalies = anomaly::detect(&transactions, &Options::default());
+//! assert_eq!(anomalies.len(), 1);
+//! assert_eq!(anomalies[0].transaction, "big");
+//! assert_eq!(anomalies[0].kind, Kind::MerchantAmount);
+//! # }
+//! ```
+//!
+//! [detect]: ./fn.detect.html
+//! [Anomaly]: ./struct.Anomaly.html
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use analytics::{ recurring, round };
+use api::data::{ Amount, UID };
+use api::data::date::Day;
+use api::types::Transaction;
+
+/// Why a transaction is unusual.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    /// The amount is far outside the history of the merchant.
+    MerchantAmount,
+    /// The amount is far outside the history of the category.
+    CategoryAmount,
+    /// The merchant is new, and the amount above `new_merchant_amount`.
+    NewMerchant,
+    /// The transaction charges the same as the given earlier one.
+    Duplicate(UID),
+    /// The location is new.
+    ForeignLocation
+}
+
+/// # Anomaly
+/// An unusual transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    /// The id of the transaction.
+    pub transaction: UID,
+    /// Why it is unusual.
+    pub kind: Kind,
+    /// How unusual it is, from `0` to `1`.
+    pub score: f64,
+    /// A description of the anomaly, for people reviewing it.
+    pub reason: String
+}
+
+/// The thresholds of detection.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The fewest earlier transactions of a merchant or category for its distribution
+    /// to be used, and of located transactions for a location to count as new.
+    pub min_history: usize,
+    /// The number of deviations from the mean above which an amount is unusual.
+    pub max_deviations: f64,
+    /// The smallest deviation, relative to the mean, so that a merchant that always
+    /// charges the same does not make a cent of difference unusual.
+    pub min_relative_deviation: f64,
+    /// The amount above which the first transaction at a merchant is unusual.
+    pub new_merchant_amount: Amount,
+    /// The most days between two transactions that charge the same for the second
+    /// to be a duplicate. Any more than `0` flags purchases that are repeated daily.
+    pub duplicate_days: i64
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            min_history: 5,
+            max_deviations: 3.0,
+            min_relative_deviation: 0.1,
+            new_merchant_amount: 200.0,
+            duplicate_days: 0
+        }
+    }
+
+}
+
+/// The mean and population standard deviation of some amounts.
+pub fn distribution(amounts: &[Amount]) -> (Amount, Amount) {
+    if amounts.is_empty() { return (0.0, 0.0) }
+    let mean = amounts.iter().sum::<Amount>() / amounts.len() as f64;
+    let variance = amounts.iter().map(|a| (a - mean) * (a - mean)).sum::<f64>() / amounts.len() as f64;
+    (mean, variance.sqrt())
+}
+
+/// The location of a transaction that is compared with the user's history: its
+/// state, or else its city.
+pub fn location(transaction: &Transaction) -> Option<String> {
+    transaction.meta.as_ref()
+        .and_then(|m| m.location.state.as_ref().or(m.location.city.as_ref()))
+        .map(|l| l.trim().to_uppercase())
+        .filter(|l| !l.is_empty())
+}
+
+/// The score of a value past a threshold: `value / (value + threshold)`.
+fn score(value: f64, threshold: f64) -> f64 {
+    round(if value + threshold > 0.0 { value / (value + threshold) } else { 0.0 })
+}
+
+fn same_amount(a: Amount, b: Amount) -> bool {
+    (a - b).abs() < 0.005
+}
+
+/// Whether a merchant's history already charged `amount` twice within `days`, in
+/// which case charging it again is usual for the merchant rather than a duplicate.
+fn is_repeated(history: &[(Day, &Transaction)], amount: Amount, days: i64) -> bool {
+    history.iter().enumerate().any(|(i, &(day, t))| {
+        same_amount(t.amount, amount) && history[..i].iter().rev()
+            .take_while(|&&(d, _)| day.days_since(&d) <= days)
+            .any(|&(_, h)| same_amount(h.amount, amount))
+    })
+}
+
+/// Detect the anomalies in a user's transactions, most unusual first. Pending
+/// transactions are ignored, and so are money coming in for every check but the location.
+pub fn detect(transactions: &[Transaction], options: &Options) -> Vec<Anomaly> {
+    let mut dated: Vec<(Day, &Transaction)> = transactions.iter()
+        .filter(|t| !t.pending)
+        .filter_map(|t| Day::parse(&t.date).map(|d| (d, t)))
+        .collect();
+    dated.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.id.cmp(&b.1.id)));
+
+    let mut merchants: HashMap<String, Vec<(Day, &Transaction)>> = HashMap::new();
+    let mut categories: HashMap<String, Vec<Amount>> = HashMap::new();
+    let mut locations: HashMap<String, usize> = HashMap::new();
+    let mut located = 0;
+    let mut spent = 0;
+    let mut anomalies = Vec::new();
+
+    for &(day, t) in &dated {
+        let merchant = recurring::merchant_key(t);
+        let name = t.normalized_name().unwrap_or_else(|| t.id.clone());
+        let category = t.categories.join(":");
+
+        if t.amount > 0.0 {
+            let history: Vec<(Day, &Transaction)> = merchant.as_ref()
+                .and_then(|m| merchants.get(m)).cloned().unwrap_or_default();
+
+            let unusual = |amounts: &[Amount]| -> Option<(f64, Amount)> {
+                if amounts.len() < options.min_history { return None }
+                let (mean, deviation) = distribution(amounts);
+                let deviation = deviation.max(options.min_relative_deviation * mean.abs()).max(0.01);
+                let deviations = (t.amount - mean).abs() / deviation;
+                if deviations > options.max_deviations { Some((deviations, mean)) } else { None }
+            };
+            let amounts: Vec<Amount> = history.iter().map(|&(_, h)| h.amount).collect();
+            let found = if amounts.len() >= options.min_history {
+                unusual(&amounts).map(|u| (Kind::MerchantAmount, u, name.clone()))
+            } else if !category.is_empty() {
+                categories.get(&category).and_then(|a| unusual(a)).map(|u| (Kind::CategoryAmount, u, category.clone()))
+            } else {
+                None
+            };
+            if let Some((kind, (deviations, mean), of)) = found {
+                anomalies.push(Anomaly {
+                    transaction: t.id.clone(),
+                    kind: kind,
+                    score: score(deviations, options.max_deviations),
+                    reason: format!("{:.2} is {:.1} deviations from the average of {:.2} at {}",
+                                    t.amount, deviations, mean, of)
+                });
+            }
+
+            if merchant.is_some() && history.is_empty() && spent >= options.min_history &&
+                t.amount > options.new_merchant_amount {
+                anomalies.push(Anomaly {
+                    transaction: t.id.clone(),
+                    kind: Kind::NewMerchant,
+                    score: score(t.amount, options.new_merchant_amount),
+                    reason: format!("{:.2} is the first amount spent at {}", t.amount, name)
+                });
+            }
+
+            let duplicate = if is_repeated(&history, t.amount, options.duplicate_days) { None } else {
+                history.iter().rev()
+                    .take_while(|&&(d, _)| day.days_since(&d) <= options.duplicate_days)
+                    .find(|&&(_, h)| same_amount(h.amount, t.amount))
+            };
+            if let Some(&(d, original)) = duplicate {
+                let days = day.days_since(&d);
+                anomalies.push(Anomaly {
+                    transaction: t.id.clone(),
+                    kind: Kind::Duplicate(original.id.clone()),
+                    score: score(1.0 + (options.duplicate_days - days) as f64, 1.0),
+                    reason: format!("{:.2} at {} was also charged {} day(s) before", t.amount, name, days)
+                });
+            }
+
+            if let Some(m) = merchant {
+                merchants.entry(m).or_default().push((day, t));
+            }
+            if !category.is_empty() {
+                categories.entry(category).or_default().push(t.amount);
+            }
+            spent += 1;
+        }
+
+        if let Some(place) = location(t) {
+            if located >= options.min_history && !locations.contains_key(&place) {
+                anomalies.push(Anomaly {
+                    transaction: t.id.clone(),
+                    kind: Kind::ForeignLocation,
+                    score: score(located as f64, options.min_history as f64),
+                    reason: format!("{} is not where any of the {} transactions before it were", place, located)
+                });
+            }
+            *locations.entry(place).or_insert(0) += 1;
+            located += 1;
+        }
+    }
+
+    anomalies.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+
+    use analytics::anomaly::*;
+    use api::types::Transaction;
+    use testing::{ transaction, TransactionFixture };
+
+    fn history() -> Vec<Transaction> {
+        let mut transactions = Vec::new();
+        for d in 1..7u8 {
+            let date = format!("2016-03-{:02}", d);
+            transactions.push(transaction(&format!("coffee{}", d), 4.0 + d as f64 / 10.0, &date).named("Blue Bottle")
+                              .categories(&["Food and Drink", "Coffee Shop"]).state("CA"));
+            transactions.push(transaction(&format!("lunch{}", d), 12.0, &date).named(&format!("Deli {}", (b'A' + d) as char))
+                              .categories(&["Food and Drink", "Restaurants"]).state("CA"));
+        }
+        transactions
+    }
+
+    fn kinds(anomalies: &[Anomaly], id: &str) -> Vec<Kind> {
+        anomalies.iter().filter(|a| a.transaction == id).map(|a| a.kind.clone()).collect()
+    }
+
+    #[test]
+    fn test_distribution() {
+        assert_eq!(distribution(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]), (5.0, 2.0));
+        assert_eq!(distribution(&[]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_usual_history() {
+        assert!(detect(&history(), &Options::default()).is_empty());
+    }
+
+    #[test]
+    fn test_unusual_amounts() {
+        let mut transactions = history();
+        transactions.push(transaction("latte", 4.55, "2016-03-10").named("Blue Bottle"));
+        transactions.push(transaction("catering", 95.0, "2016-03-10").named("Blue Bottle"));
+        transactions.push(transaction("feast", 150.0, "2016-03-11").named("New Deli").categories(&["Food and Drink", "Restaurants"]));
+
+        let anomalies = detect(&transactions, &Options::default());
+        assert_eq!(kinds(&anomalies, "latte"), vec![]);
+        assert_eq!(kinds(&anomalies, "catering"), vec![Kind::MerchantAmount]);
+        assert_eq!(kinds(&anomalies, "feast"), vec![Kind::CategoryAmount]);
+        let catering = anomalies.iter().find(|a| a.transaction == "catering").unwrap();
+        assert!(catering.score > 0.9);
+        assert_eq!(catering.reason, "95.00 is 208.4 deviations from the average of 4.35 at Blue Bottle");
+    }
+
+    #[test]
+    fn test_new_merchants_and_duplicates() {
+        let mut transactions = history();
+        transactions.push(transaction("tv", 800.0, "2016-03-10").named("Best Buy").categories(&["Shops"]));
+        transactions.push(transaction("socks", 20.0, "2016-03-10").named("Gap").categories(&["Shops"]));
+        transactions.push(transaction("tv2", 800.0, "2016-03-10").named("Best Buy").categories(&["Shops"]));
+        transactions.push(transaction("tv3", 800.0, "2016-03-20").named("Best Buy").categories(&["Shops"]));
+
+        let anomalies = detect(&transactions, &Options::default());
+        assert_eq!(kinds(&anomalies, "tv"), vec![Kind::NewMerchant]);
+        assert_eq!(kinds(&anomalies, "socks"), vec![]);
+        assert_eq!(kinds(&anomalies, "tv2"), vec![Kind::Duplicate("tv".to_string())]);
+        assert_eq!(kinds(&anomalies, "tv3"), vec![]);
+        assert_eq!(anomalies[0].transaction, "tv");
+        assert_eq!(anomalies[0].score, 0.8);
+        assert_eq!(anomalies[1].score, 0.5);
+    }
+
+    #[test]
+    fn test_repeated_purchases() {
+        let mut transactions = history();
+        for d in 1..29u8 {
+            let date = format!("2016-04-{:02}", d);
+            transactions.push(transaction(&format!("daily{}", d), 4.5, &date).named("Starbucks"));
+            // Two bagels at once every other day.
+            if d % 2 == 0 {
+                transactions.push(transaction(&format!("bagel{}a", d), 2.0, &date).named("Bagel Shop"));
+                transactions.push(transaction(&format!("bagel{}b", d), 2.0, &date).named("Bagel Shop"));
+            }
+        }
+        let anomalies = detect(&transactions, &Options::default());
+        let kinds: Vec<(&str, &Kind)> = anomalies.iter().map(|a| (a.transaction.as_str(), &a.kind)).collect();
+        assert_eq!(kinds, vec![("bagel2b", &Kind::Duplicate("bagel2a".to_string()))]);
+
+        let daily: Vec<Transaction> = transactions.into_iter().filter(|t| t.id.starts_with("daily")).collect();
+        assert!(detect(&daily, &Options::default()).is_empty());
+    }
+
+    #[test]
+    fn test_foreign_locations() {
+        let mut transactions = history();
+        transactions.push(transaction("abroad", 12.0, "2016-03-10").named("Deli B").state("NY"));
+        transactions.push(transaction("again", 12.0, "2016-03-11").named("Deli C").state("NY"));
+
+        let anomalies = detect(&transactions, &Options::default());
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].kind, Kind::ForeignLocation);
+        assert_eq!(anomalies[0].transaction, "abroad");
+        assert_eq!(anomalies[0].score, score(12.0, 5.0));
+    }
+
+}
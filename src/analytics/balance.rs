@@ -0,0 +1,359 @@
+//! Daily balance series of an account, reconstructed from its transactions and
+//! forecast from its recurring streams.
+//!
+//! Plaid only reports the balance of an account as of today. [`history`][history]
+//! walks back from the `current_balance` through the account's posted transactions,
+//! giving the balance at the end of each day since the earliest one. The current
+//! balance does not include pending transactions, so they are left out of the history.
+//!
+//! [`forecast`][forecast] walks forward instead: it starts from the current balance
+//! less the pending transactions that have not posted yet, then applies the expected
+//! transactions of the account's active [`recurring`][recurring] streams. Days on which
+//! the projected balance would be overdrawn are grouped into [`Overdraft`][Overdraft]s.
+//!
+//! The balance of a credit, loan or mortgage account is the amount owed, which money
+//! leaving the account increases. Such an account is overdrawn when it exceeds its limit.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! # use plaid::api::types::{ Account, Transaction };
+//! # let transaction = |id: &str, amount: f64, date: &str| -> Transaction { json::decode(&format!(r#"
+//! #     {{ "_id": "{}", "_account": "checking", "amount": {}, "date": "{}", "name": "{}",
+//! #        "pending": false, "type": {{ "primary": "place" }}, "category": [], "category_id": "13005000" }}
+//! # "#, id, amount, date, id)).unwrap() };
+//! # let account: Account = json::decode(r#"{ "_id": "checking", "_item": "item", "institution_type": "fake_institution",
+//! #     "type": "depository", "balance": { "current": 100.0, "available": 100.0 } }"#).unwrap();
+//! use plaid::api::data::date::Day;
+//! use plaid::analytics::balance::{ self, Options };
+//!
+//! let transactions = vec![transaction("Rent", 900.0, "2016-01-01"), transaction("Rent", 900.0, "2016-02-01"),
+//!                         transaction("Rent", 900.0, "2016-03-01"), transaction("Salary", -950.0, "2016-03-01")];
+//! let today = Day::new(2016, 3, 10).unwrap();
+//!
+//! let history = balance::history(&account, &transactions, today);
+//! assert_eq!(history[0].day.to_date(), "2016-01-01");
+//! assert_eq!(history[0].balance, 950.0);
+//!
+//! let forecast = balance::forecast(&account, &transactions, today, &Options::default());
+//! assert_eq!(forecast.overdrafts[0].start.to_date(), "2016-04-01");
+//! assert_eq!(forecast.overdrafts[0].worst, -800.0);
+//! # }
+//! ```
+//!
+//! [history]: ./fn.history.html
+//! [forecast]: ./fn.forecast.html
+//! [recurring]: ../recurring/index.html
+//! [Overdraft]: ./struct.Overdraft.html
+
+use std::collections::{ BTreeMap, HashSet };
+
+use analytics::round;
+use analytics::recurring::{ self, Cadence, RecurringStream };
+use api::data::{ Amount, UID };
+use api::data::date::Day;
+use api::product::ConnectData;
+use api::types::{ Account, Transaction };
+
+/// The balance of an account at the end of a day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DailyBalance {
+    /// The day.
+    pub day: Day,
+    /// The balance at the end of the day.
+    pub balance: Amount
+}
+
+/// Consecutive days on which an account is overdrawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Overdraft {
+    /// The first day.
+    pub start: Day,
+    /// The last day.
+    pub end: Day,
+    /// The worst balance: the lowest of an asset, or the highest owed on a liability.
+    pub worst: Amount
+}
+
+/// The projected balances of an account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Forecast {
+    /// The balance at the end of each day, from the day after `today`.
+    pub balances: Vec<DailyBalance>,
+    /// The projected overdrafts, in order.
+    pub overdrafts: Vec<Overdraft>
+}
+
+/// The balance series of an account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    /// The id of the account.
+    pub account_id: UID,
+    /// The reconstructed balances, see [`history`](./fn.history.html).
+    pub history: Vec<DailyBalance>,
+    /// The projected balances, see [`forecast`](./fn.forecast.html).
+    pub forecast: Forecast
+}
+
+/// How balances are forecast.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The number of days to forecast.
+    pub horizon: i64,
+    /// Whether pending transactions are applied at the start of the forecast.
+    pub include_pending: bool,
+    /// The balance of an asset account below which it is overdrawn.
+    pub overdraft_threshold: Amount,
+    /// How recurring streams are detected.
+    pub recurring: recurring::Options
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            horizon: 30,
+            include_pending: true,
+            overdraft_threshold: 0.0,
+            recurring: recurring::Options::default()
+        }
+    }
+
+}
+
+/// Whether the balance of an account is an amount owed.
+pub fn is_liability(account: &Account) -> bool {
+    match account.account_type.as_str() {
+        "credit" | "loan" | "mortgage" => true,
+        _ => false
+    }
+}
+
+/// How a transaction changes the balance of its account.
+pub fn effect(account: &Account, transaction: &Transaction) -> Amount {
+    if is_liability(account) { transaction.amount } else { -transaction.amount }
+}
+
+/// Whether an account with the given balance is overdrawn.
+pub fn is_overdrawn(account: &Account, balance: Amount, options: &Options) -> bool {
+    if is_liability(account) {
+        match account.meta.as_ref().and_then(|m| m.limit) {
+            Some(limit) => balance > limit,
+            None => false
+        }
+    } else {
+        balance < options.overdraft_threshold
+    }
+}
+
+/// The balance at the end of each day, from the day of the account's earliest posted
+/// transaction until `today`, when the balance was `current_balance`. Transactions
+/// dated after `today` are counted on `today`.
+///
+/// `balance(d - 1) = balance(d) - Σ effect(t)` for the posted transactions `t` of day `d`.
+pub fn history(account: &Account, transactions: &[Transaction], today: Day) -> Vec<DailyBalance> {
+    let mut effects: BTreeMap<Day, Amount> = BTreeMap::new();
+    for t in transactions.iter().filter(|t| t.account_id == account.id && !t.pending) {
+        if let Some(day) = Day::parse(&t.date) {
+            *effects.entry(::std::cmp::min(day, today)).or_insert(0.0) += effect(account, t);
+        }
+    }
+
+    let first = effects.keys().next().cloned().unwrap_or(today);
+    let mut balances = Vec::new();
+    let mut balance = account.current_balance;
+    let mut day = today;
+    while day >= first {
+        balances.push(DailyBalance { day: day, balance: round(balance) });
+        balance -= effects.get(&day).cloned().unwrap_or(0.0);
+        day = day.add_days(-1);
+    }
+    balances.reverse();
+    balances
+}
+
+/// The pending transactions of an account that have not posted yet, i.e that no
+/// posted transaction refers to with its `pending_transaction_id`.
+pub fn outstanding<'a>(account: &Account, transactions: &'a [Transaction]) -> Vec<&'a Transaction> {
+    let posted: HashSet<&str> = transactions.iter()
+        .filter(|t| !t.pending)
+        .filter_map(|t| t.pending_transaction_id.as_deref())
+        .collect();
+    transactions.iter()
+        .filter(|t| t.account_id == account.id && t.pending && !posted.contains(t.id.as_str()))
+        .collect()
+}
+
+/// The days after `today` and until `end` on which a stream is expected to recur.
+/// An occurrence that is already overdue is expected the day after `today`.
+pub fn occurrences(stream: &RecurringStream, today: Day, end: Day) -> Vec<Day> {
+    let mut days = Vec::new();
+    let mut overdue = false;
+    for k in 1.. {
+        let day = match stream.cadence {
+            Cadence::Monthly => stream.last_date.add_months(k),
+            Cadence::Annual => stream.last_date.add_months(12 * k),
+            cadence => stream.last_date.add_days((k as f64 * cadence.days()).round() as i64)
+        };
+        if day > end { break }
+        if day <= today { overdue = true } else { days.push(day) }
+    }
+    if overdue && days.first() != Some(&today.add_days(1)) {
+        days.insert(0, today.add_days(1));
+    }
+    days
+}
+
+/// Forecast the balance of an account for `options.horizon` days after `today`.
+///
+/// `balance(today) = current_balance + Σ effect(p)` for the outstanding pending
+/// transactions `p`, then `balance(d) = balance(d - 1) + Σ effect(s)` for the active
+/// recurring streams `s` expected on day `d`, at their average amount.
+pub fn forecast(account: &Account, transactions: &[Transaction], today: Day, options: &Options) -> Forecast {
+    let mut balance = account.current_balance;
+    if options.include_pending {
+        balance += outstanding(account, transactions).iter().map(|t| effect(account, t)).sum::<Amount>();
+    }
+
+    let own: Vec<Transaction> = transactions.iter().filter(|t| t.account_id == account.id).cloned().collect();
+    let end = today.add_days(options.horizon);
+    let mut expected: BTreeMap<Day, Amount> = BTreeMap::new();
+    for stream in recurring::detect(&own, &options.recurring).iter().filter(|s| s.is_active(&today)) {
+        let change = if is_liability(account) { stream.average_amount } else { -stream.average_amount };
+        for day in occurrences(stream, today, end) {
+            *expected.entry(day).or_insert(0.0) += change;
+        }
+    }
+
+    let mut balances = Vec::new();
+    let mut overdrafts: Vec<Overdraft> = Vec::new();
+    for n in 1..(options.horizon + 1) {
+        let day = today.add_days(n);
+        balance += expected.get(&day).cloned().unwrap_or(0.0);
+        let rounded = round(balance);
+        balances.push(DailyBalance { day: day, balance: rounded });
+        if !is_overdrawn(account, rounded, options) { continue }
+        let worse = |a: Amount, b: Amount| if is_liability(account) { a.max(b) } else { a.min(b) };
+        match overdrafts.last_mut() {
+            Some(ref mut o) if o.end.add_days(1) == day => {
+                o.end = day;
+                o.worst = worse(o.worst, rounded);
+            },
+            _ => overdrafts.push(Overdraft { start: day, end: day, worst: rounded })
+        }
+    }
+    Forecast { balances: balances, overdrafts: overdrafts }
+}
+
+/// The balance series of every account of a user.
+pub fn series(data: &ConnectData, today: Day, options: &Options) -> Vec<Series> {
+    data.accounts.iter().map(|account| Series {
+        account_id: account.id.clone(),
+        history: history(account, &data.transactions, today),
+        forecast: forecast(account, &data.transactions, today, options)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use analytics::balance::*;
+    use api::data::date::Day;
+    use api::product::ConnectData;
+    use api::types::Transaction;
+    use rustc_serialize::json;
+    use testing::{ account, transaction, with_limit, TransactionFixture };
+
+    #[test]
+    fn test_history() {
+        let checking = account("checking", "depository", 100.0);
+        let transactions = vec![transaction("a", 25.0, "2016-03-01").account("checking"),
+                                transaction("b", -40.0, "2016-03-03").account("checking"),
+                                transaction("c", 10.0, "2016-03-03").account("checking"),
+                                transaction("p", 60.0, "2016-03-04").account("checking").pending(),
+                                transaction("other", 500.0, "2016-02-01").account("savings")];
+
+        let history = history(&checking, &transactions, Day::new(2016, 3, 4).unwrap());
+        let balances: Vec<(String, f64)> = history.iter().map(|b| (b.day.to_date(), b.balance)).collect();
+        assert_eq!(balances, vec![("2016-03-01".to_string(), 70.0), ("2016-03-02".to_string(), 70.0),
+                                  ("2016-03-03".to_string(), 100.0), ("2016-03-04".to_string(), 100.0)]);
+
+        let card = with_limit(account("card", "credit", 300.0), 1000.0);
+        let transactions = vec![transaction("x", 50.0, "2016-03-02").account("card"),
+                                transaction("y", -20.0, "2016-03-03").account("card")];
+        let history = super::history(&card, &transactions, Day::new(2016, 3, 3).unwrap());
+        let balances: Vec<f64> = history.iter().map(|b| b.balance).collect();
+        assert_eq!(balances, vec![320.0, 300.0]);
+    }
+
+    #[test]
+    fn test_forecast() {
+        let checking = account("checking", "depository", 120.0);
+        let mut transactions: Vec<Transaction> = ["2016-01-15", "2016-02-15", "2016-03-15"].iter()
+            .map(|d| transaction("Rent", 100.0, d).account("checking"))
+            .collect();
+        transactions.extend(["2016-01-01", "2016-02-01", "2016-03-01"].iter()
+            .map(|d| transaction("Salary", -50.0, d).account("checking")));
+        transactions.push(transaction("pending", 30.0, "2016-03-20").account("checking").pending());
+        let mut posted = transaction("posted", 5.0, "2016-03-19").account("checking");
+        posted.pending_transaction_id = Some("settled".to_string());
+        transactions.push(posted);
+        transactions.push(transaction("settled", 5.0, "2016-03-18").account("checking").pending());
+
+        let today = Day::new(2016, 3, 20).unwrap();
+        let forecast = forecast(&checking, &transactions, today, &Options::default());
+        assert_eq!(forecast.balances.len(), 30);
+        assert_eq!(forecast.balances[0].balance, 90.0);
+        let at = |date: &str| forecast.balances.iter().find(|b| b.day.to_date() == date).unwrap().balance;
+        assert_eq!(at("2016-04-01"), 140.0);
+        assert_eq!(at("2016-04-15"), 40.0);
+        assert!(forecast.overdrafts.is_empty());
+
+        let options = Options { overdraft_threshold: 100.0, include_pending: false, .. Options::default() };
+        let forecast = super::forecast(&checking, &transactions, today, &options);
+        assert_eq!(forecast.overdrafts.len(), 1);
+        assert_eq!(forecast.overdrafts[0].start.to_date(), "2016-04-15");
+        assert_eq!(forecast.overdrafts[0].end.to_date(), "2016-04-19");
+        assert_eq!(forecast.overdrafts[0].worst, 70.0);
+    }
+
+    #[test]
+    fn test_overdue_and_liabilities() {
+        let card = with_limit(account("card", "credit", 900.0), 1000.0);
+        let transactions: Vec<Transaction> = ["2016-01-05", "2016-02-05", "2016-03-05"].iter()
+            .map(|d| transaction("Gym", 60.0, d).account("card"))
+            .collect();
+
+        let today = Day::new(2016, 4, 8).unwrap();
+        let forecast = forecast(&card, &transactions, today, &Options { horizon: 10, .. Options::default() });
+        assert_eq!(forecast.balances[0].balance, 960.0);
+        assert!(forecast.overdrafts.is_empty());
+
+        let stream = &recurring::detect(&transactions, &recurring::Options::default())[0];
+        let days: Vec<String> = occurrences(stream, today, today.add_days(40)).iter().map(|d| d.to_date()).collect();
+        assert_eq!(days, vec!["2016-04-09", "2016-05-05"]);
+
+        let forecast = super::forecast(&card, &transactions, today, &Options { horizon: 40, .. Options::default() });
+        assert_eq!(forecast.overdrafts[0].start.to_date(), "2016-05-05");
+        assert_eq!(forecast.overdrafts[0].worst, 1020.0);
+    }
+
+    #[test]
+    fn test_series() {
+        let mut data: ConnectData = json::decode(include_str!("../../fixtures/post_connect_success.json")).unwrap();
+        // The fixture's transactions refer to an `_account` that none of its accounts has.
+        for t in &mut data.transactions {
+            t.account_id = "testaccountid".to_string();
+        }
+        let series = series(&data, Day::new(2016, 6, 2).unwrap(), &Options::default());
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].history.last().unwrap().balance, data.accounts[0].current_balance);
+        assert_eq!(series[1].history.len(), 1);
+        assert_eq!(series[0].forecast.balances.len(), 30);
+    }
+
+}
@@ -0,0 +1,358 @@
+//! A local estimate of a user's income, in the same shape as the `Income` product.
+//!
+//! Not every user can be fetched from `/income/get`, so [`estimate`][estimate]
+//! computes an `IncomeInternalData` from `Connect` transactions instead: every
+//! recurring stream of money coming in (see [`recurring`][recurring]) becomes an
+//! `IncomeStream`. Money moved from another of the user's accounts is not income,
+//! so internal transfers (see [`transfers`][transfers]) are left out first. When
+//! both are available, [`compare`][compare] reports how far the estimate is from
+//! Plaid's, so that it can be validated.
+//!
+//! Plaid interpolates pre-tax income with tax tables, the estimate instead
+//! divides by one minus a flat `tax_rate`.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! # use plaid::api::types::Transaction;
+//! # let transaction = |date: String| -> Transaction { json::decode(&format!(r#"
+//! #     {{ "_id": "{}", "_account": "acc", "amount": -2400, "date": "{}", "name": "BAGUETTES INC",
+//! #        "pending": false, "type": {{ "primary": "special" }}, "category": ["Transfer", "Payroll"],
+//! #        "category_id": "21009000" }}
+//! # "#, date, date)).unwrap() };
+//! use plaid::api::data::date::Day;
+//! use plaid::analytics::income::{ self, Options };
+//!
+//! let today = Day::new(2016, 6, 10).unwrap();
+//! let transactions: Vec<Transaction> = (1..7).map(|i| transaction(today.add_months(-i).to_date())).collect();
+//!
+//! let estimate = income::estimate(&transactions, today, &Options::default());
+//! assert_eq!(estimate.income_streams[0].name, "Baguettes Inc");
+//! assert_eq!(estimate.income_streams[0].monthly_income, 2400.0);
+//! assert_eq!(estimate.projected_yearly_income, 28800.0);
+//! # }
+//! ```
+//!
+//! [estimate]: ./fn.estimate.html
+//! [compare]: ./fn.compare.html
+//! [recurring]: ../recurring/index.html
+//! [transfers]: ../transfers/index.html
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use analytics::{ round, transfers };
+use analytics::recurring::{ self, RecurringStream };
+use api::data::Amount;
+use api::data::date::Day;
+use api::data::raw::RawObject;
+use api::product::{ IncomeInternalData, IncomeStream };
+use api::types::Transaction;
+use summary;
+
+/// How income is estimated.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// How recurring streams are detected.
+    pub recurring: recurring::Options,
+    /// How internal transfers are detected.
+    pub transfers: transfers::Options,
+    /// The flat tax rate used to estimate pre-tax income, from `0` to `1`.
+    pub tax_rate: f64
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            recurring: recurring::Options::default(),
+            transfers: transfers::Options::default(),
+            tax_rate: 0.36
+        }
+    }
+
+}
+
+/// Estimate a user's income as of `today` from their transactions.
+///
+/// Transactions in Plaid's internal transfer category, and the transfers detected
+/// with the `transfers` options, are not counted as income.
+///
+/// + `income_streams` are the recurring streams of money coming in that are still
+///   active, with `days` counted from their first transaction until `today`.
+/// + `last_year_income` is the sum of the transactions of every stream over the
+///   365 days until `today`.
+/// + `projected_yearly_income` is twelve times the monthly income of the active streams.
+/// + The overlap counts consider each stream to last from its first transaction
+///   until one cycle after its last.
+pub fn estimate(transactions: &[Transaction], today: Day, options: &Options) -> IncomeInternalData {
+    let days: HashMap<&str, Day> = transactions.iter()
+        .filter_map(|t| Day::parse(&t.date).map(|d| (t.id.as_str(), d)))
+        .collect();
+    let amounts: HashMap<&str, Amount> = transactions.iter().map(|t| (t.id.as_str(), t.amount)).collect();
+    let year_ago = today.add_days(-365);
+
+    let detected = transfers::transaction_ids(&transfers::detect(transactions, &options.transfers));
+    let transactions: Vec<Transaction> = transactions.iter()
+        .filter(|t| !summary::is_internal_transfer(t) && !detected.contains(&t.id))
+        .cloned()
+        .collect();
+    let streams: Vec<RecurringStream> = recurring::detect(&transactions, &options.recurring).into_iter()
+        .filter(|s| s.is_income())
+        .collect();
+    let span = |s: &RecurringStream| -> (Day, Day) {
+        let first = s.transactions.first().and_then(|id| days.get(id.as_str())).cloned().unwrap_or(s.last_date);
+        (first, s.last_date.add_days(s.cadence.days().round() as i64))
+    };
+
+    let mut last_year_income = 0.0;
+    let mut intervals = Vec::new();
+    for s in &streams {
+        let recent: Vec<&String> = s.transactions.iter()
+            .filter(|id| match days.get(id.as_str()) {
+                Some(d) => *d > year_ago && *d <= today,
+                None => false
+            })
+            .collect();
+        if recent.is_empty() { continue }
+        last_year_income -= recent.iter().filter_map(|id| amounts.get(id.as_str())).sum::<Amount>();
+        let (start, end) = span(s);
+        intervals.push((::std::cmp::max(start, year_ago), end));
+    }
+
+    let mut income_streams: Vec<IncomeStream> = streams.iter()
+        .filter(|s| s.is_active(&today))
+        .map(|s| IncomeStream {
+            monthly_income: round(-s.monthly_amount()),
+            confidence: s.confidence,
+            days: today.days_since(&span(s).0).max(0) as u64,
+            name: s.merchant.clone(),
+            raw_json: RawObject::default()
+        })
+        .collect();
+    income_streams.sort_by(|a, b| b.monthly_income.partial_cmp(&a.monthly_income).unwrap_or(Ordering::Equal));
+
+    let projected = round(income_streams.iter().map(|s| s.monthly_income * 12.0).sum());
+    let last_year_income = round(last_year_income);
+    IncomeInternalData {
+        last_year_income: last_year_income,
+        last_year_income_before_tax: round(last_year_income / (1.0 - options.tax_rate)),
+        projected_yearly_income: projected,
+        projected_yearly_income_before_tax: round(projected / (1.0 - options.tax_rate)),
+        max_number_of_overlapping_income_streams: max_overlap(&intervals),
+        number_of_income_streams: intervals.len() as u64,
+        income_streams: income_streams
+    }
+}
+
+/// The largest number of intervals that include the same day.
+fn max_overlap(intervals: &[(Day, Day)]) -> u64 {
+    let mut events: Vec<(Day, i64)> = Vec::new();
+    for &(start, end) in intervals {
+        events.push((start, 1));
+        events.push((end.add_days(1), -1));
+    }
+    // Ends sort before starts on the same day.
+    events.sort();
+    let (mut current, mut max) = (0, 0);
+    for (_, change) in events {
+        current += change;
+        max = ::std::cmp::max(max, current);
+    }
+    max as u64
+}
+
+/// How an estimated income stream compares to Plaid's stream of the same name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamDivergence {
+    /// The name of Plaid's stream.
+    pub name: String,
+    /// The estimated monthly income.
+    pub estimated: Amount,
+    /// Plaid's monthly income.
+    pub plaid: Amount,
+    /// The difference relative to Plaid's, see [`relative`](./fn.relative.html).
+    pub relative: f64
+}
+
+/// How an estimate compares to the data of the `Income` product.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// The relative difference of `last_year_income`.
+    pub last_year_income: f64,
+    /// The relative difference of `projected_yearly_income`.
+    pub projected_yearly_income: f64,
+    /// The estimated number of streams minus Plaid's.
+    pub number_of_income_streams: i64,
+    /// The streams that were found by both, matched by name.
+    pub streams: Vec<StreamDivergence>,
+    /// The names of the streams that were only estimated.
+    pub only_estimated: Vec<String>,
+    /// The names of the streams that only Plaid found.
+    pub only_plaid: Vec<String>
+}
+
+impl Divergence {
+
+    /// Whether every stream was found by both, and every relative difference is
+    /// at most `tolerance`, e.g `0.1` for 10%.
+    pub fn is_within(&self, tolerance: f64) -> bool {
+        self.only_estimated.is_empty() && self.only_plaid.is_empty() &&
+            self.last_year_income.abs() <= tolerance &&
+            self.projected_yearly_income.abs() <= tolerance &&
+            self.streams.iter().all(|s| s.relative.abs() <= tolerance)
+    }
+
+}
+
+/// The difference of `estimated` from `plaid`, relative to `plaid`, e.g `0.1`
+/// when the estimate is 10% higher. A difference from zero is relative to one dollar.
+pub fn relative(estimated: Amount, plaid: Amount) -> f64 {
+    (estimated - plaid) / plaid.abs().max(1.0)
+}
+
+/// Compare an estimate with the data of the `Income` product. Streams are
+/// matched by their name, ignoring case, digits and punctuation.
+pub fn compare(estimated: &IncomeInternalData, plaid: &IncomeInternalData) -> Divergence {
+    let key = |s: &IncomeStream| recurring::name_key(&s.name).unwrap_or_default();
+    let mut only_estimated: Vec<String> = Vec::new();
+    let mut streams = Vec::new();
+    let mut matched = Vec::new();
+    for e in &estimated.income_streams {
+        match plaid.income_streams.iter().position(|p| key(p) == key(e)) {
+            Some(i) if !matched.contains(&i) => {
+                matched.push(i);
+                let p = &plaid.income_streams[i];
+                streams.push(StreamDivergence {
+                    name: p.name.clone(),
+                    estimated: e.monthly_income,
+                    plaid: p.monthly_income,
+                    relative: relative(e.monthly_income, p.monthly_income)
+                });
+            },
+            _ => only_estimated.push(e.name.clone())
+        }
+    }
+    let only_plaid = plaid.income_streams.iter().enumerate()
+        .filter(|&(i, _)| !matched.contains(&i))
+        .map(|(_, p)| p.name.clone())
+        .collect();
+
+    Divergence {
+        last_year_income: relative(estimated.last_year_income, plaid.last_year_income),
+        projected_yearly_income: relative(estimated.projected_yearly_income, plaid.projected_yearly_income),
+        number_of_income_streams: estimated.number_of_income_streams as i64 - plaid.number_of_income_streams as i64,
+        streams: streams,
+        only_estimated: only_estimated,
+        only_plaid: only_plaid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use analytics::income::*;
+    use api::data::date::Day;
+    use api::product::IncomeData;
+    use api::types::Transaction;
+    use rustc_serialize::json;
+    use testing::{ self, TransactionFixture };
+
+    fn transaction(name: &str, amount: f64, date: Day) -> Transaction {
+        testing::transaction(&format!("{}-{}", name, date), amount, &date.to_date()).named(name)
+    }
+
+    fn history(today: Day) -> Vec<Transaction> {
+        let mut transactions = Vec::new();
+        // Paid on the 1st and 15th for the last ten months.
+        for i in 1..11 {
+            let month = today.first_of_month().add_months(-i);
+            transactions.push(transaction("PLAID", -2625.0, month));
+            transactions.push(transaction("PLAID", -2625.0, month.add_days(14)));
+        }
+        // Monthly, but stopped four months ago.
+        for i in 4..14 {
+            transactions.push(transaction("Baguettes Inc.", -2400.0, today.add_months(-i)));
+        }
+        transactions.push(transaction("Golden Crepes", 12.74, today.add_days(-3)));
+        transactions
+    }
+
+    fn sweeps(today: Day) -> Vec<Transaction> {
+        // A monthly move from savings to checking, on both sides.
+        (1..11).flat_map(|i| {
+            let day = today.first_of_month().add_months(-i).add_days(2);
+            vec![transaction("Online Transfer from Savings", -500.0, day),
+                 transaction("Online Transfer to Checking", 500.0, day).account("savings")]
+        }).collect()
+    }
+
+    #[test]
+    fn test_estimate() {
+        let today = Day::new(2016, 6, 10).unwrap();
+        let estimate = estimate(&history(today), today, &Options::default());
+
+        assert_eq!(estimate.income_streams.len(), 1);
+        assert_eq!(estimate.income_streams[0].name, "Plaid");
+        assert_eq!(estimate.income_streams[0].monthly_income, 5250.0);
+        assert_eq!(estimate.income_streams[0].days, 314);
+        assert_eq!(estimate.projected_yearly_income, 63000.0);
+        assert_eq!(estimate.projected_yearly_income_before_tax, 98437.5);
+        // 20 payments from PLAID, and 8 from Baguettes since 2015-06-11.
+        assert_eq!(estimate.last_year_income, 20.0 * 2625.0 + 8.0 * 2400.0);
+        assert_eq!(estimate.number_of_income_streams, 2);
+        assert_eq!(estimate.max_number_of_overlapping_income_streams, 2);
+    }
+
+    #[test]
+    fn test_estimate_ignores_transfers() {
+        let today = Day::new(2016, 6, 10).unwrap();
+        let mut transactions = history(today);
+        transactions.extend(sweeps(today));
+        // Only the savings side is linked, but the move is categorized as internal.
+        for i in 1..11 {
+            let day = today.first_of_month().add_months(-i).add_days(20);
+            transactions.push(transaction("Sweep", -300.0, day).account("savings")
+                              .categories(&["Transfer", "Internal Account Transfer"]));
+        }
+
+        let estimate = estimate(&transactions, today, &Options::default());
+        let names: Vec<&str> = estimate.income_streams.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Plaid"]);
+        assert_eq!(estimate.projected_yearly_income, 63000.0);
+        assert_eq!(estimate.last_year_income, 20.0 * 2625.0 + 8.0 * 2400.0);
+    }
+
+    #[test]
+    fn test_compare_with_plaid() {
+        let today = Day::new(2016, 6, 10).unwrap();
+        let plaid: IncomeData = json::decode(include_str!("../../fixtures/post_income_success.json")).unwrap();
+        let estimate = estimate(&history(today), today, &Options::default());
+
+        let divergence = compare(&estimate, &plaid.income);
+        assert_eq!(divergence.streams.len(), 1);
+        assert_eq!(divergence.streams[0].name, "PLAID");
+        assert_eq!(divergence.streams[0].relative, 0.0);
+        assert_eq!(divergence.only_plaid, vec!["BAGUETTES INC".to_string()]);
+        assert!(divergence.only_estimated.is_empty());
+        assert_eq!(divergence.projected_yearly_income, 0.0);
+        assert_eq!(divergence.number_of_income_streams, 0);
+        assert!(!divergence.is_within(0.1));
+
+        let same = compare(&plaid.income, &plaid.income);
+        assert!(same.is_within(0.0));
+    }
+
+    #[test]
+    fn test_max_overlap() {
+        let day = |d: u32| Day::new(2016, 1, d).unwrap();
+        assert_eq!(max_overlap(&[(day(1), day(10)), (day(11), day(20))]), 1);
+        assert_eq!(max_overlap(&[(day(1), day(10)), (day(10), day(20)), (day(5), day(12))]), 3);
+        assert_eq!(max_overlap(&[]), 0);
+    }
+
+}
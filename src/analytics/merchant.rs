@@ -0,0 +1,373 @@
+//! Normalization of merchant names, e.g `Starbucks` for
+//! `POS DEBIT 1234 STARBUCKS #2231 SEATTLE WA`.
+//!
+//! A [`Normalizer`][Normalizer] cleans a transaction's name in steps:
+//!
+//! 1. It strips the prefixes added by processors and card networks, such as
+//!    `POS DEBIT` or `SQ *`, and the card numbers and dates that follow them.
+//! 2. It keeps the words before the first one with a digit, which drops store
+//!    numbers, card suffixes and reference codes.
+//! 3. It strips a location tail, i.e a US state code and the city before it.
+//! 4. It title-cases names that are all in capitals.
+//!
+//! It then maps the cleaned name to a canonical merchant with the first rule of
+//! its table that matches. The prefixes and rules are data, in a plain text format
+//! (see [`DEFAULT_RULES`][DEFAULT_RULES]), so that you can ship your own:
+//!
+//! ```text
+//! # A comment.
+//! prefix SQ *
+//! amzn mktp => Amazon
+//! ```
+//!
+//! A rule matches when the words of its pattern appear in a row in the cleaned
+//! name, ignoring case and punctuation. Normalization is deterministic: the
+//! same rules always give the same name.
+//!
+//! ## Example
+//!
+//! ```
+//! use plaid::analytics::merchant::Normalizer;
+//!
+//! let normalizer = Normalizer::default();
+//! assert_eq!(normalizer.normalize("POS DEBIT 1234 STARBUCKS #2231 SEATTLE WA"), Some("Starbucks".to_string()));
+//!
+//! let ours = Normalizer::default().with_rules("blue bottle => Blue Bottle Coffee").unwrap();
+//! assert_eq!(ours.normalize("SQ *BLUE BOTTLE 0042 OAKLAND CA"), Some("Blue Bottle Coffee".to_string()));
+//! ```
+//!
+//! `Transaction::normalized_name` normalizes a transaction with the
+//! [`default_normalizer`][default_normalizer], whose rules are only parsed once.
+//!
+//! [Normalizer]: ./struct.Normalizer.html
+//! [DEFAULT_RULES]: ./constant.DEFAULT_RULES.html
+//! [default_normalizer]: ./fn.default_normalizer.html
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::OnceLock;
+
+use api::types::Transaction;
+
+/// The prefixes and rules of `Normalizer::default()`.
+pub const DEFAULT_RULES: &'static str = "\
+# Processors and card networks
+prefix POS DEBIT
+prefix POS PURCHASE
+prefix POS
+prefix DEBIT CARD PURCHASE
+prefix DEBIT PURCHASE
+prefix PURCHASE AUTHORIZED ON
+prefix PURCHASE
+prefix CHECKCARD
+prefix CHECK CARD
+prefix DBT CRD
+prefix VISA DDA PUR
+prefix ACH DEBIT
+prefix ACH CREDIT
+prefix RECURRING PAYMENT
+prefix SQ *
+prefix SQ*
+prefix TST*
+prefix PAYPAL *
+prefix PP*
+
+# Merchants
+amzn => Amazon
+amazon => Amazon
+apple com bill => Apple
+itunes => Apple
+uber eats => Uber Eats
+uber => Uber
+lyft => Lyft
+netflix => Netflix
+spotify => Spotify
+starbucks => Starbucks
+mcdonalds => McDonald's
+7 eleven => 7-Eleven
+wal mart => Walmart
+walmart => Walmart
+wm supercenter => Walmart
+target => Target
+costco => Costco
+";
+
+/// The US state codes that end a location tail.
+const STATES: &'static [&'static str] = &[
+    "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "DC", "FL", "GA", "HI", "ID", "IL", "IN", "IA", "KS",
+    "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE", "NV", "NH", "NJ", "NM", "NY", "NC",
+    "ND", "OH", "OK", "OR", "PA", "RI", "SC", "SD", "TN", "TX", "UT", "VT", "VA", "WA", "WV", "WI", "WY"
+];
+
+/// The first words of city names of two words, e.g `SAN` of `SAN FRANCISCO`.
+const CITY_PREFIXES: &'static [&'static str] = &[
+    "SAN", "SANTA", "LOS", "LAS", "NEW", "FORT", "FT", "ST", "SAINT", "SALT", "PALO", "EL", "LONG"
+];
+
+/// # RuleError
+/// The reason that a table of rules can not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleError {
+    /// The line, counted from `1`, is neither a comment, a prefix nor a rule.
+    Malformed(usize, String)
+}
+
+impl fmt::Display for RuleError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RuleError::Malformed(line, ref text) => write!(f, "Line {} is not a prefix or a rule: {}", line, text)
+        }
+    }
+
+}
+
+impl StdError for RuleError {
+
+    fn description(&self) -> &str {
+        match *self {
+            RuleError::Malformed(..) => "The rules are malformed"
+        }
+    }
+
+}
+
+/// A rule mapping the names that contain a pattern to a canonical merchant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    /// The words of the pattern, see [`key`](./fn.key.html).
+    pub pattern: Vec<String>,
+    /// The canonical name of the merchant.
+    pub name: String
+}
+
+impl Rule {
+
+    /// A rule mapping names that contain `pattern` to `name`.
+    pub fn new(pattern: &str, name: &str) -> Rule {
+        Rule { pattern: key(pattern), name: name.trim().to_string() }
+    }
+
+    /// Whether the rule matches the key of a cleaned name.
+    pub fn matches(&self, key: &[String]) -> bool {
+        !self.pattern.is_empty() && key.windows(self.pattern.len()).any(|w| w == self.pattern.as_slice())
+    }
+
+}
+
+/// # Normalizer
+/// Normalizes merchant names with a table of prefixes and rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalizer {
+    prefixes: Vec<String>,
+    rules: Vec<Rule>
+}
+
+impl Default for Normalizer {
+
+    fn default() -> Normalizer {
+        Normalizer::parse(DEFAULT_RULES).expect("The default rules are valid")
+    }
+
+}
+
+impl Normalizer {
+
+    /// A normalizer without prefixes or rules, which only cleans names.
+    pub fn new() -> Normalizer {
+        Normalizer { prefixes: Vec::new(), rules: Vec::new() }
+    }
+
+    /// A normalizer with the prefixes and rules of a table.
+    pub fn parse(table: &str) -> Result<Normalizer, RuleError> {
+        Normalizer::new().with_rules(table)
+    }
+
+    /// Add the prefixes and rules of a table. Its rules are tried before those
+    /// that the normalizer already has, in the order of the table.
+    pub fn with_rules(mut self, table: &str) -> Result<Normalizer, RuleError> {
+        let mut rules = Vec::new();
+        for (i, line) in table.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+            if let Some(prefix) = line.strip_prefix("prefix ") {
+                self.prefixes.push(prefix.trim().to_ascii_uppercase());
+                continue
+            }
+            let rule = match line.find("=>") {
+                Some(n) => Rule::new(&line[..n], &line[n + 2..]),
+                None => return Err(RuleError::Malformed(i + 1, line.to_string()))
+            };
+            if rule.pattern.is_empty() || rule.name.is_empty() {
+                return Err(RuleError::Malformed(i + 1, line.to_string()))
+            }
+            rules.push(rule);
+        }
+        rules.append(&mut self.rules);
+        self.rules = rules;
+        Ok(self)
+    }
+
+    /// Add a prefix to strip, e.g `SQ *`.
+    pub fn prefix(mut self, prefix: &str) -> Normalizer {
+        self.prefixes.push(prefix.trim().to_ascii_uppercase());
+        self
+    }
+
+    /// Add a rule, which is tried before those that the normalizer already has.
+    pub fn rule(mut self, pattern: &str, name: &str) -> Normalizer {
+        self.rules.insert(0, Rule::new(pattern, name));
+        self
+    }
+
+    /// The cleaned name, before any rule is applied, or `None` when nothing is left.
+    pub fn clean(&self, name: &str) -> Option<String> {
+        let mut rest = name.trim();
+        loop {
+            let before = rest.len();
+            rest = self.strip_prefix(rest);
+            while let Some(token) = rest.split_whitespace().next() {
+                if !is_noise(token) { break }
+                rest = rest[rest.find(token).unwrap_or(0) + token.len()..].trim_start();
+            }
+            if rest.len() == before { break }
+        }
+
+        let mut words: Vec<&str> = Vec::new();
+        for (i, word) in rest.split_whitespace().enumerate() {
+            if i > 0 && (word.starts_with('#') || word.chars().any(|c| c.is_ascii_digit())) { break }
+            words.push(word);
+        }
+        if words.len() >= 3 && STATES.contains(&words[words.len() - 1]) {
+            words.truncate(words.len() - 2);
+            if words.len() >= 2 && CITY_PREFIXES.contains(&words[words.len() - 1]) {
+                words.pop();
+            }
+        }
+        if words.is_empty() { return None }
+
+        let cleaned = words.join(" ");
+        if cleaned.chars().any(|c| c.is_lowercase()) { Some(cleaned) } else { Some(title_case(&cleaned)) }
+    }
+
+    /// The canonical merchant of a transaction, from its `name` or else its
+    /// `original_description`.
+    pub fn normalize_transaction(&self, transaction: &Transaction) -> Option<String> {
+        transaction.name.as_ref().or(transaction.original_description.as_ref())
+            .and_then(|name| self.normalize(name))
+    }
+
+    /// The canonical merchant of a name, or its cleaned name when no rule matches.
+    pub fn normalize(&self, name: &str) -> Option<String> {
+        self.clean(name).map(|cleaned| {
+            let key = key(&cleaned);
+            match self.rules.iter().find(|r| r.matches(&key)) {
+                Some(rule) => rule.name.clone(),
+                None => cleaned
+            }
+        })
+    }
+
+    /// The name without the longest prefix that it starts with.
+    fn strip_prefix<'a>(&self, name: &'a str) -> &'a str {
+        let upper = name.to_ascii_uppercase();
+        let longest = self.prefixes.iter()
+            .filter(|p| upper.starts_with(p.as_str()) && (p.ends_with('*') || match upper[p.len()..].chars().next() {
+                Some(c) => c.is_whitespace() || c == '*',
+                None => true
+            }))
+            .map(|p| p.len())
+            .max();
+        match longest {
+            Some(n) => name[n..].trim_start_matches(|c: char| c.is_whitespace() || c == '*'),
+            None => name
+        }
+    }
+
+}
+
+/// The normalizer with the default rules, which is built on first use.
+pub fn default_normalizer() -> &'static Normalizer {
+    static DEFAULT: OnceLock<Normalizer> = OnceLock::new();
+    DEFAULT.get_or_init(Normalizer::default)
+}
+
+/// The words of a name that rules match: lowercased, without apostrophes, and split
+/// at anything that is not a letter or digit.
+pub fn key(name: &str) -> Vec<String> {
+    name.chars()
+        .filter(|&c| c != '\'')
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .map(|w| w.to_string())
+        .collect()
+}
+
+/// Whether a word before the merchant is a card number or a date, e.g `XXXX1234` or `08/12`.
+fn is_noise(word: &str) -> bool {
+    word.chars().any(|c| c.is_ascii_digit()) &&
+        word.chars().all(|c| c.is_ascii_digit() || "Xx*#/-.".contains(c))
+}
+
+fn title_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut start = true;
+    for c in name.chars() {
+        if start { out.extend(c.to_uppercase()) } else { out.extend(c.to_lowercase()) }
+        start = !c.is_alphanumeric() && c != '\'';
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+
+    use analytics::merchant::*;
+
+    #[test]
+    fn test_clean() {
+        let normalizer = Normalizer::default();
+        let cases = [("POS DEBIT 1234 STARBUCKS #2231 SEATTLE WA", "Starbucks"),
+                     ("SQ *BLUE BOTTLE COFFEE SAN FRANCISCO CA", "Blue Bottle Coffee"),
+                     ("SQ*SIGHTGLASS", "Sightglass"),
+                     ("PURCHASE AUTHORIZED ON 08/12 CHIPOTLE 1234 AUSTIN TX", "Chipotle"),
+                     ("CHECKCARD 0815 UBER EATS HELP.UBER.COM CA", "Uber Eats"),
+                     ("7-ELEVEN 34567 AUSTIN TX", "7-Eleven"),
+                     ("Touchstone Climbing", "Touchstone Climbing"),
+                     ("POSTMATES", "Postmates")];
+        for &(name, cleaned) in cases.iter() {
+            assert_eq!(normalizer.clean(name).unwrap(), cleaned, "{}", name);
+        }
+        assert_eq!(normalizer.clean("POS DEBIT 1234"), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let normalizer = Normalizer::default();
+        assert_eq!(normalizer.normalize("AMZN Mktp US*2K3LJ1").unwrap(), "Amazon");
+        assert_eq!(normalizer.normalize("CHECKCARD 0815 UBER EATS HELP.UBER.COM CA").unwrap(), "Uber Eats");
+        assert_eq!(normalizer.normalize("UBER TRIP 8XJ2 HELP.UBER.COM").unwrap(), "Uber");
+        assert_eq!(normalizer.normalize("MCDONALD'S F1234").unwrap(), "McDonald's");
+        assert_eq!(normalizer.normalize("7-ELEVEN 34567 AUSTIN TX").unwrap(), "7-Eleven");
+        assert_eq!(normalizer.normalize("Golden Crepes").unwrap(), "Golden Crepes");
+
+        let ours = Normalizer::default().rule("uber", "Uber Technologies").prefix("BB*");
+        assert_eq!(ours.normalize("UBER EATS").unwrap(), "Uber Technologies");
+        assert_eq!(ours.normalize("BB*GOLDEN CREPES").unwrap(), "Golden Crepes");
+        assert_eq!(Normalizer::new().normalize("POS DEBIT AMZN").unwrap(), "Pos Debit Amzn");
+    }
+
+    #[test]
+    fn test_parse() {
+        let table = "# Ours\nprefix XYZ*\n\ngolden crepes => Golden Crêpes\n";
+        let normalizer = Normalizer::parse(table).unwrap();
+        assert_eq!(normalizer.normalize("XYZ*GOLDEN CREPES 99").unwrap(), "Golden Crêpes");
+
+        assert_eq!(Normalizer::parse("prefix POS\nstarbucks Starbucks"),
+                   Err(RuleError::Malformed(2, "starbucks Starbucks".to_string())));
+        assert_eq!(Normalizer::parse(" => Nothing"), Err(RuleError::Malformed(1, "=> Nothing".to_string())));
+    }
+
+}
@@ -0,0 +1,38 @@
+//! Analysis of a user's transaction history, computed locally.
+//!
+//! + **[`recurring`][recurring]** detects recurring streams of transactions, such as
+//!   subscriptions, rent and payroll.
+//! + **[`income`][income]** estimates income in the shape of the `Income` product, and
+//!   compares the estimate with Plaid's.
+//! + **[`transfers`][transfers]** pairs the two sides of money moved between a user's
+//!   own accounts.
+//! + **[`balance`][balance]** reconstructs the daily balances of an account, and forecasts
+//!   them to flag overdrafts.
+//! + **[`risk`][risk]** computes underwriting signals, such as overdraft fees and the
+//!   average daily balance.
+//! + **[`merchant`][merchant]** normalizes merchant names with a table of rules.
+//! + **[`anomaly`][anomaly]** flags unusual transactions, such as duplicate charges.
+//!
+//! [recurring]: ./recurring/index.html
+//! [income]: ./income/index.html
+//! [transfers]: ./transfers/index.html
+//! [balance]: ./balance/index.html
+//! [risk]: ./risk/index.html
+//! [merchant]: ./merchant/index.html
+//! [anomaly]: ./anomaly/index.html
+
+use api::data::Amount;
+
+pub mod recurring;
+pub mod income;
+pub mod transfers;
+pub mod balance;
+pub mod risk;
+pub mod merchant;
+pub mod anomaly;
+
+/// Round an amount to whole cents, as every amount in the results of these modules is,
+/// or a score to two decimals.
+pub fn round(amount: Amount) -> Amount {
+    (amount * 100.0).round() / 100.0
+}
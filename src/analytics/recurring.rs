@@ -0,0 +1,354 @@
+//! Detection of recurring transactions, such as subscriptions, rent, utilities
+//! and payroll, from a user's `Connect` transactions.
+//!
+//! This is a local counterpart to the `IncomeStream`s of the `Income` product,
+//! which covers spending as well as income. Transactions are grouped by their
+//! merchant (see [`merchant_key`][merchant_key]) and direction, then into
+//! clusters of similar amounts. A cluster whose dates recur at a regular
+//! [`Cadence`][Cadence] becomes a [`RecurringStream`][RecurringStream].
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! # use plaid::api::types::Transaction;
+//! # let transaction = |date: &str| -> Transaction { json::decode(&format!(r#"
+//! #     {{ "_id": "{}", "_account": "acc", "amount": 9.99, "date": "{}", "name": "Netflix",
+//! #        "pending": false, "type": {{ "primary": "digital" }}, "category": [], "category_id": "17018000" }}
+//! # "#, date, date)).unwrap() };
+//! use plaid::analytics::recurring::{ self, Cadence, Options };
+//!
+//! let transactions: Vec<Transaction> = ["2016-01-05", "2016-02-05", "2016-03-05", "2016-04-05"]
+//!     .iter().map(|d| transaction(d)).collect();
+//!
+//! let streams = recurring::detect(&transactions, &Options::default());
+//! assert_eq!(streams[0].merchant, "Netflix");
+//! assert_eq!(streams[0].cadence, Cadence::Monthly);
+//! assert_eq!(streams[0].next_expected.to_date(), "2016-05-05");
+//! # }
+//! ```
+//!
+//! [merchant_key]: ./fn.merchant_key.html
+//! [Cadence]: ./enum.Cadence.html
+//! [RecurringStream]: ./struct.RecurringStream.html
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use analytics::round;
+use api::data::{ Amount, UID };
+use api::data::date::Day;
+use api::types::Transaction;
+
+/// How often a stream recurs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cadence {
+    /// Every 7 days.
+    Weekly,
+    /// Every 14 days.
+    Biweekly,
+    /// Twice a month, e.g on the 1st and 15th.
+    SemiMonthly,
+    /// Once a month.
+    Monthly,
+    /// Once a year.
+    Annual
+}
+
+impl Cadence {
+
+    /// The average number of days between occurrences.
+    pub fn days(&self) -> f64 {
+        365.25 / self.per_year()
+    }
+
+    /// The number of occurrences in a year.
+    pub fn per_year(&self) -> f64 {
+        match *self {
+            Cadence::Weekly => 52.0,
+            Cadence::Biweekly => 26.0,
+            Cadence::SemiMonthly => 24.0,
+            Cadence::Monthly => 12.0,
+            Cadence::Annual => 1.0
+        }
+    }
+
+    /// The range of days between two occurrences that still counts as this cadence.
+    fn interval(&self) -> (i64, i64) {
+        match *self {
+            Cadence::Weekly => (5, 9),
+            Cadence::Biweekly => (12, 16),
+            Cadence::SemiMonthly => (12, 18),
+            Cadence::Monthly => (26, 35),
+            Cadence::Annual => (350, 380)
+        }
+    }
+
+    /// The cadence of a median interval, in days.
+    fn from_interval(days: i64, intervals: &[i64]) -> Option<Cadence> {
+        let cadence = match days {
+            5..=9 => Cadence::Weekly,
+            12..=18 => {
+                // Both recur about every two weeks, but only a biweekly stream does
+                // so exactly, a semi-monthly one follows the calendar.
+                let exact = intervals.iter().filter(|&&i| i == 14).count();
+                if exact * 4 >= intervals.len() * 3 { Cadence::Biweekly } else { Cadence::SemiMonthly }
+            },
+            26..=35 => Cadence::Monthly,
+            350..=380 => Cadence::Annual,
+            _ => return None
+        };
+        Some(cadence)
+    }
+
+}
+
+/// A stream of transactions that recur at a regular cadence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecurringStream {
+    /// The normalized name of the merchant, see `Transaction::normalized_name`.
+    pub merchant: String,
+    /// The average amount, positive when money leaves the account like every Plaid amount.
+    pub average_amount: Amount,
+    /// How often the stream recurs.
+    pub cadence: Cadence,
+    /// The day of the latest transaction.
+    pub last_date: Day,
+    /// The day the next transaction is expected.
+    pub next_expected: Day,
+    /// How confident the detection is, from `0` to `1`.
+    pub confidence: f64,
+    /// The ids of the transactions in the stream, from oldest to newest.
+    pub transactions: Vec<UID>
+}
+
+impl RecurringStream {
+
+    /// Whether the stream brings money in, e.g payroll.
+    pub fn is_income(&self) -> bool {
+        self.average_amount < 0.0
+    }
+
+    /// The average amount over a month.
+    pub fn monthly_amount(&self) -> Amount {
+        self.average_amount * self.cadence.per_year() / 12.0
+    }
+
+    /// Whether the stream is still active on the given day. Like an `IncomeStream`,
+    /// a stream becomes inactive once it has not recurred for two cycles.
+    pub fn is_active(&self, today: &Day) -> bool {
+        (today.days_since(&self.last_date) as f64) <= 2.0 * self.cadence.days()
+    }
+
+}
+
+/// The thresholds of detection.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The minimum number of transactions in a stream. Annual streams need only two.
+    pub min_occurrences: usize,
+    /// The largest difference in amount from the first transaction of a cluster,
+    /// relative to it, e.g `0.2` for utilities that vary by 20%.
+    pub amount_tolerance: f64,
+    /// The minimum confidence of a stream that is returned.
+    pub min_confidence: f64
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            min_occurrences: 3,
+            amount_tolerance: 0.2,
+            min_confidence: 0.5
+        }
+    }
+
+}
+
+/// The key that transactions of the same merchant share: the key of their
+/// [`normalized_name`](../../api/data/transaction/struct.Transaction.html#method.normalized_name),
+/// so that processor prefixes, store numbers and locations do not split a merchant.
+pub fn merchant_key(transaction: &Transaction) -> Option<String> {
+    transaction.normalized_name().and_then(|name| name_key(&name))
+}
+
+/// The key of a merchant name, see [`merchant_key`](./fn.merchant_key.html).
+pub fn name_key(name: &str) -> Option<String> {
+    let key: Vec<String> = name.split_whitespace()
+        .map(|w| w.chars().filter(|c| c.is_alphabetic()).flat_map(|c| c.to_lowercase()).collect::<String>())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if key.is_empty() { None } else { Some(key.join(" ")) }
+}
+
+/// Detect the recurring streams in the given transactions, ordered by
+/// confidence. Pending transactions are ignored.
+pub fn detect(transactions: &[Transaction], options: &Options) -> Vec<RecurringStream> {
+    let mut groups: HashMap<(String, bool), Vec<(Day, &Transaction)>> = HashMap::new();
+    for t in transactions.iter().filter(|t| !t.pending && t.amount != 0.0) {
+        if let (Some(key), Some(day)) = (merchant_key(t), Day::parse(&t.date)) {
+            groups.entry((key, t.amount < 0.0)).or_default().push((day, t));
+        }
+    }
+
+    let mut streams = Vec::new();
+    for (_, group) in groups {
+        for cluster in clusters(group, options.amount_tolerance) {
+            if let Some(stream) = stream(cluster, options) {
+                streams.push(stream);
+            }
+        }
+    }
+    streams.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(Ordering::Equal)
+                    .then_with(|| a.merchant.cmp(&b.merchant))
+                    .then_with(|| a.last_date.cmp(&b.last_date)));
+    streams
+}
+
+/// Split transactions into clusters of similar amounts, each ordered by day.
+fn clusters(mut group: Vec<(Day, &Transaction)>, tolerance: f64) -> Vec<Vec<(Day, &Transaction)>> {
+    group.sort_by(|a, b| a.1.amount.abs().partial_cmp(&b.1.amount.abs()).unwrap_or(Ordering::Equal));
+    let mut clusters: Vec<Vec<(Day, &Transaction)>> = Vec::new();
+    for entry in group {
+        if let Some(cluster) = clusters.last_mut() {
+            let first = cluster[0].1.amount.abs();
+            if entry.1.amount.abs() - first <= first * tolerance + 0.01 {
+                cluster.push(entry);
+                continue
+            }
+        }
+        clusters.push(vec![entry]);
+    }
+    for c in clusters.iter_mut() {
+        c.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.id.cmp(&b.1.id)));
+    }
+    clusters
+}
+
+/// The stream of a cluster, if it recurs regularly enough.
+///
+/// Its confidence is the product of three parts, each from `0` to `1`: the share
+/// of intervals that fit the cadence, how many occurrences were seen (full at
+/// four, or three for annual streams), and one minus the coefficient of
+/// variation of the amounts.
+fn stream(cluster: Vec<(Day, &Transaction)>, options: &Options) -> Option<RecurringStream> {
+    let mut days: Vec<Day> = cluster.iter().map(|&(d, _)| d).collect();
+    days.dedup();
+    if days.len() < 2 { return None }
+
+    let intervals: Vec<i64> = days.windows(2).map(|w| w[1].days_since(&w[0])).collect();
+    let mut sorted = intervals.clone();
+    sorted.sort();
+    let cadence = match Cadence::from_interval(sorted[sorted.len() / 2], &intervals) {
+        Some(c) => c,
+        None => return None
+    };
+    let required = if cadence == Cadence::Annual { 2 } else { options.min_occurrences };
+    if days.len() < required { return None }
+
+    let (low, high) = cadence.interval();
+    let regular = intervals.iter().filter(|&&i| i >= low && i <= high).count() as f64 / intervals.len() as f64;
+    let full = if cadence == Cadence::Annual { 3.0 } else { 4.0 };
+    let occurrences = ((days.len() - 1) as f64 / (full - 1.0)).min(1.0);
+
+    let amounts: Vec<f64> = cluster.iter().map(|&(_, t)| t.amount).collect();
+    let mean = amounts.iter().sum::<f64>() / amounts.len() as f64;
+    let variance = amounts.iter().map(|a| (a - mean) * (a - mean)).sum::<f64>() / amounts.len() as f64;
+    let consistency = (1.0 - variance.sqrt() / mean.abs()).max(0.0);
+
+    let confidence = regular * occurrences * consistency;
+    if confidence < options.min_confidence { return None }
+
+    let last_date = days[days.len() - 1];
+    let next_expected = match cadence {
+        Cadence::Weekly => last_date.add_days(7),
+        Cadence::Biweekly => last_date.add_days(14),
+        Cadence::SemiMonthly => days[days.len() - 2].add_months(1),
+        Cadence::Monthly => last_date.add_months(1),
+        Cadence::Annual => last_date.add_months(12)
+    };
+    let latest = cluster[cluster.len() - 1].1;
+
+    Some(RecurringStream {
+        merchant: latest.normalized_name().unwrap_or_default(),
+        average_amount: round(mean),
+        cadence: cadence,
+        last_date: last_date,
+        next_expected: next_expected,
+        confidence: confidence,
+        transactions: cluster.iter().map(|&(_, t)| t.id.clone()).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use analytics::recurring::*;
+    use api::data::date::Day;
+    use api::types::Transaction;
+    use testing::{ transaction, TransactionFixture };
+
+    fn series(name: &str, amount: f64, start: &str, step: &dyn Fn(Day, usize) -> Day, n: usize) -> Vec<Transaction> {
+        let start = Day::parse(start).unwrap();
+        (0..n).map(|i| {
+            let date = step(start, i).to_date();
+            transaction(&format!("{}-{}", name, date), amount, &date).named(name)
+        }).collect()
+    }
+
+    #[test]
+    fn test_detect_cadences() {
+        let mut transactions = Vec::new();
+        transactions.extend(series("GYM 1234", 20.0, "2016-01-04", &|d, i| d.add_days(7 * i as i64), 6));
+        transactions.extend(series("Payroll", -1500.0, "2016-01-08", &|d, i| d.add_days(14 * i as i64), 6));
+        transactions.extend(series("Salary", -2000.0, "2016-01-01", &|d, i| d.add_months(i as i32 / 2).add_days(14 * (i % 2) as i64), 6));
+        transactions.extend(series("Rent", 1200.0, "2016-01-01", &|d, i| d.add_months(i as i32), 5));
+        transactions.extend(series("Domain Renewal", 15.0, "2014-03-10", &|d, i| d.add_months(12 * i as i32), 2));
+
+        let streams = detect(&transactions, &Options::default());
+        let cadence = |merchant: &str| streams.iter().find(|s| s.merchant == merchant).map(|s| s.cadence);
+        assert_eq!(cadence("Gym"), Some(Cadence::Weekly));
+        assert_eq!(cadence("Payroll"), Some(Cadence::Biweekly));
+        assert_eq!(cadence("Salary"), Some(Cadence::SemiMonthly));
+        assert_eq!(cadence("Rent"), Some(Cadence::Monthly));
+        assert_eq!(cadence("Domain Renewal"), Some(Cadence::Annual));
+
+        let salary = streams.iter().find(|s| s.merchant == "Salary").unwrap();
+        assert_eq!(salary.next_expected.to_date(), "2016-04-01");
+        assert!(salary.is_income());
+        assert_eq!(salary.monthly_amount(), -4000.0);
+    }
+
+    #[test]
+    fn test_groups_by_merchant_and_amount() {
+        let mut transactions = series("Netflix #22", 9.99, "2016-01-05", &|d, i| d.add_months(i as i32), 3);
+        transactions.extend(series("NETFLIX #31", 9.99, "2016-04-05", &|d, i| d.add_months(i as i32), 2));
+        transactions.extend(series("Netflix", 45.0, "2016-02-20", &|d, i| d.add_months(i as i32), 1));
+
+        let streams = detect(&transactions, &Options::default());
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].transactions.len(), 5);
+        assert_eq!(streams[0].average_amount, 9.99);
+        assert_eq!(streams[0].merchant, "Netflix");
+        assert!(streams[0].confidence > 0.99);
+        assert!(streams[0].is_active(&Day::new(2016, 6, 20).unwrap()));
+        assert!(!streams[0].is_active(&Day::new(2016, 8, 20).unwrap()));
+    }
+
+    #[test]
+    fn test_ignores_irregular_transactions() {
+        let transactions = vec![transaction("a", 12.0, "2016-01-02").named("Golden Crepes"),
+                                transaction("b", 11.5, "2016-01-03").named("Golden Crepes"),
+                                transaction("c", 12.5, "2016-02-20").named("Golden Crepes"),
+                                transaction("d", 12.0, "2016-02-27").named("Golden Crepes")];
+        assert!(detect(&transactions, &Options::default()).is_empty());
+
+        let two = series("Rent", 1200.0, "2016-01-01", &|d, i| d.add_months(i as i32), 2);
+        assert!(detect(&two, &Options::default()).is_empty());
+    }
+
+}
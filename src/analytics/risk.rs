@@ -0,0 +1,320 @@
+//! Underwriting signals computed from a user's linked accounts.
+//!
+//! [`signals`][signals] computes the features that underwriting models commonly
+//! use from the data of the `Connect` product, optionally with fresher balances
+//! from `Balance` and Plaid's income from `Income`. Each field of
+//! [`Signals`][Signals] documents its formula. Unless noted otherwise:
+//!
+//! + the **window** is the `window_days` days until `today`, included,
+//! + the **months** are the `months` calendar months before the month of `today`,
+//!   from the month of the earliest transaction on,
+//! + **asset accounts** are accounts that are not a credit, loan or mortgage
+//!   account, see [`balance::is_liability`][is_liability],
+//! + transfers between the user's own accounts are excluded, see
+//!   [`transfers`][transfers].
+//!
+//! [signals]: ./fn.signals.html
+//! [Signals]: ./struct.Signals.html
+//! [is_liability]: ../balance/fn.is_liability.html
+//! [transfers]: ../transfers/index.html
+
+use std::collections::HashMap;
+
+use analytics::{ balance, income, round, transfers };
+use api::data::Amount;
+use api::data::date::Day;
+use api::product::{ BalanceData, ConnectData, IncomeData };
+use api::types::{ Account, Transaction };
+use summary::{ self, Period };
+
+/// The category of overdraft fees.
+pub const OVERDRAFT_CATEGORY: &'static str = "Overdraft";
+
+/// The category of fees for insufficient funds (NSF).
+pub const NSF_CATEGORY: &'static str = "Insufficient Funds";
+
+/// The thresholds of the signals.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The number of days until `today` that fees and balances are counted over.
+    pub window_days: i64,
+    /// The number of calendar months before `today`'s that income, spending and
+    /// deposits are counted over.
+    pub months: usize,
+    /// How income is estimated when there is no `IncomeData`, except for its
+    /// `transfers`, which are always those below.
+    pub income: income::Options,
+    /// How internal transfers are detected, for spending, deposits and income.
+    pub transfers: transfers::Options
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            window_days: 90,
+            months: 6,
+            income: income::Options::default(),
+            transfers: transfers::Options::default()
+        }
+    }
+
+}
+
+/// # Signals
+/// The underwriting signals of a user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signals {
+    /// The number of overdraft fees in the window: the transactions taking money out
+    /// in the `Overdraft` category.
+    pub overdraft_fees: usize,
+    /// The number of NSF fees in the window: the transactions taking money out in the
+    /// `Insufficient Funds` category.
+    pub nsf_fees: usize,
+    /// The number of days in the window on which at least one asset account had a
+    /// negative balance at the end of the day, from the balances reconstructed by
+    /// [`balance::history`](../balance/fn.history.html).
+    pub negative_balance_days: usize,
+    /// The average over the days of the window of the total end of day balance of the
+    /// asset accounts: `Σ balance(d) / n`. Days before an account's earliest transaction
+    /// are counted at the balance of that day.
+    pub average_daily_balance: Amount,
+    /// The monthly income: `projected_yearly_income / 12` of the `IncomeData` if it is
+    /// given, or else of the [`income::estimate`](../income/fn.estimate.html).
+    pub monthly_income: Amount,
+    /// The average monthly spending over the months: `Σ outflow / months`, where the
+    /// outflow is the money taken out of every account.
+    pub monthly_spend: Amount,
+    /// `monthly_income / monthly_spend`, unless there was no spending.
+    pub income_to_spend: Option<f64>,
+    /// The number of days from the earliest transaction until `today`, if there are any.
+    pub account_age_days: Option<i64>,
+    /// The coefficient of variation of the monthly deposits over the months:
+    /// `σ / μ` of the money that came into asset accounts each month, where `σ` is
+    /// the population standard deviation. Only known over two months or more, with
+    /// some deposits.
+    pub deposit_volatility: Option<f64>
+}
+
+/// Whether a transaction is a fee in the given category.
+pub fn is_fee(transaction: &Transaction, category: &str) -> bool {
+    transaction.amount > 0.0 && transaction.categories.iter().any(|c| c == category)
+}
+
+/// The months that income, spending and deposits are counted over, as their first days.
+pub fn months(transactions: &[Transaction], today: Day, options: &Options) -> Vec<Day> {
+    let earliest = match transactions.iter().filter_map(|t| Day::parse(&t.date)).min() {
+        Some(day) => day.first_of_month(),
+        None => return Vec::new()
+    };
+    let current = today.first_of_month();
+    (1..(options.months as i32 + 1)).rev()
+        .map(|n| current.add_months(-n))
+        .filter(|m| *m >= earliest)
+        .collect()
+}
+
+/// Compute the signals of a user as of `today`. The balances of `balances`, when
+/// given, replace those of the `Connect` accounts with the same id.
+pub fn signals(connect: &ConnectData, balances: Option<&BalanceData>, income: Option<&IncomeData>,
+               today: Day, options: &Options) -> Signals {
+    let transactions = &connect.transactions;
+    let window_start = today.add_days(-(options.window_days - 1));
+    let in_window = |t: &&Transaction| match Day::parse(&t.date) {
+        Some(d) => d >= window_start && d <= today,
+        None => false
+    };
+
+    // Balances
+    let fresh: HashMap<&str, Amount> = balances.map(|b| b.accounts.iter()
+        .map(|a| (a.id.as_str(), a.current_balance))
+        .collect()).unwrap_or_default();
+    let assets: Vec<Account> = connect.accounts.iter()
+        .filter(|a| !balance::is_liability(a))
+        .map(|a| {
+            let mut account = a.clone();
+            if let Some(current) = fresh.get(a.id.as_str()) {
+                account.current_balance = *current;
+            }
+            account
+        })
+        .collect();
+    let mut daily = vec![0.0; options.window_days.max(0) as usize];
+    let mut negative = vec![false; daily.len()];
+    for account in &assets {
+        let history = balance::history(account, transactions, today);
+        for (i, total) in daily.iter_mut().enumerate() {
+            let day = window_start.add_days(i as i64);
+            let b = match history.binary_search_by(|b| b.day.cmp(&day)) {
+                Ok(n) => history[n].balance,
+                Err(_) => history[0].balance
+            };
+            *total += b;
+            negative[i] |= b < 0.0;
+        }
+    }
+
+    // Income and spending
+    let monthly_income = match income {
+        Some(data) => data.income.projected_yearly_income / 12.0,
+        None => {
+            let income_options = income::Options { transfers: options.transfers.clone(), .. options.income.clone() };
+            income::estimate(transactions, today, &income_options).projected_yearly_income / 12.0
+        }
+    };
+    let months = months(transactions, today, options);
+    let monthly = |accounts: Vec<String>| -> HashMap<Day, (Amount, Amount)> {
+        let summary_options = summary::Options {
+            period: Period::Month,
+            accounts: accounts,
+            transfers: options.transfers.clone(),
+            .. summary::Options::default()
+        };
+        summary::summarize(transactions, &summary_options).periods.iter()
+            .map(|p| (p.start, (p.inflow, p.outflow)))
+            .collect()
+    };
+    let all = monthly(Vec::new());
+    let monthly_spend = if months.is_empty() { 0.0 } else {
+        months.iter().map(|m| all.get(m).map(|p| p.1).unwrap_or(0.0)).sum::<Amount>() / months.len() as f64
+    };
+
+    let asset_ids: Vec<String> = assets.iter().map(|a| a.id.clone()).collect();
+    let deposits: Vec<Amount> = if asset_ids.is_empty() { Vec::new() } else {
+        let by_month = monthly(asset_ids);
+        months.iter().map(|m| by_month.get(m).map(|p| p.0).unwrap_or(0.0)).collect()
+    };
+
+    Signals {
+        overdraft_fees: transactions.iter().filter(&in_window).filter(|t| is_fee(t, OVERDRAFT_CATEGORY)).count(),
+        nsf_fees: transactions.iter().filter(&in_window).filter(|t| is_fee(t, NSF_CATEGORY)).count(),
+        negative_balance_days: negative.iter().filter(|&&n| n).count(),
+        average_daily_balance: if daily.is_empty() { 0.0 } else {
+            round(daily.iter().sum::<Amount>() / daily.len() as f64)
+        },
+        monthly_income: round(monthly_income),
+        monthly_spend: round(monthly_spend),
+        income_to_spend: if monthly_spend > 0.0 { Some(ratio(monthly_income / monthly_spend)) } else { None },
+        account_age_days: transactions.iter().filter_map(|t| Day::parse(&t.date)).min()
+            .map(|d| today.days_since(&d)),
+        deposit_volatility: coefficient_of_variation(&deposits)
+    }
+}
+
+/// `σ / μ` of the given values, with two values or more and a positive mean.
+pub fn coefficient_of_variation(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 { return None }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    if mean <= 0.0 { return None }
+    let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / values.len() as f64;
+    Some(ratio(variance.sqrt() / mean))
+}
+
+fn ratio(value: f64) -> f64 {
+    (value * 10000.0).round() / 10000.0
+}
+
+#[cfg(test)]
+mod tests {
+
+    use analytics::risk::*;
+    use api::data::date::Day;
+    use api::product::{ BalanceData, ConnectData, IncomeData };
+    use rustc_serialize::json;
+    use testing::{ account, transaction, TransactionFixture };
+
+    /// A checking account paid 1000 then 1200 on the 1st of each month, spending 900
+    /// on the 15th, with a credit card and one overdraft in March.
+    fn connect() -> ConnectData {
+        let mut transactions = Vec::new();
+        for (month, pay) in [(1, 1000.0), (2, 1200.0), (3, 1000.0), (4, 1200.0)].iter() {
+            transactions.push(transaction("Payroll", -pay, &format!("2016-{:02}-01", month))
+                              .account("checking").categories(&["Transfer", "Payroll"]));
+            transactions.push(transaction("Rent", 900.0, &format!("2016-{:02}-15", month)).account("checking"));
+        }
+        transactions.push(transaction("Fee", 35.0, "2016-03-25").account("checking").categories(&["Bank Fees", "Overdraft"]));
+        transactions.push(transaction("Refund", -35.0, "2016-03-28").account("checking").categories(&["Bank Fees", "Overdraft"]));
+        transactions.push(transaction("NSF", 30.0, "2015-12-20").account("checking").categories(&["Bank Fees", "Insufficient Funds"]));
+        transactions.push(transaction("Books", 100.0, "2016-03-10").account("card").categories(&["Shops"]));
+        ConnectData { accounts: vec![account("checking", "depository", 50.0), account("card", "credit", 100.0)],
+                      transactions: transactions }
+    }
+
+    #[test]
+    fn test_months() {
+        let data = connect();
+        let months: Vec<String> = months(&data.transactions, Day::new(2016, 4, 20).unwrap(), &Options::default())
+            .iter().map(|d| d.to_date()).collect();
+        assert_eq!(months, vec!["2015-12-01", "2016-01-01", "2016-02-01", "2016-03-01"]);
+    }
+
+    #[test]
+    fn test_signals() {
+        let data = connect();
+        let today = Day::new(2016, 4, 20).unwrap();
+        let options = Options { window_days: 30, .. Options::default() };
+        let signals = signals(&data, None, None, today, &options);
+
+        assert_eq!((signals.overdraft_fees, signals.nsf_fees), (1, 0));
+        assert_eq!(signals.account_age_days, Some(122));
+
+        // From March 22nd: -250, then -285 between the fee and its refund, -250 until
+        // the payroll of April 1st, 950 until rent on the 15th and 50 after.
+        assert_eq!(signals.negative_balance_days, 10);
+        assert_eq!(signals.average_daily_balance,
+                   round((-250.0 * 7.0 - 285.0 * 3.0 + 950.0 * 14.0 + 50.0 * 6.0) / 30.0));
+
+        // Deposits of 0, 1000, 1200 and 1035 from December to March.
+        assert_eq!(signals.deposit_volatility, coefficient_of_variation(&[0.0, 1000.0, 1200.0, 1035.0]));
+        assert_eq!(signals.monthly_spend, round((30.0 + 900.0 + 900.0 + 1035.0) / 4.0));
+        assert_eq!(signals.monthly_income, 1100.0);
+        assert_eq!(signals.income_to_spend, Some(ratio(1100.0 / signals.monthly_spend)));
+    }
+
+    #[test]
+    fn test_signals_ignore_transfers() {
+        let mut data = connect();
+        data.accounts.push(account("savings", "depository", 5000.0));
+        for month in 1..5 {
+            data.transactions.push(transaction("Online Transfer to Checking", 400.0, &format!("2016-{:02}-05", month))
+                                   .account("savings"));
+            data.transactions.push(transaction("Online Transfer from Savings", -400.0, &format!("2016-{:02}-06", month))
+                                   .account("checking"));
+        }
+        let today = Day::new(2016, 4, 20).unwrap();
+        let detected = signals(&data, None, None, today, &Options::default());
+        assert_eq!(detected.monthly_income, 1100.0);
+        assert_eq!(detected.monthly_spend, round((30.0 + 900.0 + 900.0 + 1035.0) / 4.0));
+
+        // Pairs that are not detected with the transfer options count as income.
+        let options = Options { transfers: transfers::Options { max_days: 0, .. transfers::Options::default() },
+                                .. Options::default() };
+        assert_eq!(signals(&data, None, None, today, &options).monthly_income, 1500.0);
+    }
+
+    #[test]
+    fn test_signals_with_balance_and_income() {
+        let data = connect();
+        let balances = BalanceData { accounts: vec![account("checking", "depository", 1050.0)] };
+        let income: IncomeData = json::decode(r#"{ "accounts": [], "income": {
+            "income_streams": [], "last_year_income": 0, "last_year_income_before_tax": 0,
+            "projected_yearly_income": 24000, "projected_yearly_income_before_tax": 30000,
+            "max_number_of_overlapping_income_streams": 1, "number_of_income_streams": 1 } }"#).unwrap();
+        let today = Day::new(2016, 4, 20).unwrap();
+        let options = Options { window_days: 30, .. Options::default() };
+        let signals = signals(&data, Some(&balances), Some(&income), today, &options);
+
+        assert_eq!(signals.negative_balance_days, 0);
+        assert_eq!(signals.average_daily_balance, round((750.0 * 7.0 + 715.0 * 3.0 + 1950.0 * 14.0 + 1050.0 * 6.0) / 30.0));
+        assert_eq!(signals.monthly_income, 2000.0);
+    }
+
+    #[test]
+    fn test_coefficient_of_variation() {
+        assert_eq!(coefficient_of_variation(&[100.0, 100.0, 100.0]), Some(0.0));
+        assert_eq!(coefficient_of_variation(&[50.0, 150.0]), Some(0.5));
+        assert_eq!(coefficient_of_variation(&[100.0]), None);
+        assert_eq!(coefficient_of_variation(&[0.0, 0.0]), None);
+    }
+
+}
@@ -0,0 +1,235 @@
+//! Detection of internal transfers, i.e money moved between two accounts of the
+//! same user, such as from checking to savings.
+//!
+//! `Connect` returns such a move as two transactions, one leaving an account and
+//! one entering another, which would otherwise count as both spending and income.
+//! [`detect`][detect] pairs them into [`Transfer`][Transfer]s: the two transactions
+//! must be in different accounts, have opposite amounts and be at most a few days
+//! apart. By default at least one of them must also be in the `Transfer` category
+//! or be named like a transfer, as two unrelated transactions often cancel out by
+//! coincidence. When a transaction could be paired with several others, the most
+//! likely pairs are kept.
+//!
+//! The transactions given to `detect` should all belong to one user, e.g the
+//! transactions of a `ConnectData`.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! # use plaid::api::types::Transaction;
+//! # let transaction = |id: &str, account: &str, amount: f64, date: &str| -> Transaction { json::decode(&format!(r#"
+//! #     {{ "_id": "{}", "_account": "{}", "amount": {}, "date": "{}", "name": "Online Transfer",
+//! #        "pending": false, "type": {{ "primary": "special" }}, "category": ["Transfer"], "category_id": "21000000" }}
+//! # "#, id, account, amount, date)).unwrap() };
+//! use plaid::analytics::transfers::{ self, Options };
+//!
+//! let transactions = vec![transaction("out", "checking", 250.0, "2016-03-01"),
+//!                         transaction("in", "savings", -250.0, "2016-03-02")];
+//!
+//! let found = transfers::detect(&transactions, &Options::default());
+//! assert_eq!(found[0].from_account, "checking");
+//! assert_eq!(found[0].to_account, "savings");
+//! assert_eq!(found[0].amount, 250.0);
+//! # }
+//! ```
+//!
+//! [detect]: ./fn.detect.html
+//! [Transfer]: ./struct.Transfer.html
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use analytics::round;
+use api::data::{ Amount, UID };
+use api::data::date::Day;
+use api::types::Transaction;
+
+/// The top-level category of transfers.
+pub const TRANSFER_CATEGORY: &'static str = "Transfer";
+
+/// Money moved from one of the user's accounts to another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transfer {
+    /// The id of the transaction that left `from_account`.
+    pub from: UID,
+    /// The id of the transaction that entered `to_account`.
+    pub to: UID,
+    /// The account the money left.
+    pub from_account: UID,
+    /// The account the money entered.
+    pub to_account: UID,
+    /// The amount moved, always positive.
+    pub amount: Amount,
+    /// The number of days between the two transactions.
+    pub days: i64,
+    /// How confident the match is, from `0` to `1`.
+    pub confidence: f64
+}
+
+impl Transfer {
+
+    /// Whether the given transaction is one of the two sides of the transfer.
+    pub fn contains(&self, id: &str) -> bool {
+        self.from == id || self.to == id
+    }
+
+}
+
+/// The thresholds of detection.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The most days between the two transactions of a transfer, as transfers
+    /// between institutions can take a few days to settle.
+    pub max_days: i64,
+    /// The largest difference between the two amounts, e.g for a fee taken by
+    /// the sending institution.
+    pub amount_tolerance: Amount,
+    /// Whether at least one of the two transactions must look like a transfer,
+    /// see [`is_transfer_hint`](./fn.is_transfer_hint.html). Without it, any two
+    /// opposite amounts in different accounts are paired, e.g a card payment and a
+    /// refund of the same amount.
+    pub require_hint: bool,
+    /// The minimum confidence of a transfer that is returned.
+    pub min_confidence: f64
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            max_days: 4,
+            amount_tolerance: 0.005,
+            require_hint: true,
+            min_confidence: 0.5
+        }
+    }
+
+}
+
+/// Whether a transaction looks like a transfer: it is in the `Transfer` category,
+/// or its name mentions a transfer.
+pub fn is_transfer_hint(transaction: &Transaction) -> bool {
+    transaction.categories.first().map(|c| c == TRANSFER_CATEGORY).unwrap_or(false) ||
+        transaction.name.as_ref().or(transaction.original_description.as_ref())
+            .map(|n| n.to_lowercase().contains("transfer"))
+            .unwrap_or(false)
+}
+
+/// Detect the internal transfers in the given transactions, ordered by the day
+/// they started. Each transaction is part of at most one transfer, and pending
+/// transactions are ignored.
+///
+/// The confidence of a pair starts at `0.5`, and adds `0.2` for each transaction
+/// that looks like a transfer, and up to `0.1` the closer the two days are.
+pub fn detect(transactions: &[Transaction], options: &Options) -> Vec<Transfer> {
+    let dated: Vec<(Day, &Transaction)> = transactions.iter()
+        .filter(|t| !t.pending && t.amount != 0.0)
+        .filter_map(|t| Day::parse(&t.date).map(|d| (d, t)))
+        .collect();
+
+    let mut candidates = Vec::new();
+    for &(out_day, out) in dated.iter().filter(|&&(_, t)| t.amount > 0.0) {
+        for &(in_day, into) in dated.iter().filter(|&&(_, t)| t.amount < 0.0) {
+            let days = in_day.days_since(&out_day);
+            if into.account_id == out.account_id || days.abs() > options.max_days ||
+                (out.amount + into.amount).abs() > options.amount_tolerance {
+                continue
+            }
+            let hints = is_transfer_hint(out) as u8 + is_transfer_hint(into) as u8;
+            if options.require_hint && hints == 0 { continue }
+            let closeness = 1.0 - days.abs() as f64 / (options.max_days + 1) as f64;
+            let confidence = 0.5 + 0.2 * hints as f64 + 0.1 * closeness;
+            if confidence >= options.min_confidence {
+                candidates.push((confidence, days, out_day, out, into));
+            }
+        }
+    }
+
+    // The most likely pairs first, then the closest, so that a transaction that
+    // could match several others is paired with the best of them.
+    candidates.sort_by(|a, b| {
+        b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal)
+            .then(a.1.abs().cmp(&b.1.abs()))
+            .then(a.2.cmp(&b.2))
+    });
+
+    let mut used: HashSet<&str> = HashSet::new();
+    let mut transfers: Vec<(Day, Transfer)> = Vec::new();
+    for (confidence, days, out_day, out, into) in candidates {
+        if used.contains(out.id.as_str()) || used.contains(into.id.as_str()) { continue }
+        used.insert(&out.id);
+        used.insert(&into.id);
+        transfers.push((out_day, Transfer {
+            from: out.id.clone(),
+            to: into.id.clone(),
+            from_account: out.account_id.clone(),
+            to_account: into.account_id.clone(),
+            amount: out.amount,
+            days: days,
+            confidence: round(confidence)
+        }));
+    }
+    transfers.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.from.cmp(&b.1.from)));
+    transfers.into_iter().map(|(_, t)| t).collect()
+}
+
+/// The ids of both transactions of every transfer, e.g to exclude them from a summary.
+pub fn transaction_ids(transfers: &[Transfer]) -> HashSet<UID> {
+    transfers.iter().flat_map(|t| vec![t.from.clone(), t.to.clone()]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use analytics::transfers::*;
+    use testing::{ transaction, TransactionFixture };
+
+    #[test]
+    fn test_detect() {
+        let transactions = vec![
+            transaction("save", 500.0, "2016-03-01").account("checking").named("Online Transfer to Savings").categories(&["Transfer"]),
+            transaction("saved", -500.0, "2016-03-03").account("savings").named("Deposit"),
+            // Same amount, but in the same account as the outgoing side.
+            transaction("refund", -500.0, "2016-03-01").account("checking").named("Refund"),
+            // Too far apart.
+            transaction("rent", 1200.0, "2016-03-01").account("checking").named("Rent"),
+            transaction("late", -1200.0, "2016-03-10").account("savings").named("Deposit"),
+            transaction("card", 80.0, "2016-03-05").account("checking").named("Credit Card Payment").categories(&["Payment"]),
+            transaction("paid", -80.0, "2016-03-05").account("credit").named("Payment Thank You").categories(&["Payment"])
+        ];
+
+        let options = Options { require_hint: false, .. Options::default() };
+        let found = detect(&transactions, &options);
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].from.as_str(), found[0].to.as_str(), found[0].days), ("save", "saved", 2));
+        assert_eq!(found[0].confidence, 0.76);
+        assert_eq!((found[1].from_account.as_str(), found[1].to_account.as_str()), ("checking", "credit"));
+        assert_eq!(found[1].confidence, 0.6);
+
+        let ids = transaction_ids(&found);
+        assert!(ids.contains("saved") && ids.contains("paid") && !ids.contains("refund"));
+
+        let found = detect(&transactions, &Options::default());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].from, "save");
+    }
+
+    #[test]
+    fn test_detect_pairs_each_transaction_once() {
+        let transactions = vec![
+            transaction("a", 100.0, "2016-03-01").account("checking").named("Transfer").categories(&["Transfer"]),
+            transaction("b", 100.0, "2016-03-04").account("checking").named("Transfer").categories(&["Transfer"]),
+            transaction("x", -100.0, "2016-03-04").account("savings").named("Transfer").categories(&["Transfer"]),
+            transaction("y", -100.0, "2016-03-05").account("savings").named("Transfer").categories(&["Transfer"])
+        ];
+
+        let found = detect(&transactions, &Options::default());
+        let pairs: Vec<(&str, &str)> = found.iter().map(|t| (t.from.as_str(), t.to.as_str())).collect();
+        assert_eq!(pairs, vec![("a", "y"), ("b", "x")]);
+    }
+
+}
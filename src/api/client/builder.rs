@@ -0,0 +1,418 @@
+//! Typed builders for every kind of [`Payload`][Payload].
+//!
+//! Each builder collects the required arguments in its `new` constructor,
+//! optional arguments through chained methods, and validates the combination
+//! when `build` is called with the `Client` to use.
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate hyper;
+//! #
+//! # fn main() {
+//! # let hyper = hyper::Client::new();
+//! use plaid::api::client::Client;
+//! use plaid::api::client::builder::Authenticate;
+//! use plaid::api::client::payload::SelectedDevice;
+//! use plaid::api::mfa::Device;
+//!
+//! let client = Client { endpoint:  "https://tartan.plaid.com",
+//!                       client_id: "testclient",
+//!                       secret:    "testsecret",
+//!                       hyper:     &hyper };
+//!
+//! let payload = Authenticate::new("chase", "username", "password")
+//!     .pin("1234")
+//!     .webhook("https://example.com/webhook")
+//!     .list_mfa_devices()
+//!     .build(client)
+//!     .unwrap();
+//!
+//! // `send_method` can only be used together with `list_mfa_devices`.
+//! assert!(Authenticate::new("chase", "username", "password")
+//!     .send_method(SelectedDevice::Device(Device::Email))
+//!     .build(client)
+//!     .is_err());
+//! # }
+//! ```
+//!
+//! [Payload]: ../payload/enum.Payload.html
+
+use api::types::*;
+use api::client::Client;
+use api::client::payload::{ Payload, AuthenticateOptions, FetchDataOptions, SelectedDevice, validate_webhook };
+use api::error::Error;
+use api::mfa;
+use api::user::User;
+
+fn require(field: &str, value: &str) -> Result<(), Error> {
+    if value.is_empty() {
+        return Err(Error::InvalidOptions(format!("`{}` must not be empty", field)))
+    }
+    Ok(())
+}
+
+macro_rules! credentials_builder {
+    ($name:ident, $variant:ident, $doc:expr) => (
+        #[doc=$doc]
+        #[derive(Debug)]
+        pub struct $name {
+            institution: Institution,
+            username: Username,
+            password: Password,
+            pin: Option<PIN>,
+            options: Option<AuthenticateOptions>
+        }
+
+        impl $name {
+
+            /// Start building the payload with the required credentials.
+            pub fn new<I, U, P>(institution: I, username: U, password: P) -> $name
+                where I: Into<Institution>, U: Into<Username>, P: Into<Password> {
+                $name {
+                    institution: institution.into(),
+                    username: username.into(),
+                    password: password.into(),
+                    pin: None,
+                    options: None
+                }
+            }
+
+            /// Set the user's PIN, required by some institutions.
+            pub fn pin<S: Into<PIN>>(mut self, pin: S) -> $name {
+                self.pin = Some(pin.into());
+                self
+            }
+
+            /// Set the webhook that should be used by Plaid when events are generated.
+            pub fn webhook<S: Into<String>>(self, webhook: S) -> $name {
+                self.map_options(|o| o.webhook(webhook))
+            }
+
+            /// Only authenticate the user, without fetching initial data.
+            pub fn login_only(self) -> $name {
+                self.map_options(|o| o.login_only(true))
+            }
+
+            /// Ask for a list of possible mfa devices to be presented.
+            pub fn list_mfa_devices(self) -> $name {
+                self.map_options(|o| o.list(true))
+            }
+
+            /// Select the device to use for multifactor authentication.
+            /// Requires `list_mfa_devices`.
+            pub fn send_method(self, device: SelectedDevice) -> $name {
+                self.map_options(|o| o.send_method(device))
+            }
+
+            /// Replace every option with the given `AuthenticateOptions`.
+            pub fn options(mut self, options: AuthenticateOptions) -> $name {
+                self.options = Some(options);
+                self
+            }
+
+            fn map_options<F>(mut self, f: F) -> $name
+                where F: FnOnce(AuthenticateOptions) -> AuthenticateOptions {
+                let options = self.options.take().unwrap_or_else(AuthenticateOptions::default);
+                self.options = Some(f(options));
+                self
+            }
+
+            /// Validate the payload and build it for the given `Client`.
+            pub fn build<'a>(self, client: Client<'a>) -> Result<Payload<'a>, Error> {
+                try!(require("institution", &self.institution));
+                try!(require("username", &self.username));
+                try!(require("password", self.password.expose()));
+                if let Some(ref pin) = self.pin { try!(require("pin", pin.expose())); }
+                if let Some(ref options) = self.options { try!(options.validate()); }
+                Ok(Payload::$variant(client, self.institution, self.username, self.password, self.pin, self.options))
+            }
+
+        }
+    )
+}
+
+credentials_builder!(Authenticate, Authenticate, "Builds a `Payload::Authenticate`.");
+credentials_builder!(Reauthenticate, Reauthenticate, "Builds a `Payload::Reauthenticate`.");
+
+/// Builds a `Payload::Upgrade`.
+#[derive(Debug)]
+pub struct Upgrade {
+    user: User,
+    options: AuthenticateOptions
+}
+
+impl Upgrade {
+
+    /// Start building the payload for the given `User`.
+    pub fn new(user: User) -> Upgrade {
+        Upgrade { user: user, options: AuthenticateOptions::default() }
+    }
+
+    /// Set the webhook that should be used by Plaid when events are generated.
+    pub fn webhook<S: Into<String>>(mut self, webhook: S) -> Upgrade {
+        self.options = self.options.webhook(webhook);
+        self
+    }
+
+    /// Only upgrade the user, without fetching initial data.
+    pub fn login_only(mut self) -> Upgrade {
+        self.options = self.options.login_only(true);
+        self
+    }
+
+    /// Validate the payload and build it for the given `Client`.
+    pub fn build<'a>(self, client: Client<'a>) -> Result<Payload<'a>, Error> {
+        try!(require("access_token", self.user.access_token.expose()));
+        try!(self.options.validate());
+        Ok(Payload::Upgrade(client, self.user, Some(self.options)))
+    }
+
+}
+
+/// Builds a `Payload::RemoveUser`.
+#[derive(Debug)]
+pub struct RemoveUser {
+    user: User
+}
+
+impl RemoveUser {
+
+    /// Start building the payload for the given `User`.
+    pub fn new(user: User) -> RemoveUser {
+        RemoveUser { user: user }
+    }
+
+    /// Validate the payload and build it for the given `Client`.
+    pub fn build<'a>(self, client: Client<'a>) -> Result<Payload<'a>, Error> {
+        try!(require("access_token", self.user.access_token.expose()));
+        Ok(Payload::RemoveUser(client, self.user))
+    }
+
+}
+
+/// Builds a `Payload::StepMFA`.
+#[derive(Debug)]
+pub struct StepMFA {
+    user: User,
+    response: mfa::Response
+}
+
+impl StepMFA {
+
+    /// Start building the payload for the given `User` and challenge response.
+    pub fn new(user: User, response: mfa::Response) -> StepMFA {
+        StepMFA { user: user, response: response }
+    }
+
+    /// Validate the payload and build it for the given `Client`.
+    pub fn build<'a>(self, client: Client<'a>) -> Result<Payload<'a>, Error> {
+        try!(require("access_token", self.user.access_token.expose()));
+        match self.response {
+            mfa::Response::Code(ref code) => try!(require("code", code)),
+            mfa::Response::Questions(ref answers) |
+            mfa::Response::Selections(ref answers) => {
+                if answers.is_empty() {
+                    return Err(Error::InvalidOptions("At least one answer is required".to_string()))
+                }
+            }
+        }
+        Ok(Payload::StepMFA(client, self.user, self.response))
+    }
+
+}
+
+/// Builds a `Payload::FetchData`.
+#[derive(Debug)]
+pub struct FetchData {
+    user: User,
+    options: FetchDataOptions
+}
+
+impl FetchData {
+
+    /// Start building the payload for the given `User`.
+    pub fn new(user: User) -> FetchData {
+        FetchData { user: user, options: FetchDataOptions::default() }
+    }
+
+    /// Filter out transactions that have occured before the given `Date`.
+    pub fn start_date<S: Into<Date>>(mut self, date: S) -> FetchData {
+        self.options = self.options.start_date(date);
+        self
+    }
+
+    /// Filter out transactions that have occured after the given `Date`.
+    pub fn end_date<S: Into<Date>>(mut self, date: S) -> FetchData {
+        self.options = self.options.end_date(date);
+        self
+    }
+
+    /// Only include transactions for the account with the given id.
+    pub fn account<S: Into<UID>>(mut self, account_id: S) -> FetchData {
+        self.options = self.options.account(account_id);
+        self
+    }
+
+    /// Include or exclude pending transactions.
+    pub fn pending(mut self, pending: bool) -> FetchData {
+        self.options = self.options.pending(pending);
+        self
+    }
+
+    /// Only include transactions on or after the given `Date`.
+    pub fn gte<S: Into<Date>>(mut self, date: S) -> FetchData {
+        self.options = self.options.gte(date);
+        self
+    }
+
+    /// Only include transactions on or before the given `Date`.
+    pub fn lte<S: Into<Date>>(mut self, date: S) -> FetchData {
+        self.options = self.options.lte(date);
+        self
+    }
+
+    /// Replace every option with the given `FetchDataOptions`.
+    pub fn options(mut self, options: FetchDataOptions) -> FetchData {
+        self.options = options;
+        self
+    }
+
+    /// Validate the payload and build it for the given `Client`.
+    pub fn build<'a>(self, client: Client<'a>) -> Result<Payload<'a>, Error> {
+        try!(require("access_token", self.user.access_token.expose()));
+        try!(self.options.validate());
+        Ok(Payload::FetchData(client, self.user, Some(self.options)))
+    }
+
+}
+
+/// Builds a `Payload::UpdateWebhook`.
+#[derive(Debug)]
+pub struct UpdateWebhook {
+    user: User,
+    webhook: String
+}
+
+impl UpdateWebhook {
+
+    /// Start building the payload for the given `User` and new webhook.
+    pub fn new<S: Into<String>>(user: User, webhook: S) -> UpdateWebhook {
+        UpdateWebhook { user: user, webhook: webhook.into() }
+    }
+
+    /// Validate the payload and build it for the given `Client`.
+    pub fn build<'a>(self, client: Client<'a>) -> Result<Payload<'a>, Error> {
+        try!(require("access_token", self.user.access_token.expose()));
+        try!(validate_webhook(&self.webhook));
+        Ok(Payload::UpdateWebhook(client, self.user, self.webhook))
+    }
+
+}
+
+/// Builds a `Payload::PatchCredentials`.
+#[derive(Debug)]
+pub struct PatchCredentials {
+    user: User,
+    username: Username,
+    password: Password,
+    pin: Option<PIN>
+}
+
+impl PatchCredentials {
+
+    /// Start building the payload for the given `User` and new credentials.
+    pub fn new<U, P>(user: User, username: U, password: P) -> PatchCredentials
+        where U: Into<Username>, P: Into<Password> {
+        PatchCredentials { user: user, username: username.into(), password: password.into(), pin: None }
+    }
+
+    /// Set the user's PIN, required by some institutions.
+    pub fn pin<S: Into<PIN>>(mut self, pin: S) -> PatchCredentials {
+        self.pin = Some(pin.into());
+        self
+    }
+
+    /// Validate the payload and build it for the given `Client`.
+    pub fn build<'a>(self, client: Client<'a>) -> Result<Payload<'a>, Error> {
+        try!(require("access_token", self.user.access_token.expose()));
+        try!(require("username", &self.username));
+        try!(require("password", self.password.expose()));
+        if let Some(ref pin) = self.pin { try!(require("pin", pin.expose())); }
+        Ok(Payload::PatchCredentials(client, self.user, self.username, self.password, self.pin))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use api::client::Client;
+    use api::client::builder::*;
+    use api::mfa;
+    use api::user::User;
+    use rustc_serialize::json;
+    use hyper as h;
+
+    fn user() -> User {
+        User::new("accesstoken123")
+    }
+
+    #[test]
+    fn test_authenticate_builder() {
+        let hyper = h::Client::new();
+        let client = Client { endpoint: "https://tartan.plaid.com",
+                              client_id: "testclientid",
+                              secret: "testsecret",
+                              hyper: &hyper };
+
+        let payload = Authenticate::new("testinst", "username", "password")
+            .pin("PINCODE")
+            .list_mfa_devices()
+            .build(client)
+            .unwrap();
+
+        assert_eq!(json::encode(&payload).unwrap(),
+                   r###"{"client_id":"testclientid","secret":"testsecret","username":"username","password":"password","type":"testinst","options":{"webhook":null,"login_only":null,"list":true,"send_method":null},"pin":"PINCODE"}"###);
+
+        assert!(Reauthenticate::new("testinst", "", "password").build(client).is_err());
+        assert!(Authenticate::new("testinst", "username", "password").webhook("nope").build(client).is_err());
+    }
+
+    #[test]
+    fn test_other_builders_validate() {
+        let hyper = h::Client::new();
+        let client = Client { endpoint: "https://tartan.plaid.com",
+                              client_id: "testclientid",
+                              secret: "testsecret",
+                              hyper: &hyper };
+
+        assert!(Upgrade::new(user()).webhook("https://example.com").build(client).is_ok());
+        assert!(RemoveUser::new(User::new("")).build(client).is_err());
+        assert!(StepMFA::new(user(), mfa::Response::Code("1234".to_string())).build(client).is_ok());
+        assert!(StepMFA::new(user(), mfa::Response::Questions(vec![])).build(client).is_err());
+        assert!(FetchData::new(user()).gte("2016-01-01").lte("2016-02-01").build(client).is_ok());
+        assert!(FetchData::new(user()).gte("2016-03-01").lte("2016-02-01").build(client).is_err());
+        assert!(UpdateWebhook::new(user(), "https://example.com/webhook").build(client).is_ok());
+        assert!(UpdateWebhook::new(user(), "").build(client).is_err());
+        assert!(PatchCredentials::new(user(), "username", "password").pin("1234").build(client).is_ok());
+        assert!(PatchCredentials::new(user(), "username", "").build(client).is_err());
+    }
+
+    #[test]
+    fn test_fetch_data_dates() {
+        let hyper = h::Client::new();
+        let client = Client { endpoint: "https://tartan.plaid.com",
+                              client_id: "testclientid",
+                              secret: "testsecret",
+                              hyper: &hyper };
+
+        let payload = FetchData::new(user()).start_date("2016-01-01").end_date("2016-02-01").build(client).unwrap();
+        let encoded = json::encode(&payload).unwrap();
+        assert!(encoded.contains(r#""start_date":"2016-01-01","end_date":"2016-02-01""#));
+
+        assert!(FetchData::new(user()).start_date("2016-03-01").end_date("2016-02-01").build(client).is_err());
+        assert!(FetchData::new(user()).start_date("2016-02-30").build(client).is_err());
+        assert!(FetchData::new(user()).end_date("2016-02-01").gte("2016-03-01").build(client).is_ok());
+    }
+
+}
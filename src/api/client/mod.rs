@@ -1,13 +1,15 @@
 //! Data structures and methods that interact with Plaid via HTTP.
 
-use std::io::Read;
+use std::fmt;
+use std::io::BufReader;
 
 use api::user::User;
 use api::product::*;
 use api::error::Error;
 use api::mfa;
+use api::client::payload::FetchDataOptions;
 
-use rustc_serialize::json;
+use rustc_serialize::json::{ self, Json };
 
 use hyper as h;
 use hyper::header::{ContentType, Accept, qitem};
@@ -20,6 +22,14 @@ pub mod payload;
 pub use self::response::Response;
 pub mod response;
 
+pub use self::pages::Pages;
+pub mod pages;
+
+pub mod builder;
+
+pub use self::stream::TransactionStream;
+pub mod stream;
+
 /// # Client
 ///
 /// Represents a Plaid API consumer. Encapsulates the `endpoint`,
@@ -41,59 +51,192 @@ pub struct Client<'a> {
     pub hyper: &'a h::Client
 }
 
+/// The `secret` is masked, and `hyper` is not printed.
+impl<'a> fmt::Debug for Client<'a> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("endpoint", &self.endpoint)
+            .field("client_id", &self.client_id)
+            .field("secret", &"****")
+            .finish()
+    }
+
+}
+
 impl<'a> Client<'a> {
 
     /// Make a request to the given [Product](../product/struct.Product.html), using a
     /// [Payload](./payload/struct.Payload.html) describing the intention of the operation.
+    ///
+    /// The response body is parsed once, and both the `User` and the product data
+    /// are decoded from that document. Use `stream_transactions` to avoid holding
+    /// a large transaction history in memory.
     pub fn request<P: Product>(&self, product: P, payload: Payload) -> Result<Response<P>, Error> {
+        let (status, body) = try!(self.receive(product, &payload));
+        Client::respond(status, payload, body, false)
+    }
 
-        let body = try!(json::encode(&payload));
-        let mut body = body.into_bytes();
-        let body_capacity = body.len();
-        let endpoint = payload.endpoint(&self, product);
-        let method = payload.method();
-
-        let mut res: h::client::Response = try!(
-            self.hyper
-                .request(method, endpoint.as_ref() as &str)
-                .header(ContentType(Mime(TopLevel::Application, SubLevel::Json, vec![])))
-                .header(Accept(vec![qitem(Mime(TopLevel::Application, SubLevel::Json,
-                                               vec![(Attr::Charset, Value::Utf8)]))]))
-                .body(h::client::Body::BufBody(&mut body, body_capacity))
-                .send());
+    /// The same as `request`, but the decoded values also keep the fields of the response
+    /// that are not decoded, see [`raw`](../data/raw/index.html). Useful for fields that
+    /// are not yet supported by this library.
+    ///
+    /// ```
+    /// # #[macro_use(http_stub)] extern crate plaid;
+    /// # #[macro_use] extern crate yup_hyper_mock as hyper_mock;
+    /// # extern crate hyper;
+    /// #
+    /// # fn main() {
+    /// #
+    /// # http_stub!(StubPolicy, 200, include_str!("fixtures/post_connect_success.json"));
+    /// #
+    /// # let hyper = hyper::Client::with_connector(StubPolicy::default());
+    /// #
+    /// # use plaid::api::client::{ Client, Payload, Response };
+    /// # use plaid::api::product;
+    /// # use plaid::api::user::User;
+    /// #
+    /// # let client = Client { endpoint:  "https://tartan.plaid.com",
+    /// #                       client_id: "testclient",
+    /// #                       secret:    "testsecret",
+    /// #                       hyper:     &hyper };
+    /// #
+    /// let user = User::new("testaccesstoken");
+    /// let response = client.request_raw(
+    ///     product::Connect,
+    ///     Payload::FetchData(client, user, None)).unwrap();
+    ///
+    /// match response {
+    ///     Response::ProductData(ref data) => {
+    ///         let extra = data.accounts[0].extra();
+    ///         assert_eq!(extra.get("_user").and_then(|u| u.as_string()), Some("testuserid"));
+    ///         assert!(data.transactions[0].raw().unwrap().get("_account").is_some());
+    ///     },
+    ///     _ => panic!("Expected product data")
+    /// }
+    /// # }
+    /// ```
+    pub fn request_raw<P: Product>(&self, product: P, payload: Payload) -> Result<Response<P>, Error> {
+        let (status, body) = try!(self.receive(product, &payload));
+        Client::respond(status, payload, body, true)
+    }
 
-        let mut buffer = String::new();
+    fn receive<P: Product>(&self, product: P, payload: &Payload) -> Result<(StatusCode, Json), Error> {
+        let res = try!(self.send(product, payload));
         match (res.status, payload) {
+            (StatusCode::Created, &Payload::Authenticate( .. )) |
+            (StatusCode::Created, &Payload::Reauthenticate( .. )) |
+            (StatusCode::Created, &Payload::PatchCredentials( .. )) |
+            (StatusCode::Ok, &Payload::Authenticate( .. )) |
+            (StatusCode::Ok, &Payload::StepMFA( .. )) |
+            (StatusCode::Ok, &Payload::PatchCredentials( .. )) |
+            (StatusCode::Ok, &Payload::FetchData( .. )) |
+            (StatusCode::Ok, &Payload::UpdateWebhook( .. )) => {
+                let status = res.status;
+                Ok((status, try!(stream::parse(BufReader::new(res)))))
+            },
+            // By default, we assume a bad response
+            (s, _) => Err(Error::UnsuccessfulResponse(s))
+        }
+    }
+
+    fn respond<P: Product>(status: StatusCode, payload: Payload, mut body: Json, raw: bool) -> Result<Response<P>, Error> {
+        let decode = |body| if raw { stream::decode_raw(body) } else { stream::decode(body) };
+        match (status, payload) {
             // A `201` indicates that the `User` has been created but
             // is missing the multi-factor authentication step.
             (StatusCode::Created, Payload::Authenticate( .. )) |
-            (StatusCode::Created, Payload::Reauthenticate( .. )) => {
-                try!(res.read_to_string(&mut buffer));
-                let user: User = try!(json::decode(&mut buffer));
-                let mfa_challenge: mfa::Challenge = try!(json::decode(&mut buffer));
+            (StatusCode::Created, Payload::Reauthenticate( .. )) |
+            (StatusCode::Created, Payload::PatchCredentials( .. )) => {
+                let user = try!(stream::take_user(&mut body));
+                let mfa_challenge: mfa::Challenge = try!(stream::decode(body));
                 Ok(Response::MFA(user, mfa_challenge))
             },
             // A `200` response for authentication is accompanied with the
             // endpoint data that was requested for.
             (StatusCode::Ok, Payload::Authenticate( .. )) |
-            (StatusCode::Ok, Payload::StepMFA( .. )) => {
-                try!(res.read_to_string(&mut buffer));
-                let mut buffer_copy = buffer.clone();
-                let user: User = try!(json::decode(&mut buffer));
-                let data: P::Data = try!(json::decode(&mut buffer_copy));
+            (StatusCode::Ok, Payload::StepMFA( .. )) |
+            (StatusCode::Ok, Payload::PatchCredentials( .. )) => {
+                let user = try!(stream::take_user(&mut body));
+                let data: P::Data = try!(decode(body));
                 Ok(Response::Authenticated(user, data))
             },
             // A `200` response for data requests
             (StatusCode::Ok, Payload::FetchData( .. )) => {
-                try!(res.read_to_string(&mut buffer));
-                let mut buffer_copy = buffer.clone();
-                let data: P::Data = try!(json::decode(&mut buffer_copy));
+                let data: P::Data = try!(decode(body));
                 Ok(Response::ProductData(data))
             },
-            // By default, we assume a bad response
-            (ref s, _) => return Err(Error::UnsuccessfulResponse(*s))
+            // A `200` response for a webhook update only includes the user.
+            (StatusCode::Ok, Payload::UpdateWebhook( .. )) => {
+                let user = try!(stream::take_user(&mut body));
+                Ok(Response::WebhookUpdated(user))
+            },
+            (s, _) => Err(Error::UnsuccessfulResponse(s))
         }
+    }
+
+    /// Fetch the `Connect` transactions of the given `User`, yielding each `Transaction`
+    /// as soon as it has been read from the response, rather than waiting for the
+    /// entire response to be decoded.
+    ///
+    /// ```
+    /// # #[macro_use(http_stub)] extern crate plaid;
+    /// # #[macro_use] extern crate yup_hyper_mock as hyper_mock;
+    /// # extern crate hyper;
+    /// #
+    /// # fn main() {
+    /// #
+    /// # http_stub!(StubPolicy, 200, include_str!("fixtures/post_connect_success.json"));
+    /// #
+    /// # let hyper = hyper::Client::with_connector(StubPolicy::default());
+    /// #
+    /// # use plaid::api::client::Client;
+    /// # use plaid::api::user::User;
+    /// #
+    /// # let client = Client { endpoint:  "https://tartan.plaid.com",
+    /// #                       client_id: "testclient",
+    /// #                       secret:    "testsecret",
+    /// #                       hyper:     &hyper };
+    /// #
+    /// let user = User::new("testaccesstoken");
+    /// for transaction in client.stream_transactions(user, None).unwrap() {
+    ///     let transaction = transaction.unwrap();
+    ///     println!("{} {}", transaction.date, transaction.amount);
+    /// }
+    /// # }
+    /// ```
+    pub fn stream_transactions(&self, user: User, options: Option<FetchDataOptions>)
+        -> Result<TransactionStream<BufReader<h::client::Response>>, Error> {
+        let payload = Payload::FetchData(*self, user, options);
+        let res = try!(self.send(Connect, &payload));
+        match res.status {
+            StatusCode::Ok => Ok(TransactionStream::new(BufReader::new(res))),
+            s => Err(Error::UnsuccessfulResponse(s))
+        }
+    }
+
+    fn send<P: Product>(&self, product: P, payload: &Payload) -> Result<h::client::Response, Error> {
+        let body = try!(json::encode(payload));
+        let mut body = body.into_bytes();
+        let body_capacity = body.len();
+        let endpoint = payload.endpoint(&self, product);
+        let method = payload.method();
+
+        Ok(try!(
+            self.hyper
+                .request(method, endpoint.as_ref() as &str)
+                .header(ContentType(Mime(TopLevel::Application, SubLevel::Json, vec![])))
+                .header(Accept(vec![qitem(Mime(TopLevel::Application, SubLevel::Json,
+                                               vec![(Attr::Charset, Value::Utf8)]))]))
+                .body(h::client::Body::BufBody(&mut body, body_capacity))
+                .send()))
+    }
 
+    /// Iterate over the `Connect` data of the given `User` in windows of days,
+    /// rather than retrieving the entire transaction history in one response.
+    /// See [Pages](./pages/struct.Pages.html) for how the windows are chosen and sized.
+    pub fn pages(&self, user: User, options: FetchDataOptions) -> Pages<'a> {
+        Pages::new(*self, user, options)
     }
 
 }
@@ -0,0 +1,210 @@
+//! Page-by-page retrieval of large transaction histories.
+//!
+//! ## Example
+//!
+//! ```
+//! # #[macro_use(http_stub)] extern crate plaid;
+//! # #[macro_use] extern crate yup_hyper_mock as hyper_mock;
+//! # extern crate hyper;
+//! #
+//! # fn main() {
+//! #
+//! # http_stub!(StubPolicy, 200, include_str!("fixtures/post_connect_success.json"));
+//! #
+//! # let hyper = hyper::Client::with_connector(StubPolicy::default());
+//! #
+//! use plaid::api::client::Client;
+//! use plaid::api::client::payload::FetchDataOptions;
+//! use plaid::api::user::User;
+//!
+//! let client = Client { endpoint:  "https://tartan.plaid.com",
+//!                       client_id: "testclient",
+//!                       secret:    "testsecret",
+//!                       hyper:     &hyper };
+//!
+//! let user = User::new("testaccesstoken");
+//! let options = FetchDataOptions::default().gte("2016-05-01").lte("2016-06-30");
+//!
+//! let mut pages = 0;
+//! for page in client.pages(user, options).page_days(30) {
+//!     let data = page.unwrap();
+//!     println!("{} transactions", data.transactions.len());
+//!     pages += 1;
+//! }
+//! assert_eq!(pages, 3);
+//! # }
+//! ```
+
+use api::client::{ Client, Payload, Response };
+use api::client::payload::FetchDataOptions;
+use api::data::date::Day;
+use api::error::Error;
+use api::product::{ Connect, ConnectData };
+use api::user::User;
+
+/// The default number of days covered by each page.
+pub const DEFAULT_PAGE_DAYS: u32 = 30;
+
+/// The default number of consecutive pages without transactions after which
+/// iteration stops, when no `gte` bound is given.
+pub const DEFAULT_MAX_EMPTY_PAGES: u32 = 6;
+
+/// # Pages
+/// An iterator over a `User`'s `Connect` data, one window of days at a time,
+/// starting with the most recent. See `Client::pages`.
+///
+/// Each page is fetched with the `gte` and `lte` options narrowed to its window,
+/// and every other option is kept as given. Iteration stops once the `gte` bound
+/// has been reached, or, when `gte` is not set, after `max_empty_pages` consecutive
+/// pages without any transactions. Pages without transactions are still returned,
+/// so gaps in a history do not hide the transactions before them.
+pub struct Pages<'a> {
+    client: Client<'a>,
+    user: User,
+    options: FetchDataOptions,
+    page_days: i64,
+    lower: Option<Day>,
+    next_end: Option<Day>,
+    max_empty: u32,
+    empty: u32,
+    error: Option<Error>
+}
+
+impl<'a> Pages<'a> {
+
+    /// Create an iterator over the pages that are described by `options`, each
+    /// covering `DEFAULT_PAGE_DAYS` days.
+    pub fn new(client: Client<'a>, user: User, options: FetchDataOptions) -> Pages<'a> {
+        let mut error = None;
+        let mut parse = |date: Option<&String>| match date {
+            Some(d) => Day::parse(d).or_else(|| {
+                error = Some(Error::InvalidOptions(format!("Invalid date: {}", d)));
+                None
+            }),
+            None => None
+        };
+
+        let lower = parse(options.get_gte());
+        let upper = parse(options.get_lte()).unwrap_or_else(Day::today);
+
+        Pages {
+            client: client,
+            user: user,
+            options: options,
+            page_days: DEFAULT_PAGE_DAYS as i64,
+            lower: lower,
+            next_end: Some(upper),
+            max_empty: DEFAULT_MAX_EMPTY_PAGES,
+            empty: 0,
+            error: error
+        }
+    }
+
+    /// Set the number of days covered by each page, defaults to `DEFAULT_PAGE_DAYS`.
+    pub fn page_days(mut self, days: u32) -> Pages<'a> {
+        self.page_days = ::std::cmp::max(days, 1) as i64;
+        self
+    }
+
+    /// Set the number of consecutive pages without transactions after which iteration
+    /// stops when `gte` is not set, defaults to `DEFAULT_MAX_EMPTY_PAGES`.
+    pub fn max_empty_pages(mut self, pages: u32) -> Pages<'a> {
+        self.max_empty = ::std::cmp::max(pages, 1);
+        self
+    }
+
+}
+
+impl<'a> Iterator for Pages<'a> {
+
+    type Item = Result<ConnectData, Error>;
+
+    fn next(&mut self) -> Option<Result<ConnectData, Error>> {
+        if let Some(err) = self.error.take() {
+            self.next_end = None;
+            return Some(Err(err))
+        }
+
+        let end = match self.next_end {
+            Some(end) if self.lower.map_or(true, |l| end >= l) => end,
+            _ => return None
+        };
+
+        let mut start = end.add_days(1 - self.page_days);
+        if let Some(lower) = self.lower {
+            if start < lower { start = lower }
+        }
+        self.next_end = Some(start.add_days(-1));
+
+        let options = self.options.clone().gte(start.to_date()).lte(end.to_date());
+        let user = self.user.clone();
+        match self.client.request(Connect, Payload::FetchData(self.client, user, Some(options))) {
+            Ok(Response::ProductData(data)) => {
+                if !data.transactions.is_empty() {
+                    self.empty = 0;
+                } else if self.lower.is_none() {
+                    self.empty += 1;
+                    if self.empty >= self.max_empty {
+                        self.next_end = None;
+                    }
+                }
+                Some(Ok(data))
+            },
+            Ok(_) => {
+                self.next_end = None;
+                Some(Err(Error::InternalError))
+            },
+            Err(err) => {
+                self.next_end = None;
+                Some(Err(err))
+            }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hyper;
+    use api::client::Client;
+    use api::client::payload::FetchDataOptions;
+    use api::user::User;
+
+    fn reply(transactions: bool) -> String {
+        let body = if transactions {
+            include_str!("../../../fixtures/post_connect_success.json").to_string()
+        } else {
+            r#"{ "access_token": "test", "accounts": [], "transactions": [] }"#.to_string()
+        };
+        format!("HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=utf-8\r\n\r\n{}", body)
+    }
+
+    mock_connector_in_order!(GapPolicy {
+        reply(true)
+        reply(false)
+        reply(false)
+        reply(true)
+        reply(false)
+        reply(false)
+        reply(false)
+    });
+
+    #[test]
+    fn test_pages_across_gap() {
+        let hyper = hyper::Client::with_connector(GapPolicy::default());
+        let client = Client { endpoint: "https://tartan.plaid.com",
+                              client_id: "testclient",
+                              secret: "testsecret",
+                              hyper: &hyper };
+
+        let options = FetchDataOptions::default().lte("2016-06-30");
+        let counts: Vec<usize> = client.pages(User::new("test"), options)
+            .max_empty_pages(3)
+            .map(|page| page.unwrap().transactions.len())
+            .collect();
+
+        assert_eq!(counts, vec![2, 0, 0, 2, 0, 0, 0]);
+    }
+
+}
@@ -1,10 +1,12 @@
 //! Payload
 
 use api::types::*;
+use api::data::date::Day;
 use api::product::*;
 use api::user::User;
 use api::client::Client;
 use api::mfa;
+use api::error::Error;
 
 use rustc_serialize::{Encodable, Encoder};
 
@@ -24,7 +26,12 @@ pub enum Payload<'a> {
     /// Send multifactor authentication response.
     StepMFA(Client<'a>, User, mfa::Response),
     /// Retrieve data from the product.
-    FetchData(Client<'a>, User, Option<FetchDataOptions>)
+    FetchData(Client<'a>, User, Option<FetchDataOptions>),
+    /// Change the webhook that Plaid sends events to for an existing user.
+    UpdateWebhook(Client<'a>, User, String),
+    /// Update the credentials of an existing user, e.g after a password change.
+    /// This may result in a new multifactor authentication challenge.
+    PatchCredentials(Client<'a>, User, Username, Password, Option<PIN>)
 }
 
 impl<'a> Payload<'a> {
@@ -43,6 +50,8 @@ impl<'a> Payload<'a> {
             Payload::RemoveUser(..) => Method::Delete,
             Payload::StepMFA(..) => Method::Patch,
             Payload::FetchData(..) => Method::Get,
+            Payload::UpdateWebhook(..) => Method::Patch,
+            Payload::PatchCredentials(..) => Method::Patch,
         }
     }
 
@@ -86,11 +95,34 @@ impl<'a> Encodable for Payload<'a> {
                 })
             },
             Payload::FetchData(ref client, ref user, Some(ref options)) => {
-                encoder.emit_struct("Request", 1, |encoder| {
+                encoder.emit_struct("Request", 4, |encoder| {
                     try!(encoder.emit_struct_field("client_id", 0, |e| client.client_id.encode(e)));
                     try!(encoder.emit_struct_field("secret", 1, |e| client.secret.encode(e)));
                     try!(encoder.emit_struct_field("access_token", 2, |e| user.access_token.encode(e)));
-                    try!(encoder.emit_struct_field("options", 0, |e| options.encode(e)));
+                    try!(encoder.emit_struct_field("options", 3, |e| options.encode(e)));
+                    Ok(())
+                })
+            },
+            Payload::UpdateWebhook(ref client, ref user, ref webhook) => {
+                encoder.emit_struct("Request", 4, |encoder| {
+                    try!(encoder.emit_struct_field("client_id", 0, |e| client.client_id.encode(e)));
+                    try!(encoder.emit_struct_field("secret", 1, |e| client.secret.encode(e)));
+                    try!(encoder.emit_struct_field("access_token", 2, |e| user.access_token.encode(e)));
+                    try!(encoder.emit_struct_field("options", 3, |e| {
+                        e.emit_struct("options", 1, |e| e.emit_struct_field("webhook", 0, |e| webhook.encode(e)))
+                    }));
+                    Ok(())
+                })
+            },
+            Payload::PatchCredentials(ref client, ref user, ref username, ref password, ref pin) => {
+                let fields = if pin.is_some() { 6 } else { 5 };
+                encoder.emit_struct("Request", fields, |encoder| {
+                    try!(encoder.emit_struct_field("client_id", 0, |e| client.client_id.encode(e)));
+                    try!(encoder.emit_struct_field("secret", 1, |e| client.secret.encode(e)));
+                    try!(encoder.emit_struct_field("access_token", 2, |e| user.access_token.encode(e)));
+                    try!(encoder.emit_struct_field("username", 3, |e| username.encode(e)));
+                    try!(encoder.emit_struct_field("password", 4, |e| password.encode(e)));
+                    if pin.is_some() { try!(encoder.emit_struct_field("pin", 5, |e| pin.encode(e))); }
                     Ok(())
                 })
             },
@@ -150,15 +182,83 @@ impl AuthenticateOptions {
         }
     }
 
+    /// Set the webhook that should be used by Plaid when events are generated.
+    pub fn webhook<S: Into<String>>(mut self, webhook: S) -> AuthenticateOptions {
+        self.webhook = Some(webhook.into());
+        self
+    }
+
+    /// Set whether or not initial data should be fetched.
+    pub fn login_only(mut self, login_only: bool) -> AuthenticateOptions {
+        self.login_only = Some(login_only);
+        self
+    }
+
+    /// Set whether or not a list of possible mfa devices should be presented.
+    pub fn list(mut self, list: bool) -> AuthenticateOptions {
+        self.list = Some(list);
+        self
+    }
+
+    /// Select the device to use for multifactor authentication.
+    pub fn send_method(mut self, device: SelectedDevice) -> AuthenticateOptions {
+        self.send_method = Some(device);
+        self
+    }
+
+    /// Check that the options that have been set can be used together.
+    ///
+    /// + `webhook` must be an `http` or `https` URL.
+    /// + `send_method` requires `list` to be `true`.
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(ref webhook) = self.webhook {
+            try!(validate_webhook(webhook));
+        }
+        if self.send_method.is_some() && self.list != Some(true) {
+            return Err(Error::InvalidOptions("`send_method` requires `list` to be set".to_string()))
+        }
+        Ok(())
+    }
+
+}
+
+/// Check that the given webhook is an `http` or `https` URL.
+pub fn validate_webhook(webhook: &str) -> Result<(), Error> {
+    let rest = if webhook.starts_with("https://") { &webhook[8..] }
+               else if webhook.starts_with("http://") { &webhook[7..] }
+               else { "" };
+    if rest.is_empty() || rest.starts_with('/') {
+        return Err(Error::InvalidOptions(format!("Invalid webhook: {}", webhook)))
+    }
+    Ok(())
 }
 
 /// Options that can be passed along to any `Payload::FetchData` request.
-#[derive(Debug, RustcEncodable)]
+///
+/// Every option is unset by default, use the builder methods to set them:
+///
+/// ```
+/// # use plaid::api::client::payload::FetchDataOptions;
+/// let options = FetchDataOptions::default()
+///     .account("testaccountid")
+///     .pending(false)
+///     .gte("2016-01-01")
+///     .lte("2016-06-30");
+/// ```
+#[derive(Debug, Clone)]
 pub struct FetchDataOptions {
     /// This will filter out transactions that have occured before the given `Date`
     start_date: Option<Date>,
     /// This will filter out transactions that have occured after the given `Date`
-    end_date: Option<Date>
+    end_date: Option<Date>,
+    /// Only include transactions for the account with the given id.
+    account: Option<UID>,
+    /// Whether or not pending transactions should be included.
+    pending: Option<bool>,
+    /// Only include transactions on or after the given `Date`.
+    gte: Option<Date>,
+    /// Only include transactions on or before the given `Date`.
+    lte: Option<Date>
 }
 
 impl FetchDataOptions {
@@ -167,8 +267,105 @@ impl FetchDataOptions {
     pub fn default() -> FetchDataOptions {
         FetchDataOptions {
             start_date: None,
-            end_date: None
+            end_date: None,
+            account: None,
+            pending: None,
+            gte: None,
+            lte: None
+        }
+    }
+
+    /// Filter out transactions that have occured before the given `Date`.
+    pub fn start_date<S: Into<Date>>(mut self, date: S) -> FetchDataOptions {
+        self.start_date = Some(date.into());
+        self
+    }
+
+    /// Filter out transactions that have occured after the given `Date`.
+    pub fn end_date<S: Into<Date>>(mut self, date: S) -> FetchDataOptions {
+        self.end_date = Some(date.into());
+        self
+    }
+
+    /// Only include transactions for the account with the given id.
+    pub fn account<S: Into<UID>>(mut self, account_id: S) -> FetchDataOptions {
+        self.account = Some(account_id.into());
+        self
+    }
+
+    /// Include or exclude pending transactions.
+    pub fn pending(mut self, pending: bool) -> FetchDataOptions {
+        self.pending = Some(pending);
+        self
+    }
+
+    /// Only include transactions on or after the given `Date`.
+    pub fn gte<S: Into<Date>>(mut self, date: S) -> FetchDataOptions {
+        self.gte = Some(date.into());
+        self
+    }
+
+    /// Only include transactions on or before the given `Date`.
+    pub fn lte<S: Into<Date>>(mut self, date: S) -> FetchDataOptions {
+        self.lte = Some(date.into());
+        self
+    }
+
+    /// Check that every date is a valid `YYYY-MM-DD` date, and that each
+    /// lower bound is not after its upper bound.
+    pub fn validate(&self) -> Result<(), Error> {
+        let parse = |date: &Option<Date>| match *date {
+            Some(ref d) => Day::parse(d)
+                .map(Some)
+                .ok_or_else(|| Error::InvalidOptions(format!("Invalid date: {}", d))),
+            None => Ok(None)
+        };
+        let bounds = [(try!(parse(&self.start_date)), try!(parse(&self.end_date))),
+                      (try!(parse(&self.gte)), try!(parse(&self.lte)))];
+        for &(lower, upper) in bounds.iter() {
+            if let (Some(l), Some(u)) = (lower, upper) {
+                if l > u {
+                    return Err(Error::InvalidOptions(format!("{} is after {}", l, u)))
+                }
+            }
         }
+        Ok(())
+    }
+
+    /// The lower bound set with `gte`, if any.
+    pub fn get_gte(&self) -> Option<&Date> {
+        self.gte.as_ref()
+    }
+
+    /// The upper bound set with `lte`, if any.
+    pub fn get_lte(&self) -> Option<&Date> {
+        self.lte.as_ref()
+    }
+
+}
+
+/// Only the options that have been set are encoded.
+impl Encodable for FetchDataOptions {
+
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        let mut len = 0;
+        if self.start_date.is_some() { len += 1 }
+        if self.end_date.is_some() { len += 1 }
+        if self.account.is_some() { len += 1 }
+        if self.pending.is_some() { len += 1 }
+        if self.gte.is_some() { len += 1 }
+        if self.lte.is_some() { len += 1 }
+
+        e.emit_struct("options", len, |e| {
+            let mut i = 0;
+            if let Some(ref v) = self.start_date { try!(e.emit_struct_field("start_date", i, |e| v.encode(e))); i += 1; }
+            if let Some(ref v) = self.end_date { try!(e.emit_struct_field("end_date", i, |e| v.encode(e))); i += 1; }
+            if let Some(ref v) = self.account { try!(e.emit_struct_field("account", i, |e| v.encode(e))); i += 1; }
+            if let Some(ref v) = self.pending { try!(e.emit_struct_field("pending", i, |e| v.encode(e))); i += 1; }
+            if let Some(ref v) = self.gte { try!(e.emit_struct_field("gte", i, |e| v.encode(e))); i += 1; }
+            if let Some(ref v) = self.lte { try!(e.emit_struct_field("lte", i, |e| v.encode(e))); }
+            Ok(())
+        })
     }
 
 }
@@ -178,7 +375,8 @@ mod tests {
 
     use api::user::User;
     use api::client::{ Client, Payload };
-    use api::client::payload::{ FetchDataOptions,  AuthenticateOptions };
+    use api::client::payload::{ FetchDataOptions,  AuthenticateOptions, SelectedDevice };
+    use api::mfa::Device;
     use rustc_serialize::json;
     use hyper as h;
 
@@ -195,8 +393,8 @@ mod tests {
                 client,
                 "testinst".to_string(),
                 "username".to_string(),
-                "password".to_string(),
-                Some("PINCODE".to_string()),
+                "password".into(),
+                Some("PINCODE".into()),
                 Some(AuthenticateOptions { list: Some(true), .. AuthenticateOptions::default() }))).unwrap(),
                 r###"{"client_id":"testclientid","secret":"testsecret","username":"username","password":"password","type":"testinst","options":{"webhook":null,"login_only":null,"list":true,"send_method":null},"pin":"PINCODE"}"###)
     }
@@ -204,7 +402,7 @@ mod tests {
     #[test]
     fn test_fetch_data_payload_serialization() {
         let hyper = h::Client::new();
-        let user = User { access_token: "accesstoken123".to_string() };
+        let user = User::new("accesstoken123");
         let client = Client { endpoint: "https://tartan.plaid.com",
                               client_id: "testclientid",
                               secret: "testsecret",
@@ -214,8 +412,78 @@ mod tests {
             &Payload::FetchData(
                 client,
                 user,
-                Some(FetchDataOptions { start_date: Some("2015-01-01".to_string()), end_date: Some("2016-01-01".to_string()) }))).unwrap(),
-            r###"{"client_id":"testclientid","secret":"testsecret","access_token":"accesstoken123""options":{"start_date":"2015-01-01","end_date":"2016-01-01"}}"###)
+                Some(FetchDataOptions::default().start_date("2015-01-01").end_date("2016-01-01")))).unwrap(),
+            r###"{"client_id":"testclientid","secret":"testsecret","access_token":"accesstoken123","options":{"start_date":"2015-01-01","end_date":"2016-01-01"}}"###)
+    }
+
+    #[test]
+    fn test_authenticate_options_validation() {
+        assert!(AuthenticateOptions::default().validate().is_ok());
+        assert!(AuthenticateOptions::default()
+                .webhook("https://example.com/webhook")
+                .list(true)
+                .send_method(SelectedDevice::Device(Device::Email))
+                .validate().is_ok());
+        assert!(AuthenticateOptions::default()
+                .send_method(SelectedDevice::Device(Device::Email))
+                .validate().is_err());
+        assert!(AuthenticateOptions::default().webhook("example.com").validate().is_err());
+        assert!(AuthenticateOptions::default().webhook("https://").validate().is_err());
+    }
+
+    #[test]
+    fn test_fetch_data_options_validation() {
+        assert!(FetchDataOptions::default().gte("2016-01-01").lte("2016-01-01").validate().is_ok());
+        assert!(FetchDataOptions::default().gte("2016-02-01").lte("2016-01-01").validate().is_err());
+        assert!(FetchDataOptions::default().start_date("2016-13-01").validate().is_err());
+    }
+
+    #[test]
+    fn test_fetch_data_options_serialization() {
+        assert_eq!(json::encode(&FetchDataOptions::default()).unwrap(), "{}");
+        assert_eq!(json::encode(
+            &FetchDataOptions::default()
+                .account("testaccountid")
+                .pending(false)
+                .gte("2016-01-01")
+                .lte("2016-06-30")).unwrap(),
+            r###"{"account":"testaccountid","pending":false,"gte":"2016-01-01","lte":"2016-06-30"}"###)
+    }
+
+    #[test]
+    fn test_update_webhook_payload_serialization() {
+        let hyper = h::Client::new();
+        let user = User::new("accesstoken123");
+        let client = Client { endpoint: "https://tartan.plaid.com",
+                              client_id: "testclientid",
+                              secret: "testsecret",
+                              hyper: &hyper };
+
+        assert_eq!(json::encode(
+            &Payload::UpdateWebhook(
+                client,
+                user,
+                "https://example.com/webhook".to_string())).unwrap(),
+            r###"{"client_id":"testclientid","secret":"testsecret","access_token":"accesstoken123","options":{"webhook":"https://example.com/webhook"}}"###)
+    }
+
+    #[test]
+    fn test_patch_credentials_payload_serialization() {
+        let hyper = h::Client::new();
+        let user = User::new("accesstoken123");
+        let client = Client { endpoint: "https://tartan.plaid.com",
+                              client_id: "testclientid",
+                              secret: "testsecret",
+                              hyper: &hyper };
+
+        assert_eq!(json::encode(
+            &Payload::PatchCredentials(
+                client,
+                user,
+                "username".to_string(),
+                "newpassword".into(),
+                None)).unwrap(),
+            r###"{"client_id":"testclientid","secret":"testsecret","access_token":"accesstoken123","username":"username","password":"newpassword"}"###)
     }
 
 }
@@ -30,6 +30,8 @@ pub enum Response<P: Product> {
     /// We have successfully authenticated the user, and have retrieved
     /// the relevant `Product::Data` along with that authentication.
     Authenticated(User, P::Data),
+    /// The webhook for the `User` has been updated.
+    WebhookUpdated(User),
     /// Nothing is known about the user and no requests have been made
     Unknown
 }
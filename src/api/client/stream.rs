@@ -0,0 +1,290 @@
+//! Incremental decoding of response bodies.
+//!
+//! Responses are parsed once into a single JSON document, which both the `User` and
+//! the product data are then decoded from without copying the body. Only
+//! [`TransactionStream`][TransactionStream] parses as the body is read, it yields each
+//! `Transaction` of a `Connect` response as soon as it has been parsed, so that the
+//! full history never needs to be held in memory.
+//!
+//! [TransactionStream]: ./struct.TransactionStream.html
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{ self, Read, Bytes };
+use std::rc::Rc;
+use std::str;
+
+use api::data::raw::{ self, AttachRaw };
+use api::error::Error;
+use api::transaction::Transaction;
+use api::user::User;
+
+use rustc_serialize::Decodable;
+use rustc_serialize::json::{ self, Json, JsonEvent, Parser, ParserError, StackElement, DecoderError };
+
+/// An iterator over the `char`s of a UTF-8 encoded `Read`.
+/// Iteration stops at the first IO or encoding error, which is then
+/// available through `take_error`.
+pub struct Chars<R: Read> {
+    bytes: Bytes<R>,
+    error: Rc<RefCell<Option<io::Error>>>
+}
+
+impl<R: Read> Chars<R> {
+
+    /// Create a `Chars` over the given reader. The reader should be buffered.
+    pub fn new(reader: R) -> Chars<R> {
+        Chars { bytes: reader.bytes(), error: Rc::new(RefCell::new(None)) }
+    }
+
+    /// Take the error that stopped iteration, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.borrow_mut().take()
+    }
+
+    fn errors(&self) -> Rc<RefCell<Option<io::Error>>> {
+        self.error.clone()
+    }
+
+    fn set_error(&mut self, err: io::Error) {
+        let mut slot = self.error.borrow_mut();
+        if slot.is_none() { *slot = Some(err) }
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        match self.bytes.next() {
+            Some(Ok(b)) => Some(b),
+            Some(Err(e)) => { self.set_error(e); None },
+            None => None
+        }
+    }
+
+}
+
+impl<R: Read> Iterator for Chars<R> {
+
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.error.borrow().is_some() { return None }
+        let first = match self.next_byte() { Some(b) => b, None => return None };
+        let width = match first {
+            0x00..=0x7F => return Some(first as char),
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 0
+        };
+
+        let mut buf = [first, 0, 0, 0];
+        for i in 1..width {
+            match self.next_byte() { Some(b) => buf[i] = b, None => break }
+        }
+
+        match str::from_utf8(&buf[..width]).ok().and_then(|s| s.chars().next()) {
+            Some(c) => Some(c),
+            None => {
+                self.set_error(io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"));
+                None
+            }
+        }
+    }
+
+}
+
+/// Read and parse an entire JSON document from the given reader. The body is
+/// dropped as soon as it has been parsed.
+pub fn parse<R: Read>(mut reader: R) -> Result<Json, Error> {
+    let mut body = String::new();
+    try!(reader.read_to_string(&mut body));
+    Json::from_str(&body).map_err(|e| Error::InvalidResponse(DecoderError::ParseError(e)))
+}
+
+/// Decode a `Decodable` from an already parsed JSON document.
+pub fn decode<T: Decodable>(json: Json) -> Result<T, Error> {
+    Ok(try!(Decodable::decode(&mut json::Decoder::new(json))))
+}
+
+/// Decode a value from an already parsed JSON document, keeping the fields that are not decoded.
+/// See [`AttachRaw`](../../data/raw/trait.AttachRaw.html).
+pub fn decode_raw<T: Decodable + AttachRaw>(json: Json) -> Result<T, Error> {
+    Ok(try!(raw::decode_json(json)))
+}
+
+/// Remove the `User` from a parsed response body, leaving the rest of the
+/// document to be decoded as the product data.
+pub fn take_user(json: &mut Json) -> Result<User, Error> {
+    let token = match *json {
+        Json::Object(ref mut o) => o.remove("access_token"),
+        _ => None
+    };
+    let token = match token {
+        Some(t) => t,
+        None => return Err(Error::InvalidResponse(DecoderError::MissingFieldError("access_token".to_string())))
+    };
+    let mut object = BTreeMap::new();
+    object.insert("access_token".to_string(), token);
+    decode(Json::Object(object))
+}
+
+/// # TransactionStream
+/// An iterator that yields each `Transaction` of a `Connect` response body as soon
+/// as it has been parsed. Everything other than the `transactions` list is skipped.
+///
+/// Iteration stops after the first error.
+pub struct TransactionStream<R: Read> {
+    parser: Parser<Chars<R>>,
+    errors: Rc<RefCell<Option<io::Error>>>,
+    done: bool
+}
+
+impl<R: Read> TransactionStream<R> {
+
+    /// Create a `TransactionStream` over a response body. The reader should be buffered.
+    pub fn new(reader: R) -> TransactionStream<R> {
+        let chars = Chars::new(reader);
+        let errors = chars.errors();
+        TransactionStream { parser: Parser::new(chars), errors: errors, done: false }
+    }
+
+    fn in_transactions(&self) -> bool {
+        let stack = self.parser.stack();
+        stack.len() == 2 && match stack.get(0) {
+            StackElement::Key(k) => k == "transactions",
+            _ => false
+        }
+    }
+
+    fn build(&mut self, event: JsonEvent) -> Result<Json, ParserError> {
+        match event {
+            JsonEvent::NullValue => Ok(Json::Null),
+            JsonEvent::BooleanValue(b) => Ok(Json::Boolean(b)),
+            JsonEvent::I64Value(n) => Ok(Json::I64(n)),
+            JsonEvent::U64Value(n) => Ok(Json::U64(n)),
+            JsonEvent::F64Value(n) => Ok(Json::F64(n)),
+            JsonEvent::StringValue(s) => Ok(Json::String(s)),
+            JsonEvent::Error(e) => Err(e),
+            JsonEvent::ArrayStart => {
+                let mut array = Vec::new();
+                loop {
+                    match self.parser.next() {
+                        Some(JsonEvent::ArrayEnd) => return Ok(Json::Array(array)),
+                        Some(event) => array.push(try!(self.build(event))),
+                        None => return Err(eof())
+                    }
+                }
+            },
+            JsonEvent::ObjectStart => {
+                let mut object = BTreeMap::new();
+                loop {
+                    let event = match self.parser.next() {
+                        Some(JsonEvent::ObjectEnd) => return Ok(Json::Object(object)),
+                        Some(event) => event,
+                        None => return Err(eof())
+                    };
+                    let key = match self.parser.stack().top() {
+                        Some(StackElement::Key(k)) => k.to_string(),
+                        _ => return Err(eof())
+                    };
+                    let value = try!(self.build(event));
+                    object.insert(key, value);
+                }
+            },
+            JsonEvent::ArrayEnd | JsonEvent::ObjectEnd => Err(eof())
+        }
+    }
+
+    fn fail(&mut self, err: Error) -> Option<Result<Transaction, Error>> {
+        self.done = true;
+        match self.errors.borrow_mut().take() {
+            Some(io) => Some(Err(Error::IO(io))),
+            None => Some(Err(err))
+        }
+    }
+
+}
+
+fn eof() -> ParserError {
+    ParserError::SyntaxError(json::ErrorCode::EOFWhileParsingValue, 0, 0)
+}
+
+impl<R: Read> Iterator for TransactionStream<R> {
+
+    type Item = Result<Transaction, Error>;
+
+    fn next(&mut self) -> Option<Result<Transaction, Error>> {
+        if self.done { return None }
+        loop {
+            let event = match self.parser.next() {
+                Some(event) => event,
+                None => { self.done = true; return None }
+            };
+
+            match event {
+                JsonEvent::Error(e) => {
+                    return self.fail(Error::InvalidResponse(DecoderError::ParseError(e)))
+                },
+                JsonEvent::ObjectStart if self.in_transactions() => {
+                    let result = self.build(JsonEvent::ObjectStart)
+                        .map_err(|e| Error::InvalidResponse(DecoderError::ParseError(e)))
+                        .and_then(decode_raw);
+                    return match result {
+                        Ok(t) => Some(Ok(t)),
+                        Err(e) => self.fail(e)
+                    }
+                },
+                _ => continue
+            }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Cursor;
+
+    use api::client::stream::*;
+    use api::types::*;
+    use api::product::ConnectData;
+
+    #[test]
+    fn test_chars_decodes_utf8() {
+        let s: String = Chars::new(Cursor::new("Caf\u{e9} \u{1F4B8}".as_bytes())).collect();
+        assert_eq!(s, "Caf\u{e9} \u{1F4B8}");
+
+        let mut chars = Chars::new(Cursor::new(vec![b'a', 0xFF, b'b']));
+        assert_eq!(chars.next(), Some('a'));
+        assert_eq!(chars.next(), None);
+        assert!(chars.take_error().is_some());
+    }
+
+    #[test]
+    fn test_single_pass_decoding() {
+        let mut json = parse(Cursor::new(include_str!("../../../fixtures/post_connect_success.json"))).unwrap();
+        let user = take_user(&mut json).unwrap();
+        let data: ConnectData = decode(json).unwrap();
+        assert_eq!(user.access_token.expose(), "test");
+        assert_eq!(data.accounts[0].current_balance, 742.93 as Amount);
+        assert_eq!(data.transactions[1].id, "testtransactionid2".to_string());
+    }
+
+    #[test]
+    fn test_transaction_stream() {
+        let stream = TransactionStream::new(Cursor::new(include_str!("../../../fixtures/post_connect_success.json")));
+        let transactions: Vec<Transaction> = stream.map(|t| t.unwrap()).collect();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].amount, -700 as Amount);
+        assert_eq!(transactions[1].id, "testtransactionid2".to_string());
+        assert!(transactions[0].raw().is_some());
+    }
+
+    #[test]
+    fn test_transaction_stream_stops_on_errors() {
+        let mut stream = TransactionStream::new(Cursor::new(r#"{"transactions": [{"_id": "#));
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().is_none());
+    }
+
+}
@@ -1,11 +1,13 @@
 //! Representations of a user's bank account.
 
 use api::types::*;
-use rustc_serialize::{ Decodable, Decoder };
+use api::data::raw::{ self, RawObject };
+use rustc_serialize::{ Decodable, Decoder, Encodable, Encoder };
 
 /// # Account
 /// Represents one account associated with the given `User`.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Account {
     /// The unique id of the account.
     pub id: UID,
@@ -26,20 +28,31 @@ pub struct Account {
     /// [See here for a list of possible types][sub-types].
     /// [sub-types]: https://plaid.com/docs/api/#connect-account-subtypes
     pub account_subtype: Option<String>,
-    /// The user's bank account number.
+    /// The user's bank account number, of which only the last four digits are printed.
     /// Only available when using `api::product::Auth`.
-    pub account_number: Option<String>,
-    /// The user's routing number.
+    pub account_number: Option<AccountNumber>,
+    /// The user's routing number, of which only the last four digits are printed.
     /// Only available when using `api::product::Auth`.
-    pub routing_number: Option<String>,
-    /// The user's wire routing number.
+    pub routing_number: Option<RoutingNumber>,
+    /// The user's wire routing number, of which only the last four digits are printed.
     /// Only available when using `api::product::Auth`.
-    pub wire_routing_number: Option<String>,
+    pub wire_routing_number: Option<RoutingNumber>,
     /// Meta-data associated with this account
-    pub meta: Option<Meta>
+    pub meta: Option<Meta>,
+    /// The fields of the JSON object this account was decoded from which were not decoded,
+    /// if they were kept, see `raw()` and `extra()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw_json: RawObject
 }
 
-#[derive(Debug, RustcDecodable)]
+/// The fields of an account's JSON object that are decoded into `Account`.
+const FIELDS: &'static [&'static str] = &["_id", "_item", "balance", "institution_type", "type",
+                                          "subtype", "numbers", "meta"];
+
+raw_accessors!(Account, FIELDS);
+
+#[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Any meta-data associated with the account.
 pub struct Meta {
     /// Name of the account (e.g "Plaid credit card".)
@@ -90,17 +103,51 @@ impl Decodable for Account {
                 account_number: account_number,
                 routing_number: routing_number,
                 wire_routing_number: wire_routing_number,
-                meta: try!(decoder.read_struct_field("meta", 7, |d| Decodable::decode(d)))
+                meta: try!(decoder.read_struct_field("meta", 7, |d| Decodable::decode(d))),
+                raw_json: RawObject::default()
             })
         })
     }
 
 }
 
+impl Encodable for Account {
+
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("root", 8, |s| {
+            try!(s.emit_struct_field("_id", 0, |s| self.id.encode(s)));
+            try!(s.emit_struct_field("_item", 1, |s| self.item_id.encode(s)));
+            try!(s.emit_struct_field("balance", 2, |s| {
+                s.emit_struct("balance", 2, |s| {
+                    try!(s.emit_struct_field("current", 0, |s| self.current_balance.encode(s)));
+                    s.emit_struct_field("available", 1, |s| self.available_balance.encode(s))
+                })
+            }));
+            try!(s.emit_struct_field("institution_type", 3, |s| self.institution.encode(s)));
+            try!(s.emit_struct_field("type", 4, |s| self.account_type.encode(s)));
+            try!(s.emit_struct_field("subtype", 5, |s| self.account_subtype.encode(s)));
+            try!(s.emit_struct_field("numbers", 6, |s| {
+                if self.account_number.is_none() && self.routing_number.is_none() && self.wire_routing_number.is_none() {
+                    return s.emit_option_none()
+                }
+                s.emit_struct("numbers", 3, |s| {
+                    try!(s.emit_struct_field("account", 0, |s| self.account_number.encode(s)));
+                    try!(s.emit_struct_field("routing", 1, |s| self.routing_number.encode(s)));
+                    s.emit_struct_field("wireRouting", 2, |s| self.wire_routing_number.encode(s))
+                })
+            }));
+            try!(s.emit_struct_field("meta", 7, |s| self.meta.encode(s)));
+            raw::encode_extra(s, &self.raw_json, 8)
+        })
+    }
+
+}
+
 #[cfg(test)]
 mod tests {
 
     use api::types::*;
+    use api::data::raw;
     use rustc_serialize::json;
 
     #[test]
@@ -136,12 +183,35 @@ mod tests {
         assert_eq!(acc.institution, "fake_institution".to_string());
         assert_eq!(acc.account_type, "depository".to_string());
         assert_eq!(acc.account_subtype, Some("checking".to_string()));
-        assert_eq!(acc.account_number, Some("9900009606".to_string()));
-        assert_eq!(acc.routing_number, Some("021000021".to_string()));
-        assert_eq!(acc.wire_routing_number, Some("021000022".to_string()));
+        assert_eq!(acc.account_number, Some(AccountNumber::from("9900009606")));
+        assert_eq!(acc.routing_number, Some(RoutingNumber::from("021000021")));
+        assert_eq!(acc.wire_routing_number, Some(RoutingNumber::from("021000022")));
+        assert_eq!(format!("{:?}", acc.account_number), "Some(AccountNumber(****9606))");
         assert_eq!(acc.meta.unwrap().name, Some("Plaid Credit Card".to_string()));
     }
 
+    #[test]
+    fn test_decode_account_keeps_raw_json() {
+        let acc: Account = raw::decode(r##"
+            {
+            "_id": "YzzrzBrO9OSzo6BXwAvVuL5dmMKMqkhOoEqeo",
+            "_item": "aWWVW4VqGqIdaP495QyOSVLN1nzjLwhXaPDJJ",
+            "_user": "bkkVkMVwQwfYmBMy9jzqHQob9O1KwpFaEyLOE",
+            "balance": { "current": 7255.23 },
+            "institution_type": "fake_institution",
+            "meta": {},
+            "type": "depository"
+            }
+        "##).unwrap();
+
+        let raw = acc.raw().unwrap();
+        assert_eq!(raw.get("_user"), acc.extra().get("_user"));
+        assert_eq!(raw.get("type").and_then(|t| t.as_string()), Some("depository"));
+        let extra = acc.extra();
+        assert_eq!(extra.len(), 1);
+        assert_eq!(extra.get("_user").and_then(|u| u.as_string()), Some("bkkVkMVwQwfYmBMy9jzqHQob9O1KwpFaEyLOE"));
+    }
+
     #[test]
     fn test_decode_account_with_missing_values() {
         let acc: Account = json::decode(r##"
@@ -1,8 +1,10 @@
 //! Stores types that represent addresses
 
-use rustc_serialize::{ Decodable, Decoder };
+use api::data::raw::{ self, RawObject };
+use rustc_serialize::{ Decodable, Decoder, Encodable, Encoder };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// A user's address, as returned by Plaid.
 pub struct Address {
     /// The address zip code part.
@@ -16,9 +18,18 @@ pub struct Address {
     /// Longitude.
     pub longitude: Option<f64>,
     /// Latitude.
-    pub latitude: Option<f64>
+    pub latitude: Option<f64>,
+    /// The fields of the JSON object this address was decoded from which were not decoded,
+    /// if they were kept, see `raw()` and `extra()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw_json: RawObject
 }
 
+/// The fields of an address's JSON object that are decoded into `Address`.
+const FIELDS: &'static [&'static str] = &["zip", "state", "city", "street", "address", "coordinates"];
+
+raw_accessors!(Address, FIELDS);
+
 impl Decodable for Address {
 
     fn decode<D: Decoder>(d: &mut D) -> Result<Address, D::Error> {
@@ -52,17 +63,43 @@ impl Decodable for Address {
                 city: try!(d.read_struct_field("city", 5, |d| Decodable::decode(d))),
                 street: street.or(address),
                 latitude: lat,
-                longitude: lon
+                longitude: lon,
+                raw_json: RawObject::default()
             })
         })
     }
 
 }
 
+/// The street is always encoded as `street`, even when it was decoded from `address`
+/// as in the locations of transactions, so the `raw()` object of such an address has
+/// `street` in its place.
+impl Encodable for Address {
+
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("address", 5, |s| {
+            try!(s.emit_struct_field("zip", 0, |s| self.zip.encode(s)));
+            try!(s.emit_struct_field("state", 1, |s| self.state.encode(s)));
+            try!(s.emit_struct_field("city", 2, |s| self.city.encode(s)));
+            try!(s.emit_struct_field("street", 3, |s| self.street.encode(s)));
+            try!(s.emit_struct_field("coordinates", 4, |s| {
+                if self.latitude.is_none() && self.longitude.is_none() { return s.emit_option_none() }
+                s.emit_struct("coordinates", 2, |s| {
+                    try!(s.emit_struct_field("lat", 0, |s| self.latitude.encode(s)));
+                    s.emit_struct_field("lon", 1, |s| self.longitude.encode(s))
+                })
+            }));
+            raw::encode_extra(s, &self.raw_json, 5)
+        })
+    }
+
+}
+
 #[cfg(test)]
 mod tests {
 
     use api::data::address::Address;
+    use api::data::raw;
     use rustc_serialize::json;
 
     #[test]
@@ -108,4 +145,13 @@ mod tests {
         assert_eq!(Some(40.74 as f64), x.latitude);
         assert_eq!(Some(-74.00 as f64), x.longitude);
     }
+
+    #[test]
+    fn raw_address_is_rebuilt_with_street() {
+        let x: Address = raw::decode(r#"{ "address": "3819 Greenhaven Ln", "city": "San Francisco", "xyz": 1 }"#).unwrap();
+        let raw = x.raw().unwrap();
+        assert_eq!(raw.get("street").and_then(|s| s.as_string()), Some("3819 Greenhaven Ln"));
+        assert!(!raw.contains_key("address"));
+        assert_eq!(x.extra().len(), 1);
+    }
 }
@@ -0,0 +1,161 @@
+//! A minimal calendar date, used wherever this library needs to do
+//! arithmetic on the ISO 8601 `Date` strings returned by Plaid.
+
+use std::fmt;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use api::data::Date;
+
+/// # Day
+/// A day in the proleptic Gregorian calendar.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Day {
+    /// The year, e.g `2016`.
+    pub year: i32,
+    /// The month, from `1` to `12`.
+    pub month: u32,
+    /// The day of the month, from `1` to `31`.
+    pub day: u32
+}
+
+impl Day {
+
+    /// Create a `Day`, returning `None` if it does not exist.
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Day> {
+        if month < 1 || month > 12 || day < 1 || day > days_in_month(year, month) { return None }
+        Some(Day { year: year, month: month, day: day })
+    }
+
+    /// Parse a `Date` in the form `YYYY-MM-DD`. Anything after the day, such as a
+    /// time component, is ignored.
+    pub fn parse(date: &str) -> Option<Day> {
+        let date = date.trim();
+        if date.len() < 10 || !date.is_char_boundary(10) { return None }
+        let mut parts = date[..10].split('-');
+        let year = parts.next().and_then(|s| s.parse().ok());
+        let month = parts.next().and_then(|s| s.parse().ok());
+        let day = parts.next().and_then(|s| s.parse().ok());
+        match (year, month, day) {
+            (Some(y), Some(m), Some(d)) => Day::new(y, m, d),
+            _ => None
+        }
+    }
+
+    /// The current day in UTC.
+    pub fn today() -> Day {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Day::from_days((secs / 86_400) as i64)
+    }
+
+    /// The `Day` that is the given number of days since `1970-01-01`.
+    pub fn from_days(days: i64) -> Day {
+        // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = (if z >= 0 { z } else { z - 146_096 }) / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+        Day { year: year, month: month, day: day }
+    }
+
+    /// The number of days since `1970-01-01`.
+    pub fn days(&self) -> i64 {
+        let y = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+        let era = (if y >= 0 { y } else { y - 399 }) / 400;
+        let yoe = y - era * 400;
+        let m = self.month as i64;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    /// The `Day` that is `n` days after this one, `n` may be negative.
+    pub fn add_days(&self, n: i64) -> Day {
+        Day::from_days(self.days() + n)
+    }
+
+    /// The same day of the month, `n` months later, clamped to the
+    /// last day of the resulting month.
+    pub fn add_months(&self, n: i32) -> Day {
+        let index = self.year * 12 + self.month as i32 - 1 + n;
+        let year = index.div_euclid(12);
+        let month = index.rem_euclid(12) as u32 + 1;
+        let day = ::std::cmp::min(self.day, days_in_month(year, month));
+        Day { year: year, month: month, day: day }
+    }
+
+    /// The number of days from `other` until this day.
+    pub fn days_since(&self, other: &Day) -> i64 {
+        self.days() - other.days()
+    }
+
+    /// The day of the week, from `0` (Monday) to `6` (Sunday).
+    pub fn weekday(&self) -> u32 {
+        (self.days() + 3).rem_euclid(7) as u32
+    }
+
+    /// The first day of this day's month.
+    pub fn first_of_month(&self) -> Day {
+        Day { day: 1, .. *self }
+    }
+
+    /// Format the day as a `Date`, e.g `2016-06-02`.
+    pub fn to_date(&self) -> Date {
+        format!("{}", self)
+    }
+
+}
+
+impl fmt::Display for Day {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+}
+
+/// The number of days in the given month.
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        _ => 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use api::data::date::Day;
+
+    #[test]
+    fn test_parse_and_format() {
+        let d = Day::parse("2016-06-02").unwrap();
+        assert_eq!(d, Day { year: 2016, month: 6, day: 2 });
+        assert_eq!(d.to_date(), "2016-06-02".to_string());
+        assert_eq!(Day::parse("2016-06-02T12:00:00Z"), Some(d));
+        assert_eq!(Day::parse("2016-02-30"), None);
+        assert_eq!(Day::parse("yesterday"), None);
+    }
+
+    #[test]
+    fn test_day_arithmetic() {
+        let d = Day::parse("2016-03-01").unwrap();
+        assert_eq!(Day::from_days(0), Day { year: 1970, month: 1, day: 1 });
+        assert_eq!(Day::from_days(d.days()), d);
+        assert_eq!(d.add_days(-1).to_date(), "2016-02-29".to_string());
+        assert_eq!(d.add_days(365).to_date(), "2017-03-01".to_string());
+        assert_eq!(Day::parse("2016-01-31").unwrap().add_months(1).to_date(), "2016-02-29".to_string());
+        assert_eq!(Day::parse("2016-01-31").unwrap().add_months(-2).to_date(), "2015-11-30".to_string());
+        assert_eq!(d.days_since(&Day::parse("2016-02-01").unwrap()), 29);
+        // 2016-03-01 was a Tuesday.
+        assert_eq!(d.weekday(), 1);
+    }
+
+}
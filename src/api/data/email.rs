@@ -1,18 +1,30 @@
 //! Strucutres to encapsulate an email as returned by the Plaid API
 
-use rustc_serialize::{ Decoder, Decodable };
+use api::data::raw::{ self, RawObject };
+use api::data::secret::Secret;
+use rustc_serialize::{ Decoder, Decodable, Encodable, Encoder };
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// A user's email, including meta data returned by Plaid.
 pub struct Email {
     /// Whether or not the user has chosen this as their primary email.
     pub primary: bool,
     /// The designated type for this email (e.g personal, home).
     pub email_type: String,
-    /// The actual email address.
-    pub email: String
+    /// The actual email address, which is masked when printed.
+    pub email: Secret<String>,
+    /// The fields of the JSON object this email was decoded from which were not decoded,
+    /// if they were kept, see `raw()` and `extra()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw_json: RawObject
 }
 
+/// The fields of an email's JSON object that are decoded into `Email`.
+const FIELDS: &'static [&'static str] = &["primary", "type", "data"];
+
+raw_accessors!(Email, FIELDS);
+
 impl Decodable for Email {
 
     fn decode<D: Decoder>(d: &mut D) -> Result<Email, D::Error> {
@@ -20,13 +32,27 @@ impl Decodable for Email {
             Ok(Email {
                 primary: try!(d.read_struct_field("primary", 0, |d| d.read_bool())),
                 email_type: try!(d.read_struct_field("type", 1, |d| d.read_str())),
-                email: try!(d.read_struct_field("data", 2, |d| d.read_str()))
+                email: Secret::new(try!(d.read_struct_field("data", 2, |d| d.read_str()))),
+                raw_json: RawObject::default()
             })
         })
     }
 
 }
 
+impl Encodable for Email {
+
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("root", 3, |s| {
+            try!(s.emit_struct_field("primary", 0, |s| self.primary.encode(s)));
+            try!(s.emit_struct_field("type", 1, |s| self.email_type.encode(s)));
+            try!(s.emit_struct_field("data", 2, |s| self.email.encode(s)));
+            raw::encode_extra(s, &self.raw_json, 3)
+        })
+    }
+
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -43,7 +69,7 @@ mod tests {
 
         assert_eq!(true, x.primary);
         assert_eq!("personal".to_string(), x.email_type);
-        assert_eq!("kelly.walters30@example.com".to_string(), x.email);
+        assert_eq!("kelly.walters30@example.com", x.email.expose());
     }
 
 }
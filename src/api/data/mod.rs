@@ -2,19 +2,47 @@
 //!
 //! Most of these types will implement [`rustc_serialize::Decodable`][Decodable].
 //!
+//! ## Serde
+//!
+//! With the `serde` feature enabled, these types, along with the data of every product,
+//! also implement `serde::Serialize` and `serde::Deserialize`. This is meant for storing
+//! decoded data and passing it between processes, **not** for talking to Plaid.
+//!
+//! The serde form is a canonical JSON representation that mirrors the Rust types rather
+//! than Plaid's responses: every field is named after its struct field (e.g `account_id`
+//! rather than `_account`), every field is always present with unset `Option`s written
+//! as `null`, and enums such as `Context` are written as lowercase strings.
+//! The raw JSON objects kept by [`raw::AttachRaw`](./raw/trait.AttachRaw.html) types are
+//! not part of this form. Serializing and then deserializing a value in this form yields
+//! an identical value.
+//! When using `serde_json`, enable its `float_roundtrip` feature so that every `Amount`
+//! is read back exactly as it was written.
+//!
+//! ```toml
+//! [dependencies]
+//! plaid = { version = "0.2", features = ["serde"] }
+//! ```
+//!
 //! [Decodable]: https://doc.rust-lang.org/rustc-serialize/rustc_serialize/trait.Decodable.html
 
+#[macro_use]
+pub mod raw;
 pub mod account;
 pub mod transaction;
 pub mod address;
 pub mod phone_number;
 pub mod email;
+pub mod date;
+pub mod secret;
+pub mod numbers;
 
 pub use self::account::Account;
 pub use self::transaction::Transaction;
 pub use self::address::Address;
 pub use self::phone_number::PhoneNumber;
 pub use self::email::Email;
+pub use self::secret::{ Secret, LastFour };
+pub use self::numbers::{ AccountNumber, RoutingNumber };
 
 /// Unique identifiers in Plaid are represented as a globally unique hash.
 pub type UID = String;
@@ -33,25 +61,22 @@ pub type Username = String;
 pub type Name = String;
 
 /// A user's bank account password.
-pub type Password = String;
+pub type Password = Secret<String>;
 
 /// A user's multi-factor authentication code.
 pub type MFACode = String;
 
 /// A user's secret access token
-pub type AccessToken = String;
+pub type AccessToken = Secret<String>;
 
 /// Your client id from the Plaid dashboard.
 pub type ClientID = String;
 
-/// Your client secret from the Plaid dashboard.
-pub type ClientSecret = String;
-
 /// A user's institution. See [here for a list](https://plaid.com/docs/api/#institutions).
 pub type Institution = String;
 
 /// A PIN number
-pub type PIN = String;
+pub type PIN = Secret<String>;
 
 /// Dates are simply stored as their original `String` representation.
 /// It is up to you to parse it with your favorite date/time library.
@@ -0,0 +1,305 @@
+//! Bank account and routing numbers, as returned by `Auth`.
+//!
+//! Numbers are decoded as they are returned by Plaid, without validation, so that
+//! an unexpected number never prevents a response from being decoded. Use
+//! `validate()` before relying on them, e.g for payments. Both types are masked
+//! when printed, see [`Secret`](../secret/struct.Secret.html).
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use api::data::secret::{ Secret, LastFour };
+
+use rustc_serialize::{ Decodable, Decoder, Encodable, Encoder };
+
+#[cfg(feature = "serde")] use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+
+/// # NumberError
+/// The reasons that an account or routing number can be invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberError {
+    /// The number has too few or too many characters.
+    Length,
+    /// The number contains characters that are not allowed.
+    Charset,
+    /// The check digit of a routing number does not match.
+    Checksum
+}
+
+impl fmt::Display for NumberError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.description())
+    }
+
+}
+
+impl StdError for NumberError {
+
+    fn description(&self) -> &str {
+        match *self {
+            NumberError::Length => "The number has an invalid length",
+            NumberError::Charset => "The number contains invalid characters",
+            NumberError::Checksum => "The routing number has an invalid check digit"
+        }
+    }
+
+}
+
+/// One of the twelve Federal Reserve districts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FedDistrict {
+    /// District 1.
+    Boston,
+    /// District 2.
+    NewYork,
+    /// District 3.
+    Philadelphia,
+    /// District 4.
+    Cleveland,
+    /// District 5.
+    Richmond,
+    /// District 6.
+    Atlanta,
+    /// District 7.
+    Chicago,
+    /// District 8.
+    StLouis,
+    /// District 9.
+    Minneapolis,
+    /// District 10.
+    KansasCity,
+    /// District 11.
+    Dallas,
+    /// District 12.
+    SanFrancisco
+}
+
+const DISTRICTS: [FedDistrict; 12] = [FedDistrict::Boston, FedDistrict::NewYork, FedDistrict::Philadelphia,
+                                      FedDistrict::Cleveland, FedDistrict::Richmond, FedDistrict::Atlanta,
+                                      FedDistrict::Chicago, FedDistrict::StLouis, FedDistrict::Minneapolis,
+                                      FedDistrict::KansasCity, FedDistrict::Dallas, FedDistrict::SanFrancisco];
+
+impl FedDistrict {
+
+    /// The district with the given number, from `1` to `12`.
+    pub fn from_number(n: u32) -> Option<FedDistrict> {
+        if n >= 1 && n <= 12 { Some(DISTRICTS[n as usize - 1]) } else { None }
+    }
+
+    /// The number of the district, from `1` to `12`.
+    pub fn number(&self) -> u32 {
+        DISTRICTS.iter().position(|d| d == self).unwrap() as u32 + 1
+    }
+
+}
+
+/// # RoutingNumber
+/// A nine digit ABA routing transit number.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RoutingNumber(Secret<String, LastFour>);
+
+impl RoutingNumber {
+
+    /// Parse and validate a routing number.
+    pub fn parse(number: &str) -> Result<RoutingNumber, NumberError> {
+        let routing = RoutingNumber(Secret::from(number));
+        try!(routing.validate());
+        Ok(routing)
+    }
+
+    /// Access the number. Take care not to log it.
+    pub fn expose(&self) -> &str {
+        self.0.expose()
+    }
+
+    /// Check that the number has nine digits and a valid ABA check digit,
+    /// i.e that `3(d1 + d4 + d7) + 7(d2 + d5 + d8) + (d3 + d6 + d9)` is a multiple of 10.
+    pub fn validate(&self) -> Result<(), NumberError> {
+        let number = self.expose();
+        if number.chars().count() != 9 { return Err(NumberError::Length) }
+        let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+        if digits.len() != 9 { return Err(NumberError::Charset) }
+
+        let sum: u32 = digits.iter().enumerate()
+            .map(|(i, d)| d * [3, 7, 1][i % 3])
+            .sum();
+        if sum % 10 == 0 { Ok(()) } else { Err(NumberError::Checksum) }
+    }
+
+    /// Whether or not `validate` succeeds.
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// The Federal Reserve district of the institution, given by the first two digits.
+    /// These are `01` to `12` for banks, `21` to `32` for thrift institutions and `61`
+    /// to `72` for electronic transactions. Any other prefix has no district.
+    pub fn district(&self) -> Option<FedDistrict> {
+        let prefix: u32 = match self.expose().get(0..2).and_then(|p| p.parse().ok()) {
+            Some(p) => p,
+            None => return None
+        };
+        match prefix {
+            1..=12 => FedDistrict::from_number(prefix),
+            21..=32 => FedDistrict::from_number(prefix - 20),
+            61..=72 => FedDistrict::from_number(prefix - 60),
+            _ => None
+        }
+    }
+
+}
+
+/// # AccountNumber
+/// A bank account number, which has at most 17 characters so that it fits in a
+/// NACHA entry.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AccountNumber(Secret<String, LastFour>);
+
+/// The maximum length of an `AccountNumber`.
+pub const MAX_ACCOUNT_NUMBER_LENGTH: usize = 17;
+
+/// The minimum length of an `AccountNumber`.
+pub const MIN_ACCOUNT_NUMBER_LENGTH: usize = 4;
+
+impl AccountNumber {
+
+    /// Parse and validate an account number.
+    pub fn parse(number: &str) -> Result<AccountNumber, NumberError> {
+        let account = AccountNumber(Secret::from(number));
+        try!(account.validate());
+        Ok(account)
+    }
+
+    /// Access the number. Take care not to log it.
+    pub fn expose(&self) -> &str {
+        self.0.expose()
+    }
+
+    /// Check that the number has between `MIN_ACCOUNT_NUMBER_LENGTH` and
+    /// `MAX_ACCOUNT_NUMBER_LENGTH` characters, which are letters, digits or `-`.
+    pub fn validate(&self) -> Result<(), NumberError> {
+        let number = self.expose();
+        let len = number.chars().count();
+        if len < MIN_ACCOUNT_NUMBER_LENGTH || len > MAX_ACCOUNT_NUMBER_LENGTH {
+            return Err(NumberError::Length)
+        }
+        if !number.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(NumberError::Charset)
+        }
+        Ok(())
+    }
+
+    /// Whether or not `validate` succeeds.
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// The number as it is printed, e.g `****9606`.
+    pub fn masked(&self) -> String {
+        self.0.to_string()
+    }
+
+}
+
+macro_rules! number_impls {
+    ($name:ident) => {
+
+        impl fmt::Display for $name {
+
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+
+        }
+
+        impl<'a> From<&'a str> for $name {
+
+            fn from(number: &'a str) -> $name {
+                $name(Secret::from(number))
+            }
+
+        }
+
+        impl Decodable for $name {
+
+            fn decode<D: Decoder>(d: &mut D) -> Result<$name, D::Error> {
+                Ok($name(try!(Decodable::decode(d))))
+            }
+
+        }
+
+        impl Encodable for $name {
+
+            fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+                self.0.encode(s)
+            }
+
+        }
+
+        #[cfg(feature = "serde")]
+        impl Serialize for $name {
+
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.0.serialize(serializer)
+            }
+
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> Deserialize<'de> for $name {
+
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<$name, D::Error> {
+                Deserialize::deserialize(deserializer).map($name)
+            }
+
+        }
+
+    }
+}
+
+number_impls!(RoutingNumber);
+number_impls!(AccountNumber);
+
+#[cfg(test)]
+mod tests {
+
+    use api::data::numbers::*;
+    use rustc_serialize::json;
+
+    #[test]
+    fn test_routing_number_checksum() {
+        assert!(RoutingNumber::parse("021000021").is_ok());
+        assert!(RoutingNumber::parse("011000138").is_ok());
+        assert_eq!(RoutingNumber::parse("021000022"), Err(NumberError::Checksum));
+        assert_eq!(RoutingNumber::parse("02100002"), Err(NumberError::Length));
+        assert_eq!(RoutingNumber::parse("02100002a"), Err(NumberError::Charset));
+    }
+
+    #[test]
+    fn test_routing_number_district() {
+        assert_eq!(RoutingNumber::from("021000021").district(), Some(FedDistrict::NewYork));
+        assert_eq!(RoutingNumber::from("322271627").district(), Some(FedDistrict::SanFrancisco));
+        assert_eq!(RoutingNumber::from("611000000").district(), Some(FedDistrict::Boston));
+        assert_eq!(RoutingNumber::from("800000000").district(), None);
+        assert_eq!(FedDistrict::SanFrancisco.number(), 12);
+    }
+
+    #[test]
+    fn test_account_number() {
+        let account = AccountNumber::parse("9900009606").unwrap();
+        assert_eq!(account.masked(), "****9606");
+        assert_eq!(format!("{:?}", account), "AccountNumber(****9606)");
+        assert_eq!(AccountNumber::parse("123"), Err(NumberError::Length));
+        assert_eq!(AccountNumber::parse("123456789012345678"), Err(NumberError::Length));
+        assert_eq!(AccountNumber::parse("1234 5678"), Err(NumberError::Charset));
+    }
+
+    #[test]
+    fn test_numbers_decode_without_validation() {
+        let routing: RoutingNumber = json::decode(r#""021000022""#).unwrap();
+        assert!(!routing.is_valid());
+        assert_eq!(json::encode(&routing).unwrap(), r#""021000022""#);
+    }
+
+}
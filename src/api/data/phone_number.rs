@@ -1,19 +1,31 @@
 //! Types to encapsulate a phone number, as represented by
 //! the Plaid API.
 
-use rustc_serialize::{ Decoder, Decodable };
+use api::data::raw::{ self, RawObject };
+use api::data::secret::{ Secret, LastFour };
+use rustc_serialize::{ Decoder, Decodable, Encodable, Encoder };
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// A user's phone number, as returned by Plaid.
 pub struct PhoneNumber {
     /// Whether or not the user has chosen this as their primary phone number.
     pub primary: bool,
     /// The type of the phone number (e.g personal, home).
     pub phone_number_type: String,
-    /// The actual phone number.
-    pub phone_number: String
+    /// The actual phone number, of which only the last four digits are printed.
+    pub phone_number: Secret<String, LastFour>,
+    /// The fields of the JSON object this phone number was decoded from which were not decoded,
+    /// if they were kept, see `raw()` and `extra()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw_json: RawObject
 }
 
+/// The fields of a phone number's JSON object that are decoded into `PhoneNumber`.
+const FIELDS: &'static [&'static str] = &["primary", "type", "data"];
+
+raw_accessors!(PhoneNumber, FIELDS);
+
 impl Decodable for PhoneNumber {
 
     fn decode<D: Decoder>(d: &mut D) -> Result<PhoneNumber, D::Error> {
@@ -21,13 +33,27 @@ impl Decodable for PhoneNumber {
             Ok(PhoneNumber {
                 primary: try!(d.read_struct_field("primary", 0, |d| d.read_bool())),
                 phone_number_type: try!(d.read_struct_field("type", 1, |d| d.read_str())),
-                phone_number: try!(d.read_struct_field("data", 2, |d| d.read_str()))
+                phone_number: Secret::new(try!(d.read_struct_field("data", 2, |d| d.read_str()))),
+                raw_json: RawObject::default()
             })
         })
     }
 
 }
 
+impl Encodable for PhoneNumber {
+
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("root", 3, |s| {
+            try!(s.emit_struct_field("primary", 0, |s| self.primary.encode(s)));
+            try!(s.emit_struct_field("type", 1, |s| self.phone_number_type.encode(s)));
+            try!(s.emit_struct_field("data", 2, |s| self.phone_number.encode(s)));
+            raw::encode_extra(s, &self.raw_json, 3)
+        })
+    }
+
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -46,7 +72,8 @@ mod tests {
 
         assert_eq!(true, x.primary);
         assert_eq!("home".to_string(), x.phone_number_type);
-        assert_eq!("4673956022".to_string(), x.phone_number);
+        assert_eq!("4673956022", x.phone_number.expose());
+        assert_eq!("****6022", format!("{:?}", x.phone_number));
     }
 
 }
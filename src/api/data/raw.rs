@@ -0,0 +1,252 @@
+//! Access to the raw JSON that a type was decoded from.
+//!
+//! Plaid returns more fields than this library decodes, and adds new ones over
+//! time. Types that implement [`AttachRaw`][AttachRaw] can keep the fields that
+//! were not decoded in a [`RawObject`][RawObject], which is exposed through two
+//! accessors:
+//!
+//! + `extra()` returns the fields that were not decoded, see `RawObject::extra`,
+//! + `raw()` rebuilds the whole object from the decoded and extra fields, see
+//!   `RawObject::rebuild`.
+//!
+//! These types are encoded in the same form that they are decoded from, including
+//! the extra fields, which is what `raw()` relies on.
+//!
+//! Keeping these fields is opt-in, they are attached by `Client::request_raw`,
+//! `TransactionStream` and [`decode`][decode]. Types that are decoded by
+//! `Client::request` or `rustc_serialize::json::decode` will not have them.
+//!
+//! [AttachRaw]: ./trait.AttachRaw.html
+//! [RawObject]: ./struct.RawObject.html
+//! [decode]: ./fn.decode.html
+
+use std::fmt;
+
+use rustc_serialize::{ Decodable, Encodable, Encoder };
+use rustc_serialize::json::{ self, Json, Object, DecoderError };
+
+/// Add the `raw()` and `extra()` accessors to a type that keeps its extra fields in
+/// a `raw_json` field. Given the keys of the fields that it decodes, also implement
+/// `AttachRaw` for it.
+macro_rules! raw_accessors {
+    ($name:ident) => (
+        impl $name {
+
+            /// The JSON object this was decoded from, rebuilt from its fields, if the
+            /// fields that were not decoded were kept.
+            pub fn raw(&self) -> Option<::rustc_serialize::json::Object> {
+                self.raw_json.rebuild(self)
+            }
+
+            /// Every field of the JSON object this was decoded from that was not decoded.
+            pub fn extra(&self) -> ::rustc_serialize::json::Object {
+                self.raw_json.extra()
+            }
+
+        }
+    );
+    ($name:ident, $fields:expr) => (
+        raw_accessors!($name);
+
+        impl $crate::api::data::raw::AttachRaw for $name {
+
+            fn split_raw(json: &mut ::rustc_serialize::json::Json) -> ::rustc_serialize::json::Json {
+                $crate::api::data::raw::split(json, $fields)
+            }
+
+            fn attach_raw(&mut self, extra: ::rustc_serialize::json::Json) {
+                self.raw_json = $crate::api::data::raw::RawObject::new(extra);
+            }
+
+        }
+    )
+}
+
+/// # RawObject
+/// The fields of a JSON object that were not decoded into a value, if they were kept.
+#[derive(Clone, Default, PartialEq)]
+pub struct RawObject(Option<Object>);
+
+impl RawObject {
+
+    /// Create a `RawObject` from the given fields, which are only kept if they are an object.
+    pub fn new(json: Json) -> RawObject {
+        match json {
+            Json::Object(o) => RawObject(Some(o)),
+            _ => RawObject(None)
+        }
+    }
+
+    /// Whether the fields that were not decoded were kept.
+    pub fn is_kept(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// The fields that were not decoded, empty if they were not kept.
+    pub fn extra(&self) -> Object {
+        self.0.clone().unwrap_or_default()
+    }
+
+    /// Rebuild the raw object of `value`, which should encode these fields along with
+    /// its own. `None` if the fields were not kept.
+    pub fn rebuild<T: Encodable>(&self, value: &T) -> Option<Object> {
+        if !self.is_kept() { return None }
+        match json::encode(value).ok().and_then(|s| Json::from_str(&s).ok()) {
+            Some(Json::Object(o)) => Some(o),
+            _ => None
+        }
+    }
+
+}
+
+/// Raw objects are not printed, as they may be large and contain sensitive data.
+impl fmt::Debug for RawObject {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("RawObject(..)"),
+            None => f.write_str("RawObject(None)")
+        }
+    }
+
+}
+
+/// Anything that implements `AttachRaw` can keep the fields of the JSON that it
+/// was decoded from which were not decoded.
+pub trait AttachRaw {
+    /// Remove every field of `json` that is not decoded into this type, including those
+    /// of nested values, in the form that `attach_raw` takes them.
+    fn split_raw(json: &mut Json) -> Json;
+    /// Attach the fields that `split_raw` removed from the JSON that `self` was decoded from.
+    fn attach_raw(&mut self, extra: Json);
+}
+
+impl<T: AttachRaw> AttachRaw for Vec<T> {
+
+    fn split_raw(json: &mut Json) -> Json {
+        match *json {
+            Json::Array(ref mut items) => Json::Array(items.iter_mut().map(T::split_raw).collect()),
+            _ => Json::Null
+        }
+    }
+
+    fn attach_raw(&mut self, extra: Json) {
+        if let Json::Array(items) = extra {
+            for (value, item) in self.iter_mut().zip(items) {
+                value.attach_raw(item);
+            }
+        }
+    }
+
+}
+
+impl<T: AttachRaw> AttachRaw for Option<T> {
+
+    fn split_raw(json: &mut Json) -> Json {
+        match *json {
+            Json::Null => Json::Null,
+            ref mut json => T::split_raw(json)
+        }
+    }
+
+    fn attach_raw(&mut self, extra: Json) {
+        if let Some(ref mut value) = *self {
+            value.attach_raw(extra);
+        }
+    }
+
+}
+
+/// Encode every field of `raw` as a struct field, numbering the fields from `index`.
+/// Used so that encoding a decoded value keeps the fields that this library does not decode.
+pub fn encode_extra<S: Encoder>(s: &mut S, raw: &RawObject, index: usize) -> Result<(), S::Error> {
+    if let Some(ref o) = raw.0 {
+        for (i, (k, v)) in o.iter().enumerate() {
+            try!(s.emit_struct_field(k, index + i, |s| v.encode(s)));
+        }
+    }
+    Ok(())
+}
+
+/// Remove every field of a JSON object whose key is not one of `known`, returning them as an object.
+pub fn split(json: &mut Json, known: &[&str]) -> Json {
+    match *json {
+        Json::Object(ref mut o) => {
+            let keys: Vec<String> = o.keys().filter(|k| !known.contains(&k.as_ref())).cloned().collect();
+            Json::Object(keys.into_iter().filter_map(|k| o.remove(&k).map(|v| (k, v))).collect())
+        },
+        _ => Json::Null
+    }
+}
+
+/// Split the fields of the nested value at `key` of a JSON object, see `AttachRaw::split_raw`.
+pub fn split_field<T: AttachRaw>(json: &mut Json, key: &str) -> Json {
+    match *json {
+        Json::Object(ref mut o) => o.get_mut(key).map_or(Json::Null, T::split_raw),
+        _ => Json::Null
+    }
+}
+
+/// Create a JSON object from the given fields, used to combine the fields split from nested values.
+pub fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+/// Remove the value at `key` from a JSON object, returning `Json::Null` if it is missing.
+pub fn take(json: &mut Json, key: &str) -> Json {
+    match *json {
+        Json::Object(ref mut o) => o.remove(key).unwrap_or(Json::Null),
+        _ => Json::Null
+    }
+}
+
+/// Decode a value from parsed JSON, keeping the fields that are not decoded.
+pub fn decode_json<T: Decodable + AttachRaw>(mut json: Json) -> Result<T, DecoderError> {
+    let extra = T::split_raw(&mut json);
+    let mut value: T = try!(Decodable::decode(&mut json::Decoder::new(json)));
+    value.attach_raw(extra);
+    Ok(value)
+}
+
+/// Decode a value from a JSON string, keeping the fields that are not decoded.
+pub fn decode<T: Decodable + AttachRaw>(s: &str) -> Result<T, DecoderError> {
+    decode_json(try!(Json::from_str(s).map_err(DecoderError::ParseError)))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use api::data::raw::*;
+    use api::types::Account;
+    use rustc_serialize::json::{ self, Json };
+
+    #[test]
+    fn test_split_keeps_unknown_fields() {
+        let mut json = Json::from_str(r#"{"a": 1, "b": 2, "c": 3}"#).unwrap();
+        let raw = RawObject::new(split(&mut json, &["a", "c"]));
+        let extra = raw.extra();
+        assert_eq!(extra.len(), 1);
+        assert_eq!(extra.get("b"), Some(&Json::U64(2)));
+        assert_eq!(json.as_object().unwrap().len(), 2);
+        assert!(raw.is_kept());
+        assert_eq!(format!("{:?}", raw), "RawObject(..)");
+        assert!(!RawObject::new(Json::Null).is_kept());
+    }
+
+    #[test]
+    fn test_raw_is_opt_in() {
+        let s = r#"{"_id": "a", "_item": "i", "_user": "u", "balance": {"current": 1.5},
+                    "institution_type": "fake_institution", "type": "depository"}"#;
+
+        let plain: Account = json::decode(s).unwrap();
+        assert!(plain.raw().is_none());
+        assert!(plain.extra().is_empty());
+
+        let account: Account = decode(s).unwrap();
+        assert_eq!(account.extra().len(), 1);
+        let raw = account.raw().unwrap();
+        assert_eq!(raw.get("_user").and_then(|u| u.as_string()), Some("u"));
+        assert_eq!(raw.get("_id").and_then(|u| u.as_string()), Some("a"));
+    }
+
+}
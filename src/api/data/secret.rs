@@ -0,0 +1,196 @@
+//! A wrapper for credentials and personal information.
+//!
+//! Values wrapped in [`Secret`](./struct.Secret.html) are never printed by `Debug`
+//! or `Display`, so that they can not end up in logs by accident. Their memory is
+//! zeroed when they are dropped.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use rustc_serialize::{ Decodable, Decoder, Encodable, Encoder };
+use zeroize::Zeroize;
+
+#[cfg(feature = "serde")] use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+
+/// How much of a `Secret` is printed.
+pub trait Mask {
+    /// The number of trailing characters that are printed.
+    fn visible() -> usize;
+}
+
+/// Nothing of the value is printed, e.g for passwords and access tokens.
+#[derive(Debug)]
+pub enum Hidden {}
+
+impl Mask for Hidden {
+    fn visible() -> usize { 0 }
+}
+
+/// The last four characters of the value are printed, e.g `****9606` for account
+/// numbers. Values shorter than eight characters are still masked entirely.
+#[derive(Debug)]
+pub enum LastFour {}
+
+impl Mask for LastFour {
+    fn visible() -> usize { 4 }
+}
+
+/// # Secret
+/// A value that is masked when printed, and zeroed when dropped.
+/// The value itself is only available through `expose()`.
+///
+/// How much of the value is printed is decided by the `Mask`, which is `Hidden`
+/// unless given. `Secret`s are encoded as their value.
+pub struct Secret<T: Zeroize, M: Mask = Hidden> {
+    value: T,
+    mask: PhantomData<M>
+}
+
+impl<T: Zeroize, M: Mask> Secret<T, M> {
+
+    /// Wrap the given value.
+    pub fn new(value: T) -> Secret<T, M> {
+        Secret { value: value, mask: PhantomData }
+    }
+
+    /// Access the value. Take care not to log it.
+    pub fn expose(&self) -> &T {
+        &self.value
+    }
+
+}
+
+impl<T: Zeroize, M: Mask> Drop for Secret<T, M> {
+
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+
+}
+
+impl<T: Zeroize + Clone, M: Mask> Clone for Secret<T, M> {
+
+    fn clone(&self) -> Secret<T, M> {
+        Secret::new(self.value.clone())
+    }
+
+}
+
+impl<T: Zeroize + PartialEq, M: Mask> PartialEq for Secret<T, M> {
+
+    fn eq(&self, other: &Secret<T, M>) -> bool {
+        self.value == other.value
+    }
+
+}
+
+impl<T: Zeroize + Eq, M: Mask> Eq for Secret<T, M> {}
+
+impl<T: Zeroize + Default, M: Mask> Default for Secret<T, M> {
+
+    fn default() -> Secret<T, M> {
+        Secret::new(T::default())
+    }
+
+}
+
+impl<M: Mask> From<String> for Secret<String, M> {
+
+    fn from(value: String) -> Secret<String, M> {
+        Secret::new(value)
+    }
+
+}
+
+impl<'a, M: Mask> From<&'a str> for Secret<String, M> {
+
+    fn from(value: &'a str) -> Secret<String, M> {
+        Secret::new(value.to_string())
+    }
+
+}
+
+impl<T: Zeroize + AsRef<str>, M: Mask> fmt::Display for Secret<T, M> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let chars: Vec<char> = self.value.as_ref().chars().collect();
+        let visible = M::visible();
+        try!(f.write_str("****"));
+        if visible > 0 && chars.len() >= visible * 2 {
+            let tail: String = chars[chars.len() - visible..].iter().cloned().collect();
+            try!(f.write_str(&tail));
+        }
+        Ok(())
+    }
+
+}
+
+impl<T: Zeroize + AsRef<str>, M: Mask> fmt::Debug for Secret<T, M> {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+
+}
+
+impl<T: Zeroize + Decodable, M: Mask> Decodable for Secret<T, M> {
+
+    fn decode<D: Decoder>(d: &mut D) -> Result<Secret<T, M>, D::Error> {
+        Ok(Secret::new(try!(Decodable::decode(d))))
+    }
+
+}
+
+impl<T: Zeroize + Encodable, M: Mask> Encodable for Secret<T, M> {
+
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        self.value.encode(s)
+    }
+
+}
+
+#[cfg(feature = "serde")]
+impl<T: Zeroize + Serialize, M: Mask> Serialize for Secret<T, M> {
+
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Zeroize + Deserialize<'de>, M: Mask> Deserialize<'de> for Secret<T, M> {
+
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Secret<T, M>, D::Error> {
+        T::deserialize(deserializer).map(Secret::new)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use api::data::secret::{ Secret, LastFour };
+    use rustc_serialize::json;
+
+    #[test]
+    fn test_secrets_are_masked() {
+        let password: Secret<String> = Secret::from("hunter2");
+        assert_eq!(format!("{:?}", password), "****");
+        assert_eq!(format!("{}", password), "****");
+        assert_eq!(password.expose(), "hunter2");
+
+        let account: Secret<String, LastFour> = Secret::from("9900009606");
+        assert_eq!(format!("{:?}", Some(account.clone())), "Some(****9606)");
+        assert_eq!(format!("{}", Secret::<String, LastFour>::from("9606")), "****");
+    }
+
+    #[test]
+    fn test_secrets_encode_their_value() {
+        let secret: Secret<String, LastFour> = json::decode(r#""9900009606""#).unwrap();
+        assert_eq!(secret, Secret::from("9900009606"));
+        assert_eq!(format!("{}", secret), "****9606");
+        assert_eq!(json::encode(&secret).unwrap(), r#""9900009606""#);
+    }
+
+}
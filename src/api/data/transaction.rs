@@ -1,10 +1,14 @@
 //! Representations of banking transactions.
 
+use analytics::merchant;
 use api::data as t;
-use rustc_serialize::{ Decodable, Decoder };
+use api::data::raw::{ self, AttachRaw, RawObject };
+use rustc_serialize::{ Decodable, Decoder, Encodable, Encoder };
+use rustc_serialize::json::Json;
 
 /// Represents a single transaction associated with a given `Account`.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Transaction {
     /// The unique identifier of this transaction.
     pub id: t::UID,
@@ -28,21 +32,94 @@ pub struct Transaction {
     /// The date on which the transaction took place.
     /// Plaid standardizes using the ISO 8601 format.
     pub date: t::Date,
+    /// The merchant name or transaction description, as cleaned up by Plaid.
+    pub name: Option<String>,
+    /// The description of the transaction as it was given by the institution.
+    pub original_description: Option<String>,
+    /// How well the `name` and location of this transaction were matched by Plaid.
+    pub score: Option<Score>,
+    /// For a posted transaction, the id of the pending transaction that it replaced.
+    pub pending_transaction_id: Option<t::UID>,
     /// Transaction meta data
-    pub meta: Option<Meta>
+    pub meta: Option<Meta>,
+    /// The fields of the JSON object this transaction was decoded from which were not decoded,
+    /// if they were kept, see `raw()` and `extra()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw_json: RawObject
+}
+
+/// The fields of a transaction's JSON object that are decoded into `Transaction`.
+const FIELDS: &'static [&'static str] = &["_id", "_account", "amount", "category_id", "type",
+                                          "category", "pending", "date", "name", "original_description",
+                                          "score", "_pendingTransaction", "meta"];
+
+raw_accessors!(Transaction);
+
+impl Transaction {
+
+    /// The canonical name of the merchant, from the `name` or else the `original_description`,
+    /// e.g `Starbucks` for `POS DEBIT 1234 STARBUCKS #2231 SEATTLE WA`.
+    /// See [`analytics::merchant`](../../../analytics/merchant/index.html) to use your own rules.
+    pub fn normalized_name(&self) -> Option<String> {
+        merchant::default_normalizer().normalize_transaction(self)
+    }
+
+}
+
+impl AttachRaw for Transaction {
+
+    fn split_raw(json: &mut Json) -> Json {
+        let location = match *json {
+            Json::Object(ref mut o) => o.get_mut("meta").map_or(Json::Null, |m| raw::split_field::<t::Address>(m, "location")),
+            _ => Json::Null
+        };
+        raw::object(vec![("fields", raw::split(json, FIELDS)), ("location", location)])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        if let Some(ref mut meta) = self.meta {
+            meta.location.attach_raw(raw::take(&mut extra, "location"));
+        }
+        self.raw_json = RawObject::new(raw::take(&mut extra, "fields"));
+    }
+
 }
 
 /// Represents meta data associated with the transaction
-#[derive(RustcDecodable, Debug)]
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Meta {
     /// The location in which the transaction most likely occured.
     pub location: t::Address
 }
 
+/// Represents the confidence of Plaid in the details of a transaction,
+/// each score is between `0` and `1`.
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Score {
+    /// The confidence in the transaction `name`.
+    pub name: Option<f64>,
+    /// The confidence in each part of the transaction location.
+    pub location: Option<LocationScore>
+}
+
+/// Represents the confidence of Plaid in each part of a transaction's location.
+#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LocationScore {
+    /// The confidence in the street address.
+    pub address: Option<f64>,
+    /// The confidence in the city.
+    pub city: Option<f64>,
+    /// The confidence in the state.
+    pub state: Option<f64>
+}
+
 impl Decodable for Transaction {
 
     fn decode<D: Decoder>(decoder: &mut D) -> Result<Transaction, D::Error> {
-        decoder.read_struct("root", 9, |d| {
+        decoder.read_struct("root", 13, |d| {
             Ok(Transaction {
                 id: try!(d.read_struct_field("_id", 0, |d| Decodable::decode(d))),
                 account_id: try!(d.read_struct_field("_account", 1, |d| Decodable::decode(d))),
@@ -52,15 +129,45 @@ impl Decodable for Transaction {
                 categories: try!(d.read_struct_field("category", 5, |d| Decodable::decode(d))),
                 pending: try!(d.read_struct_field("pending", 6, |d| Decodable::decode(d))),
                 date: try!(d.read_struct_field("date", 7, |d| Decodable::decode(d))),
-                meta: try!(d.read_struct_field("meta", 8, |d| Decodable::decode(d)))
+                name: try!(d.read_struct_field("name", 8, |d| Decodable::decode(d))),
+                original_description: try!(d.read_struct_field("original_description", 9, |d| Decodable::decode(d))),
+                score: try!(d.read_struct_field("score", 10, |d| Decodable::decode(d))),
+                pending_transaction_id: try!(d.read_struct_field("_pendingTransaction", 11, |d| Decodable::decode(d))),
+                meta: try!(d.read_struct_field("meta", 12, |d| Decodable::decode(d))),
+                raw_json: RawObject::default()
             })
         })
     }
 
 }
 
+impl Encodable for Transaction {
+
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("root", 13, |s| {
+            try!(s.emit_struct_field("_id", 0, |s| self.id.encode(s)));
+            try!(s.emit_struct_field("_account", 1, |s| self.account_id.encode(s)));
+            try!(s.emit_struct_field("amount", 2, |s| self.amount.encode(s)));
+            try!(s.emit_struct_field("category_id", 3, |s| self.category_id.encode(s)));
+            try!(s.emit_struct_field("type", 4, |s| self.context.encode(s)));
+            try!(s.emit_struct_field("category", 5, |s| self.categories.encode(s)));
+            try!(s.emit_struct_field("pending", 6, |s| self.pending.encode(s)));
+            try!(s.emit_struct_field("date", 7, |s| self.date.encode(s)));
+            try!(s.emit_struct_field("name", 8, |s| self.name.encode(s)));
+            try!(s.emit_struct_field("original_description", 9, |s| self.original_description.encode(s)));
+            try!(s.emit_struct_field("score", 10, |s| self.score.encode(s)));
+            try!(s.emit_struct_field("_pendingTransaction", 11, |s| self.pending_transaction_id.encode(s)));
+            try!(s.emit_struct_field("meta", 12, |s| self.meta.encode(s)));
+            raw::encode_extra(s, &self.raw_json, 13)
+        })
+    }
+
+}
+
 /// The context in which a transaction took place
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Context {
     /// A phyical place
     Place,
@@ -89,10 +196,25 @@ impl Decodable for Context {
 
 }
 
+impl Encodable for Context {
+
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        let primary = match *self {
+            Context::Place => "place",
+            Context::Digital => "digital",
+            Context::Special => "special",
+            Context::Unresolved => "unresolved"
+        };
+        s.emit_struct("root", 1, |s| s.emit_struct_field("primary", 0, |s| primary.encode(s)))
+    }
+
+}
+
 #[cfg(test)]
 mod tests {
 
     use api::types::*;
+    use api::product::ConnectData;
     use rustc_serialize::json;
 
     #[test]
@@ -140,7 +262,44 @@ mod tests {
         assert_eq!(transaction.account_id, "testaccount".to_string());
         assert_eq!(transaction.amount, 12.70 as Amount);
         assert_eq!(transaction.category_id, 13005000 as CategoryID);
+        assert_eq!(transaction.name, Some("Golden Crepes".to_string()));
+        assert_eq!(transaction.pending_transaction_id, None);
+        assert_eq!(transaction.original_description, None);
+        let score = transaction.score.unwrap();
+        assert_eq!(score.name, Some(0.9));
+        assert_eq!(score.location.unwrap().city, Some(1.0));
         assert_eq!(transaction.meta.unwrap().location.street.unwrap(), "262 W 15th St".to_string());
     }
 
+    #[test]
+    fn test_decode_fixture_transactions() {
+        let data: ConnectData = json::decode(include_str!("../../../fixtures/post_connect_success.json")).unwrap();
+
+        let ref atm = data.transactions[0];
+        assert_eq!(atm.name, Some("ATM Deposit".to_string()));
+        assert_eq!(atm.pending_transaction_id, None);
+        assert_eq!(atm.score.as_ref().unwrap().name, Some(1.0));
+
+        let ref crepes = data.transactions[1];
+        assert_eq!(crepes.name, Some("Golden Crepes".to_string()));
+        assert_eq!(crepes.original_description, Some("GOLDEN CREPES NEW YORK NY".to_string()));
+        assert_eq!(crepes.pending_transaction_id, Some("testpendingtransactionid".to_string()));
+        let location = crepes.score.as_ref().unwrap().location.as_ref().unwrap();
+        assert_eq!((location.address, location.city, location.state), (Some(1.0), Some(1.0), Some(1.0)));
+    }
+
+    #[test]
+    fn test_normalized_name() {
+        let data: ConnectData = json::decode(include_str!("../../../fixtures/post_connect_success.json")).unwrap();
+        let mut crepes = data.transactions[1].clone();
+        assert_eq!(crepes.normalized_name(), Some("Golden Crepes".to_string()));
+
+        crepes.name = None;
+        assert_eq!(crepes.normalized_name(), Some("Golden Crepes".to_string()));
+        crepes.original_description = Some("POS DEBIT 1234 STARBUCKS #2231 SEATTLE WA".to_string());
+        assert_eq!(crepes.normalized_name(), Some("Starbucks".to_string()));
+        crepes.original_description = None;
+        assert_eq!(crepes.normalized_name(), None);
+    }
+
 }
@@ -6,6 +6,8 @@ use std::error::Error as StdError;
 use std::io::Error as IOError;
 use std::fmt;
 use hyper;
+use api::webhook::VerificationError;
+use vault::VaultError;
 use rustc_serialize::json::{DecoderError, EncoderError};
 
 /// # Error
@@ -23,6 +25,12 @@ pub enum Error {
     HTTP(hyper::Error),
     /// Returned for errors that are forwarded from `std::io::Error`
     IO(IOError),
+    /// Returned when the options given for a request are invalid.
+    InvalidOptions(String),
+    /// Returned when an incoming webhook fails verification.
+    InvalidWebhook(VerificationError),
+    /// Returned when a `TokenVault` operation fails.
+    Vault(VaultError),
     /// This should happen very rarely, and indicates that something is most
     /// likely wrong with `plaid::api` rather than the end user.
     InternalError,
@@ -44,6 +52,9 @@ impl StdError for Error {
             Error::InvalidResponse(ref err) => err.description(),
             Error::HTTP(ref err) => err.description(),
             Error::IO(ref err) => err.description(),
+            Error::InvalidOptions(ref reason) => reason,
+            Error::InvalidWebhook(ref err) => err.description(),
+            Error::Vault(ref err) => err.description(),
             Error::InternalError => "`plaid::api` internal error, please contact Plaid for support",
         }
     }
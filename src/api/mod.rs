@@ -1,12 +1,14 @@
-//! The namespace that everything in this library falls under.
+//! The namespace that everything which interacts with the Plaid API falls under.
+
+#[macro_use]
+pub mod data;
 
 pub mod user;
 pub mod client;
 pub mod error;
 pub mod product;
 pub mod mfa;
-
-pub mod data;
+pub mod webhook;
 
 pub use self::data::account;
 pub use self::data::transaction;
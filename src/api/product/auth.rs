@@ -25,7 +25,7 @@
 //!                       secret:    "testsecret",
 //!                       hyper:     &hyper };
 //!
-//! let user = User { access_token: "testaccesstoken".to_string() };
+//! let user = User::new("testaccesstoken");
 //!
 //! let response = client.request(
 //!   product::Auth,
@@ -36,7 +36,8 @@
 //! #     Response::ProductData(ref data) => {
 //! #         assert_eq!(data.accounts[0].current_balance, 1274.93 as Amount);
 //! #         assert_eq!(data.accounts[0].available_balance, Some(1203.42 as Amount));
-//! #         assert_eq!(data.accounts[0].account_number, Some("9900009606".to_string()));
+//! #         assert_eq!(data.accounts[0].account_number.as_ref().map(|n| n.expose()), Some("9900009606"));
+//! #         assert_eq!(format!("{:?}", data.accounts[0].account_number), "Some(AccountNumber(****9606))");
 //! #     },
 //! #     _ => panic!("Expected product data")
 //! # };
@@ -46,6 +47,9 @@
 use api::product::{ Product };
 use api::account::Account;
 use api::client::Payload;
+use api::data::raw::{ self, AttachRaw };
+
+use rustc_serialize::json::Json;
 
 /// `Auth` is the endpoint you need in order to check that the user owns their account.
 #[derive(Debug)]
@@ -53,11 +57,24 @@ pub struct Auth;
 
 /// Representation of data that is retrieved from the `Auth` product.
 #[derive(Debug, RustcDecodable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AuthData {
     /// List of accounts associated with the user
     pub accounts: Vec<Account>,
 }
 
+impl AttachRaw for AuthData {
+
+    fn split_raw(json: &mut Json) -> Json {
+        raw::object(vec![("accounts", raw::split_field::<Vec<Account>>(json, "accounts"))])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        self.accounts.attach_raw(raw::take(&mut extra, "accounts"));
+    }
+
+}
+
 impl Product for Auth {
     type Data = AuthData;
     fn description<'a>(&self) -> &'a str { "Auth" }
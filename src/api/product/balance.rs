@@ -24,7 +24,7 @@
 //!                       secret:    "testsecret",
 //!                       hyper:     &hyper };
 //!
-//! let user = User { access_token: "testaccesstoken".to_string() };
+//! let user = User::new("testaccesstoken");
 //!
 //! let response = client.request(
 //!   product::Balance,
@@ -44,6 +44,9 @@
 use api::product::{ Product };
 use api::account::Account;
 use api::client::Payload;
+use api::data::raw::{ self, AttachRaw };
+
+use rustc_serialize::json::Json;
 
 /// `Balance` is the endpoint you need to fetch transaction for a `User`
 #[derive(Debug)]
@@ -51,11 +54,24 @@ pub struct Balance;
 
 /// Representation of data that is retrieved from the `Balance` product.
 #[derive(Debug, RustcDecodable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BalanceData {
     /// List of accounts associated with the user
     pub accounts: Vec<Account>
 }
 
+impl AttachRaw for BalanceData {
+
+    fn split_raw(json: &mut Json) -> Json {
+        raw::object(vec![("accounts", raw::split_field::<Vec<Account>>(json, "accounts"))])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        self.accounts.attach_raw(raw::take(&mut extra, "accounts"));
+    }
+
+}
+
 impl Product for Balance {
     type Data = BalanceData;
     fn description<'a>(&self) -> &'a str { "Balance" }
@@ -24,7 +24,7 @@
 //!                       secret:    "testsecret",
 //!                       hyper:     &hyper };
 //!
-//! let user = User { access_token: "testaccesstoken".to_string() };
+//! let user = User::new("testaccesstoken");
 //!
 //! let response = client.request(
 //!   product::Connect,
@@ -47,6 +47,9 @@ use api::product::{ Product };
 use api::account::Account;
 use api::transaction::Transaction;
 use api::client::Payload;
+use api::data::raw::{ self, AttachRaw };
+
+use rustc_serialize::json::Json;
 
 /// `Connect` is the endpoint you need to fetch transaction for a `User`
 #[derive(Debug)]
@@ -54,6 +57,7 @@ pub struct Connect;
 
 /// Representation of data that is retrieved from the `Connect` product.
 #[derive(Debug, RustcDecodable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ConnectData {
     /// List of accounts associated with the user
     pub accounts: Vec<Account>,
@@ -61,6 +65,22 @@ pub struct ConnectData {
     pub transactions: Vec<Transaction>
 }
 
+impl AttachRaw for ConnectData {
+
+    fn split_raw(json: &mut Json) -> Json {
+        raw::object(vec![
+            ("accounts", raw::split_field::<Vec<Account>>(json, "accounts")),
+            ("transactions", raw::split_field::<Vec<Transaction>>(json, "transactions"))
+        ])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        self.accounts.attach_raw(raw::take(&mut extra, "accounts"));
+        self.transactions.attach_raw(raw::take(&mut extra, "transactions"));
+    }
+
+}
+
 impl Product for Connect {
     type Data = ConnectData;
     fn description<'a>(&self) -> &'a str { "Connect" }
@@ -23,7 +23,7 @@
 //!                       secret:    "testsecret",
 //!                       hyper:     &hyper };
 //!
-//! let user = User { access_token: "testaccesstoken".to_string() };
+//! let user = User::new("testaccesstoken");
 //!
 //! let response = client.request(
 //!   product::Income,
@@ -44,12 +44,17 @@
 use api::product::{ Product };
 use api::client::Payload;
 use api::data::{ Amount, Account };
+use api::data::raw::{ self, AttachRaw, RawObject };
+
+use rustc_serialize::{ Decodable, Decoder, Encodable, Encoder };
+use rustc_serialize::json::Json;
 
 #[derive(Debug)]
 /// The Income endpoint.
 pub struct Income;
 
 #[derive(Debug, RustcDecodable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// The underlying data representation of Income.
 pub struct IncomeData {
     /// A list of user accounts and their balances.
@@ -72,6 +77,7 @@ impl Product for Income {
 }
 
 #[derive(Debug, RustcDecodable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Internal data representation of the income response
 pub struct IncomeInternalData {
     /// A list of income streams.
@@ -98,7 +104,8 @@ pub struct IncomeInternalData {
     pub number_of_income_streams: u64
 }
 
-#[derive(Debug, RustcDecodable)]
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// An income stream represents a stream of income that Plaid
 /// has detected from their transactions.
 pub struct IncomeStream {
@@ -109,5 +116,72 @@ pub struct IncomeStream {
     /// The number of days Plaid has seen this for.
     pub days: u64,
     /// The name of the income stream.
-    pub name: String
+    pub name: String,
+    /// The fields of the JSON object this income stream was decoded from which were not decoded,
+    /// if they were kept, see `raw()` and `extra()`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw_json: RawObject
+}
+
+/// The fields of an income stream's JSON object that are decoded into `IncomeStream`.
+const FIELDS: &'static [&'static str] = &["monthly_income", "confidence", "days", "name"];
+
+raw_accessors!(IncomeStream, FIELDS);
+
+impl Decodable for IncomeStream {
+
+    fn decode<D: Decoder>(d: &mut D) -> Result<IncomeStream, D::Error> {
+        d.read_struct("root", 4, |d| {
+            Ok(IncomeStream {
+                monthly_income: try!(d.read_struct_field("monthly_income", 0, |d| Decodable::decode(d))),
+                confidence: try!(d.read_struct_field("confidence", 1, |d| Decodable::decode(d))),
+                days: try!(d.read_struct_field("days", 2, |d| Decodable::decode(d))),
+                name: try!(d.read_struct_field("name", 3, |d| Decodable::decode(d))),
+                raw_json: RawObject::default()
+            })
+        })
+    }
+
+}
+
+impl Encodable for IncomeStream {
+
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("root", 4, |s| {
+            try!(s.emit_struct_field("monthly_income", 0, |s| self.monthly_income.encode(s)));
+            try!(s.emit_struct_field("confidence", 1, |s| self.confidence.encode(s)));
+            try!(s.emit_struct_field("days", 2, |s| self.days.encode(s)));
+            try!(s.emit_struct_field("name", 3, |s| self.name.encode(s)));
+            raw::encode_extra(s, &self.raw_json, 4)
+        })
+    }
+
+}
+
+impl AttachRaw for IncomeData {
+
+    fn split_raw(json: &mut Json) -> Json {
+        raw::object(vec![
+            ("accounts", raw::split_field::<Vec<Account>>(json, "accounts")),
+            ("income", raw::split_field::<IncomeInternalData>(json, "income"))
+        ])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        self.accounts.attach_raw(raw::take(&mut extra, "accounts"));
+        self.income.attach_raw(raw::take(&mut extra, "income"));
+    }
+
+}
+
+impl AttachRaw for IncomeInternalData {
+
+    fn split_raw(json: &mut Json) -> Json {
+        raw::object(vec![("income_streams", raw::split_field::<Vec<IncomeStream>>(json, "income_streams"))])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        self.income_streams.attach_raw(raw::take(&mut extra, "income_streams"));
+    }
+
 }
@@ -23,7 +23,7 @@
 //!                       secret:    "testsecret",
 //!                       hyper:     &hyper };
 //!
-//! let user = User { access_token: "testaccesstoken".to_string() };
+//! let user = User::new("testaccesstoken");
 //!
 //! let response = client.request(
 //!   product::Info,
@@ -31,8 +31,8 @@
 //! #
 //! # match response {
 //! #     Response::ProductData(ref data) => {
-//! #         assert_eq!("4673956022".to_string(), data.info.phone_numbers[0].phone_number);
-//! #         assert_eq!("kelly.walters30@example.com".to_string(), data.info.emails[0].email);
+//! #         assert_eq!("4673956022", data.info.phone_numbers[0].phone_number.expose());
+//! #         assert_eq!("kelly.walters30@example.com", data.info.emails[0].email.expose());
 //! #         let ref address = data.info.addresses[0].address;
 //! #         let ref street = address.street;
 //! #         let ref zip = address.zip;
@@ -48,8 +48,10 @@ use api::product::Product;
 use api::account::Account;
 use api::client::Payload;
 use api::data::{ Address, PhoneNumber, Email };
+use api::data::raw::{ self, AttachRaw };
 
 use rustc_serialize::{ Decodable, Decoder };
+use rustc_serialize::json::Json;
 
 /// The definition of the Info Product.
 #[derive(Debug)]
@@ -57,6 +59,7 @@ pub struct Info;
 
 /// Representation of data that is retrieved from the `Info` product.
 #[derive(Debug, RustcDecodable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct InfoData {
     /// List of accounts associated with the user. When returned from the
     /// Info endpoint it will also include account and routing numbers.
@@ -67,6 +70,7 @@ pub struct InfoData {
 
 /// Represents the *actual* info data from an info response.
 #[derive(Debug, RustcDecodable)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct InfoInternalData {
     /// Emails associated with the user.
     pub emails: Vec<Email>,
@@ -78,6 +82,7 @@ pub struct InfoInternalData {
 
 /// Representation of an address entry returned by info.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct InfoAddress {
     /// Whether or not this is the user's primary address
     pub primary: bool,
@@ -101,6 +106,52 @@ impl Decodable for InfoAddress {
 
 }
 
+impl AttachRaw for InfoData {
+
+    fn split_raw(json: &mut Json) -> Json {
+        raw::object(vec![
+            ("accounts", raw::split_field::<Vec<Account>>(json, "accounts")),
+            ("info", raw::split_field::<InfoInternalData>(json, "info"))
+        ])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        self.accounts.attach_raw(raw::take(&mut extra, "accounts"));
+        self.info.attach_raw(raw::take(&mut extra, "info"));
+    }
+
+}
+
+impl AttachRaw for InfoInternalData {
+
+    fn split_raw(json: &mut Json) -> Json {
+        raw::object(vec![
+            ("emails", raw::split_field::<Vec<Email>>(json, "emails")),
+            ("addresses", raw::split_field::<Vec<InfoAddress>>(json, "addresses")),
+            ("phone_numbers", raw::split_field::<Vec<PhoneNumber>>(json, "phone_numbers"))
+        ])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        self.emails.attach_raw(raw::take(&mut extra, "emails"));
+        self.addresses.attach_raw(raw::take(&mut extra, "addresses"));
+        self.phone_numbers.attach_raw(raw::take(&mut extra, "phone_numbers"));
+    }
+
+}
+
+impl AttachRaw for InfoAddress {
+
+    fn split_raw(json: &mut Json) -> Json {
+        raw::object(vec![("data", raw::split_field::<Address>(json, "data"))])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        self.address.attach_raw(raw::take(&mut extra, "data"));
+    }
+
+}
+
 impl Product for Info {
     type Data = InfoData;
     fn description<'a>(&self) -> &'a str { "Info" }
@@ -4,12 +4,13 @@
 use std::fmt::Debug;
 use std::any::Any;
 use rustc_serialize::Decodable;
+use api::data::raw::AttachRaw;
 use api::client::payload::Payload;
 
 /// Anything that implements `Product` can be used as a product.
 pub trait Product : Any + Sync + Debug {
     /// The response data that is associated with this product.
-    type Data: Debug + Any + Decodable;
+    type Data: Debug + Any + Decodable + AttachRaw;
     /// The endpoint of the product for the given payload.
     /// With leading slash, e.g `/connect/get`
     fn endpoint<'a, 'b>(&self, &'b Payload) -> &'a str;
@@ -28,3 +29,42 @@ pub mod auth;
 pub mod info;
 pub mod balance;
 pub mod income;
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+
+    use api::product::*;
+    use rustc_serialize::{ json, Decodable };
+    use serde::Serialize;
+    use serde::de::DeserializeOwned;
+    use serde_json;
+
+    /// Decode the fixture, then check that its canonical form survives a round-trip.
+    fn assert_round_trip<T: Decodable + Serialize + DeserializeOwned + ::std::fmt::Debug>(fixture: &str) {
+        let decoded: T = json::decode(fixture).unwrap();
+        let canonical = serde_json::to_string(&decoded).unwrap();
+        let restored: T = serde_json::from_str(&canonical).unwrap();
+        assert_eq!(serde_json::to_string(&restored).unwrap(), canonical);
+        assert_eq!(format!("{:?}", restored), format!("{:?}", decoded));
+    }
+
+    #[test]
+    fn test_product_data_round_trips() {
+        assert_round_trip::<ConnectData>(include_str!("../../../fixtures/post_connect_success.json"));
+        assert_round_trip::<AuthData>(include_str!("../../../fixtures/post_auth_success.json"));
+        assert_round_trip::<BalanceData>(include_str!("../../../fixtures/post_balance_success.json"));
+        assert_round_trip::<InfoData>(include_str!("../../../fixtures/post_info_success.json"));
+        assert_round_trip::<IncomeData>(include_str!("../../../fixtures/post_income_success.json"));
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        let data: ConnectData = json::decode(include_str!("../../../fixtures/post_connect_success.json")).unwrap();
+        let value = serde_json::to_value(&data.transactions[0]).unwrap();
+        assert_eq!(value["id"], "testtransactionid");
+        assert_eq!(value["account_id"], "testaccount");
+        assert_eq!(value["context"], "special");
+        assert_eq!(value["amount"], -700.0);
+    }
+
+}
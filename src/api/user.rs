@@ -6,12 +6,22 @@ use rustc_serialize::{Decodable, Decoder };
 
 /// # User
 /// Represents an authorized user for a given product.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct User {
     /// The access token for this user
     pub access_token: AccessToken
 }
 
+impl User {
+
+    /// Create a `User` with the given access token.
+    pub fn new<S: Into<String>>(access_token: S) -> User {
+        User { access_token: Secret::new(access_token.into()) }
+    }
+
+}
+
 impl Decodable for User {
 
     fn decode<D: Decoder>(decoder: &mut D) -> Result<User, D::Error> {
@@ -0,0 +1,93 @@
+//! Routing of verified webhook events to registered handlers.
+
+use std::collections::HashMap;
+
+use api::webhook::{ WebhookEvent, EventKind };
+
+/// A handler that can be registered with a `Dispatcher`.
+pub type Handler = Box<dyn Fn(&WebhookEvent) + Send + Sync>;
+
+/// # Dispatcher
+/// Routes each `WebhookEvent` to the handlers registered for its `EventKind`.
+/// Events with no registered handlers are passed to the fallback handler, if any.
+#[derive(Default)]
+pub struct Dispatcher {
+    handlers: HashMap<EventKind, Vec<Handler>>,
+    fallback: Option<Handler>
+}
+
+impl Dispatcher {
+
+    /// Create a `Dispatcher` with no handlers.
+    pub fn new() -> Dispatcher {
+        Dispatcher { handlers: HashMap::new(), fallback: None }
+    }
+
+    /// Register a handler for the given `EventKind`. Handlers are called
+    /// in the order that they were registered.
+    pub fn on<F>(&mut self, kind: EventKind, handler: F) -> &mut Dispatcher
+        where F: Fn(&WebhookEvent) + Send + Sync + 'static {
+        self.handlers.entry(kind).or_default().push(Box::new(handler));
+        self
+    }
+
+    /// Register a handler for events that no other handler is registered for.
+    pub fn otherwise<F>(&mut self, handler: F) -> &mut Dispatcher
+        where F: Fn(&WebhookEvent) + Send + Sync + 'static {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    /// Pass the event to its handlers, returning the number of handlers that were called.
+    pub fn dispatch(&self, event: &WebhookEvent) -> usize {
+        match self.handlers.get(&event.kind()) {
+            Some(handlers) if !handlers.is_empty() => {
+                for handler in handlers { handler(event) }
+                handlers.len()
+            },
+            _ => match self.fallback {
+                Some(ref fallback) => { fallback(event); 1 },
+                None => 0
+            }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::Arc;
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+
+    use api::user::User;
+    use api::webhook::{ WebhookEvent, EventKind, Dispatcher };
+
+    fn user() -> User {
+        User::new("xxxxx")
+    }
+
+    #[test]
+    fn test_dispatch_routes_by_kind() {
+        let normal = Arc::new(AtomicUsize::new(0));
+        let other = Arc::new(AtomicUsize::new(0));
+
+        let mut dispatcher = Dispatcher::new();
+        let n = normal.clone();
+        dispatcher.on(EventKind::NormalTransactions, move |_| { n.fetch_add(1, Ordering::SeqCst); });
+        let o = other.clone();
+        dispatcher.otherwise(move |_| { o.fetch_add(1, Ordering::SeqCst); });
+
+        assert_eq!(dispatcher.dispatch(&WebhookEvent::NormalTransactions(user(), 3)), 1);
+        assert_eq!(dispatcher.dispatch(&WebhookEvent::WebhookUpdated(user())), 1);
+        assert_eq!(normal.load(Ordering::SeqCst), 1);
+        assert_eq!(other.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_dispatch_without_handlers() {
+        let dispatcher = Dispatcher::new();
+        assert_eq!(dispatcher.dispatch(&WebhookEvent::WebhookUpdated(user())), 0);
+    }
+
+}
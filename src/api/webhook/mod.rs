@@ -0,0 +1,284 @@
+//! Webhooks that Plaid sends when new data is available for a `User`.
+//!
+//! Incoming requests should be verified before they are trusted, this is done
+//! with a [`Verifier`][Verifier] which checks the signed `Plaid-Verification` header.
+//! Verified events can then be routed to handlers using a [`Dispatcher`][Dispatcher].
+//!
+//! The [`handle`][handle] function ties the two together, and can be mounted in any
+//! web framework that gives access to the raw request body and headers.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate p256;
+//! #
+//! # fn main() {
+//! use plaid::api::webhook::{ self, Dispatcher, EventKind, StaticKeyProvider, Verifier };
+//! # use p256::ecdsa::SigningKey;
+//! # let signing_key = SigningKey::from_bytes(&[7u8; 32].into()).unwrap();
+//! # let verifying_key = *signing_key.verifying_key();
+//!
+//! let mut keys = StaticKeyProvider::new();
+//! keys.insert("testkeyid", verifying_key);
+//!
+//! let verifier = Verifier::new(keys);
+//! let mut dispatcher = Dispatcher::new();
+//! dispatcher.on(EventKind::NormalTransactions, |event| {
+//!     println!("New transactions for {:?}", event.user());
+//! });
+//!
+//! // Pass in the raw headers and body of the incoming request.
+//! let status = webhook::handle(&verifier, &dispatcher, vec![("Plaid-Verification", "invalid")], b"{}");
+//! assert_eq!(status, 401);
+//! # }
+//! ```
+//!
+//! [Verifier]: ./verify/struct.Verifier.html
+//! [Dispatcher]: ./dispatch/struct.Dispatcher.html
+//! [handle]: ./fn.handle.html
+
+use api::error::Error;
+use api::types::*;
+use api::user::User;
+
+use rustc_serialize::{ Decodable, Decoder };
+use rustc_serialize::json;
+
+pub use self::verify::{ Verifier, VerificationError, KeyProvider, StaticKeyProvider, Jwk };
+pub use self::dispatch::Dispatcher;
+
+pub mod verify;
+pub mod dispatch;
+
+/// The name of the header that carries the signed verification token.
+pub const VERIFICATION_HEADER: &'static str = "Plaid-Verification";
+
+/// # WebhookEvent
+/// Represents a single webhook request sent by Plaid.
+#[derive(Debug)]
+pub enum WebhookEvent {
+    /// The initial transaction pull has finished, includes the number of
+    /// transactions that are available.
+    InitialTransactions(User, u64),
+    /// The historical transaction pull has finished, includes the number of
+    /// transactions that are available.
+    HistoricalTransactions(User, u64),
+    /// New transactions are available since the last pull.
+    NormalTransactions(User, u64),
+    /// The given transactions have been removed by the institution.
+    RemovedTransactions(User, Vec<UID>),
+    /// The webhook for this user has been updated.
+    WebhookUpdated(User),
+    /// Something went wrong with the user, in the form of `(user, code, message, resolve)`.
+    Error(User, u64, String, Option<String>)
+}
+
+/// The discriminant of a `WebhookEvent`, used to route events to handlers.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum EventKind {
+    /// See `WebhookEvent::InitialTransactions`.
+    InitialTransactions,
+    /// See `WebhookEvent::HistoricalTransactions`.
+    HistoricalTransactions,
+    /// See `WebhookEvent::NormalTransactions`.
+    NormalTransactions,
+    /// See `WebhookEvent::RemovedTransactions`.
+    RemovedTransactions,
+    /// See `WebhookEvent::WebhookUpdated`.
+    WebhookUpdated,
+    /// See `WebhookEvent::Error`.
+    Error
+}
+
+impl WebhookEvent {
+
+    /// The `EventKind` of this event.
+    pub fn kind(&self) -> EventKind {
+        match *self {
+            WebhookEvent::InitialTransactions(..) => EventKind::InitialTransactions,
+            WebhookEvent::HistoricalTransactions(..) => EventKind::HistoricalTransactions,
+            WebhookEvent::NormalTransactions(..) => EventKind::NormalTransactions,
+            WebhookEvent::RemovedTransactions(..) => EventKind::RemovedTransactions,
+            WebhookEvent::WebhookUpdated(..) => EventKind::WebhookUpdated,
+            WebhookEvent::Error(..) => EventKind::Error
+        }
+    }
+
+    /// The `User` that this event is concerned with.
+    pub fn user(&self) -> &User {
+        match *self {
+            WebhookEvent::InitialTransactions(ref u, _) |
+            WebhookEvent::HistoricalTransactions(ref u, _) |
+            WebhookEvent::NormalTransactions(ref u, _) |
+            WebhookEvent::RemovedTransactions(ref u, _) |
+            WebhookEvent::WebhookUpdated(ref u) |
+            WebhookEvent::Error(ref u, _, _, _) => u
+        }
+    }
+
+    /// Parse an event from the raw body of a webhook request.
+    pub fn from_slice(body: &[u8]) -> Result<WebhookEvent, json::DecoderError> {
+        let body = try!(::std::str::from_utf8(body).map_err(|e| {
+            json::DecoderError::ApplicationError(format!("{}", e))
+        }));
+        json::decode(body)
+    }
+
+}
+
+impl Decodable for WebhookEvent {
+
+    fn decode<D: Decoder>(d: &mut D) -> Result<WebhookEvent, D::Error> {
+        d.read_struct("root", 5, |d| {
+            let code: u64 = try!(d.read_struct_field("code", 0, |d| Decodable::decode(d)));
+            let user = User {
+                access_token: try!(d.read_struct_field("access_token", 1, |d| Decodable::decode(d)))
+            };
+            match code {
+                0..=2 => {
+                    let total: Option<u64> = try!(d.read_struct_field("total_transactions", 2, |d| Decodable::decode(d)));
+                    let total = total.unwrap_or(0);
+                    Ok(match code {
+                        0 => WebhookEvent::InitialTransactions(user, total),
+                        1 => WebhookEvent::HistoricalTransactions(user, total),
+                        _ => WebhookEvent::NormalTransactions(user, total)
+                    })
+                },
+                3 => {
+                    let removed = try!(d.read_struct_field("removed_transactions", 2, |d| Decodable::decode(d)));
+                    Ok(WebhookEvent::RemovedTransactions(user, removed))
+                },
+                4 => Ok(WebhookEvent::WebhookUpdated(user)),
+                c => {
+                    let message: Option<String> = try!(d.read_struct_field("message", 3, |d| Decodable::decode(d)));
+                    let resolve = try!(d.read_struct_field("resolve", 4, |d| Decodable::decode(d)));
+                    Ok(WebhookEvent::Error(user, c, message.unwrap_or_default(), resolve))
+                }
+            }
+        })
+    }
+
+}
+
+/// Verify and dispatch a raw webhook request, returning the HTTP status code
+/// that should be sent back to Plaid.
+///
+/// + `200` when the event was verified and dispatched.
+/// + `400` when the event was verified but its body could not be understood.
+/// + `401` when verification failed.
+/// + `503` when the verification key could not be fetched, e.g because of a network
+///   error, so that Plaid sends the event again later.
+pub fn handle<'h, K, I>(verifier: &Verifier<K>, dispatcher: &Dispatcher, headers: I, body: &[u8]) -> u16
+    where K: KeyProvider,
+          I: IntoIterator<Item=(&'h str, &'h str)> {
+    let token = headers.into_iter()
+        .find(|&(name, _)| name.eq_ignore_ascii_case(VERIFICATION_HEADER))
+        .map(|(_, value)| value);
+
+    let token = match token {
+        Some(t) => t,
+        None => return 401
+    };
+
+    match verifier.verify(token, body) {
+        Ok(()) => (),
+        Err(Error::InvalidWebhook(_)) => return 401,
+        Err(_) => return 503
+    }
+
+    match WebhookEvent::from_slice(body) {
+        Ok(event) => {
+            dispatcher.dispatch(&event);
+            200
+        },
+        Err(_) => 400
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io;
+    use api::error::Error;
+    use api::webhook::*;
+    use p256::ecdsa::VerifyingKey;
+    use rustc_serialize::base64::{ ToBase64, URL_SAFE };
+    use rustc_serialize::json;
+
+    struct UnreachableKeys;
+
+    impl KeyProvider for UnreachableKeys {
+        fn key(&self, _: &str) -> Result<VerifyingKey, Error> {
+            Err(Error::IO(io::Error::new(io::ErrorKind::TimedOut, "timed out")))
+        }
+    }
+
+    #[test]
+    fn test_handle_status() {
+        let header = br#"{"alg":"ES256","kid":"testkeyid"}"#.to_base64(URL_SAFE);
+        let token = format!("{}.e30.AAAA", header);
+        let headers = vec![("plaid-verification", token.as_str())];
+        let dispatcher = Dispatcher::new();
+
+        assert_eq!(handle(&Verifier::new(UnreachableKeys), &dispatcher, headers.clone(), b"{}"), 503);
+        assert_eq!(handle(&Verifier::new(StaticKeyProvider::new()), &dispatcher, headers, b"{}"), 401);
+        assert_eq!(handle(&Verifier::new(UnreachableKeys), &dispatcher, vec![], b"{}"), 401);
+    }
+
+    #[test]
+    fn test_decode_transaction_events() {
+        let e: WebhookEvent = json::decode(r##"
+            { "message": "Initial transaction pull finished",
+              "access_token": "xxxxx",
+              "total_transactions": 123,
+              "code": 0 }
+        "##).unwrap();
+        assert_eq!(e.kind(), EventKind::InitialTransactions);
+        assert_eq!(e.user().access_token.expose(), "xxxxx");
+        assert_eq!(format!("{:?}", e), r#"InitialTransactions(User { access_token: **** }, 123)"#);
+
+        let e: WebhookEvent = json::decode(r##"
+            { "message": "Normal transaction pull finished",
+              "access_token": "xxxxx",
+              "total_transactions": 3,
+              "code": 2 }
+        "##).unwrap();
+        assert_eq!(e.kind(), EventKind::NormalTransactions);
+    }
+
+    #[test]
+    fn test_decode_removed_transactions_event() {
+        let e = WebhookEvent::from_slice(br##"
+            { "message": "Transactions removed",
+              "access_token": "xxxxx",
+              "removed_transactions": ["txid1", "txid2"],
+              "code": 3 }
+        "##).unwrap();
+        match e {
+            WebhookEvent::RemovedTransactions(_, ref ids) => {
+                assert_eq!(ids, &vec!["txid1".to_string(), "txid2".to_string()]);
+            },
+            _ => panic!("Expected removed transactions")
+        }
+    }
+
+    #[test]
+    fn test_decode_error_event() {
+        let e = WebhookEvent::from_slice(br##"
+            { "message": "user credentials changed",
+              "resolve": "update credentials",
+              "access_token": "xxxxx",
+              "code": 1205 }
+        "##).unwrap();
+        match e {
+            WebhookEvent::Error(_, code, ref message, ref resolve) => {
+                assert_eq!(code, 1205);
+                assert_eq!(message, "user credentials changed");
+                assert_eq!(resolve, &Some("update credentials".to_string()));
+            },
+            _ => panic!("Expected an error event")
+        }
+    }
+
+}
@@ -0,0 +1,468 @@
+//! Verification of the signed `Plaid-Verification` header.
+//!
+//! The header is a JSON Web Token signed with `ES256`. Its claims include the time
+//! at which it was issued (`iat`) and the SHA-256 of the request body
+//! (`request_body_sha256`). The key used to sign it is identified by the `kid`
+//! in the token header, and is resolved through a [`KeyProvider`][KeyProvider].
+//!
+//! [KeyProvider]: ./trait.KeyProvider.html
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+use api::client::Client;
+use api::error::Error;
+
+use rustc_serialize::{ Decodable, Decoder };
+use rustc_serialize::base64::FromBase64;
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json;
+
+use p256::EncodedPoint;
+use p256::ecdsa::{ Signature, VerifyingKey };
+use p256::ecdsa::signature::Verifier as SignatureVerifier;
+use sha2::{ Digest, Sha256 };
+
+use hyper::header::ContentType;
+use hyper::mime::{ Mime, TopLevel, SubLevel };
+use hyper::status::StatusCode;
+
+/// The default amount of seconds that a token is considered fresh for.
+pub const DEFAULT_MAX_AGE: u64 = 5 * 60;
+
+/// Represents the reasons that a webhook could fail verification.
+#[derive(Debug, Eq, PartialEq)]
+pub enum VerificationError {
+    /// The token is not a well-formed JSON Web Token.
+    MalformedToken,
+    /// The token was signed with an algorithm other than `ES256`.
+    UnsupportedAlgorithm(String),
+    /// The `KeyProvider` does not know of the key that signed the token.
+    UnknownKey(String),
+    /// The key that signed the token has expired.
+    ExpiredKey(String),
+    /// The signature does not match the token contents.
+    BadSignature,
+    /// The token was issued outside of the allowed time window.
+    Stale,
+    /// The hash of the request body does not match the signed hash.
+    BodyMismatch
+}
+
+impl fmt::Display for VerificationError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.description())
+    }
+
+}
+
+impl StdError for VerificationError {
+
+    fn description(&self) -> &str {
+        match *self {
+            VerificationError::MalformedToken => "Webhook verification token is malformed",
+            VerificationError::UnsupportedAlgorithm(_) => "Webhook verification token uses an unsupported algorithm",
+            VerificationError::UnknownKey(_) => "Webhook verification key is unknown",
+            VerificationError::ExpiredKey(_) => "Webhook verification key has expired",
+            VerificationError::BadSignature => "Webhook verification signature is invalid",
+            VerificationError::Stale => "Webhook verification token is too old",
+            VerificationError::BodyMismatch => "Webhook body does not match its signed hash"
+        }
+    }
+
+}
+
+/// Anything that implements `KeyProvider` can be used to look up the key that
+/// signed a webhook, given the `kid` from the token header.
+pub trait KeyProvider {
+    /// Return the verifying key for the given key id.
+    fn key(&self, key_id: &str) -> Result<VerifyingKey, Error>;
+}
+
+/// A `KeyProvider` backed by a fixed set of keys, useful for tests
+/// or when keys are distributed out of band.
+#[derive(Debug, Default)]
+pub struct StaticKeyProvider {
+    keys: HashMap<String, VerifyingKey>
+}
+
+impl StaticKeyProvider {
+
+    /// Create an empty `StaticKeyProvider`.
+    pub fn new() -> StaticKeyProvider {
+        StaticKeyProvider { keys: HashMap::new() }
+    }
+
+    /// Register a key under the given key id.
+    pub fn insert<S: Into<String>>(&mut self, key_id: S, key: VerifyingKey) {
+        self.keys.insert(key_id.into(), key);
+    }
+
+}
+
+impl KeyProvider for StaticKeyProvider {
+
+    fn key(&self, key_id: &str) -> Result<VerifyingKey, Error> {
+        self.keys.get(key_id).cloned()
+            .ok_or_else(|| Error::InvalidWebhook(VerificationError::UnknownKey(key_id.to_string())))
+    }
+
+}
+
+/// A `KeyProvider` that fetches keys from Plaid using the given `Client`,
+/// caching them by key id until they expire. A key id that is not cached is
+/// always fetched, so that rotated keys are picked up.
+pub struct ClientKeyProvider<'a> {
+    client: Client<'a>,
+    cache: Mutex<KeyCache>
+}
+
+impl<'a> ClientKeyProvider<'a> {
+
+    /// Create a `ClientKeyProvider` that uses the given `Client`.
+    pub fn new(client: Client<'a>) -> ClientKeyProvider<'a> {
+        ClientKeyProvider { client: client, cache: Mutex::new(KeyCache::default()) }
+    }
+
+}
+
+/// Keys by key id, with the unix time at which they expire, if any.
+#[derive(Debug, Default)]
+struct KeyCache {
+    keys: HashMap<String, (VerifyingKey, Option<u64>)>
+}
+
+impl KeyCache {
+
+    /// The key with the given id, unless it is unknown or has expired at `now`.
+    fn get(&mut self, key_id: &str, now: u64) -> Option<VerifyingKey> {
+        let expired = match self.keys.get(key_id) {
+            Some(&(key, expired_at)) if !is_expired(expired_at, now) => return Some(key),
+            Some(_) => true,
+            None => false
+        };
+        if expired { self.keys.remove(key_id); }
+        None
+    }
+
+    fn insert(&mut self, key_id: &str, key: VerifyingKey, expired_at: Option<u64>) {
+        self.keys.insert(key_id.to_string(), (key, expired_at));
+    }
+
+}
+
+fn is_expired(expired_at: Option<u64>, now: u64) -> bool {
+    match expired_at {
+        Some(at) => at <= now,
+        None => false
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[derive(RustcEncodable)]
+struct KeyRequest<'a> {
+    client_id: &'a str,
+    secret: &'a str,
+    key_id: &'a str
+}
+
+#[derive(RustcDecodable)]
+struct KeyResponse {
+    key: Jwk
+}
+
+impl<'a> KeyProvider for ClientKeyProvider<'a> {
+
+    fn key(&self, key_id: &str) -> Result<VerifyingKey, Error> {
+        let now = unix_now();
+        if let Some(key) = try!(self.cache.lock().map_err(|_| Error::InternalError)).get(key_id, now) {
+            return Ok(key)
+        }
+
+        let body = try!(json::encode(&KeyRequest {
+            client_id: self.client.client_id,
+            secret: self.client.secret,
+            key_id: key_id
+        }));
+        let endpoint = format!("{}/webhook_verification_key/get", self.client.endpoint);
+        let mut res = try!(self.client.hyper
+            .post(&endpoint as &str)
+            .header(ContentType(Mime(TopLevel::Application, SubLevel::Json, vec![])))
+            .body(&body as &str)
+            .send());
+
+        if res.status != StatusCode::Ok { return Err(Error::UnsuccessfulResponse(res.status)) }
+
+        let mut buffer = String::new();
+        try!(res.read_to_string(&mut buffer));
+        let response: KeyResponse = try!(json::decode(&buffer));
+        if is_expired(response.key.expired_at, now) {
+            return Err(Error::InvalidWebhook(VerificationError::ExpiredKey(key_id.to_string())))
+        }
+        let key = try!(response.key.verifying_key());
+        try!(self.cache.lock().map_err(|_| Error::InternalError)).insert(key_id, key, response.key.expired_at);
+        Ok(key)
+    }
+
+}
+
+/// A JSON Web Key, as returned by Plaid when fetching a verification key.
+#[derive(Debug, RustcDecodable)]
+pub struct Jwk {
+    /// The key id.
+    pub kid: String,
+    /// The key type, always `EC`.
+    pub kty: String,
+    /// The curve of the key, always `P-256`.
+    pub crv: String,
+    /// The base64url encoded x coordinate.
+    pub x: String,
+    /// The base64url encoded y coordinate.
+    pub y: String,
+    /// The unix time at which the key expired, if it has been rotated.
+    pub expired_at: Option<u64>
+}
+
+impl Jwk {
+
+    /// Convert the `Jwk` into a key that can be used for verification.
+    pub fn verifying_key(&self) -> Result<VerifyingKey, Error> {
+        let invalid = || Error::InvalidWebhook(VerificationError::UnknownKey(self.kid.clone()));
+        if self.kty != "EC" || self.crv != "P-256" { return Err(invalid()) }
+        let x = try!(self.x.from_base64().map_err(|_| invalid()));
+        let y = try!(self.y.from_base64().map_err(|_| invalid()));
+        if x.len() != 32 || y.len() != 32 { return Err(invalid()) }
+        let point = EncodedPoint::from_affine_coordinates(
+            x.as_slice().into(), y.as_slice().into(), false);
+        VerifyingKey::from_encoded_point(&point).map_err(|_| invalid())
+    }
+
+}
+
+struct TokenHeader {
+    alg: String,
+    kid: String
+}
+
+impl Decodable for TokenHeader {
+
+    fn decode<D: Decoder>(d: &mut D) -> Result<TokenHeader, D::Error> {
+        d.read_struct("root", 2, |d| {
+            Ok(TokenHeader {
+                alg: try!(d.read_struct_field("alg", 0, |d| Decodable::decode(d))),
+                kid: try!(d.read_struct_field("kid", 1, |d| Decodable::decode(d)))
+            })
+        })
+    }
+
+}
+
+struct TokenClaims {
+    iat: u64,
+    request_body_sha256: String
+}
+
+impl Decodable for TokenClaims {
+
+    fn decode<D: Decoder>(d: &mut D) -> Result<TokenClaims, D::Error> {
+        d.read_struct("root", 2, |d| {
+            Ok(TokenClaims {
+                iat: try!(d.read_struct_field("iat", 0, |d| Decodable::decode(d))),
+                request_body_sha256: try!(d.read_struct_field("request_body_sha256", 1, |d| Decodable::decode(d)))
+            })
+        })
+    }
+
+}
+
+/// # Verifier
+/// Checks that a webhook was sent by Plaid, using keys from the given `KeyProvider`.
+pub struct Verifier<K: KeyProvider> {
+    keys: K,
+    max_age: u64
+}
+
+impl<K: KeyProvider> Verifier<K> {
+
+    /// Create a `Verifier` that accepts tokens up to `DEFAULT_MAX_AGE` seconds old.
+    pub fn new(keys: K) -> Verifier<K> {
+        Verifier { keys: keys, max_age: DEFAULT_MAX_AGE }
+    }
+
+    /// Change the amount of seconds that a token is considered fresh for.
+    pub fn max_age(mut self, seconds: u64) -> Verifier<K> {
+        self.max_age = seconds;
+        self
+    }
+
+    /// Verify the `token` from the `Plaid-Verification` header against the raw request `body`.
+    pub fn verify(&self, token: &str, body: &[u8]) -> Result<(), Error> {
+        self.verify_at(token, body, unix_now())
+    }
+
+    /// Verify the `token` as if the current unix time was `now`.
+    pub fn verify_at(&self, token: &str, body: &[u8], now: u64) -> Result<(), Error> {
+        let malformed = || Error::InvalidWebhook(VerificationError::MalformedToken);
+
+        let mut parts = token.split('.');
+        let (header, claims, signature) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(h), Some(c), Some(s), None) => (h, c, s),
+            _ => return Err(malformed())
+        };
+
+        let header: TokenHeader = try!(decode_segment(header).ok_or_else(&malformed));
+        if header.alg != "ES256" {
+            return Err(Error::InvalidWebhook(VerificationError::UnsupportedAlgorithm(header.alg)))
+        }
+
+        let key = try!(self.keys.key(&header.kid));
+        let signature = try!(signature.from_base64().map_err(|_| malformed()));
+        let signature = try!(Signature::from_slice(&signature).map_err(|_| malformed()));
+        let signed_len = token.len() - signature_len(token);
+        if key.verify(&token.as_bytes()[..signed_len], &signature).is_err() {
+            return Err(Error::InvalidWebhook(VerificationError::BadSignature))
+        }
+
+        let claims: TokenClaims = try!(decode_segment(claims).ok_or_else(&malformed));
+        let age = now.abs_diff(claims.iat);
+        if age > self.max_age {
+            return Err(Error::InvalidWebhook(VerificationError::Stale))
+        }
+
+        let digest = Sha256::digest(body).to_hex();
+        if !constant_time_eq(digest.as_bytes(), claims.request_body_sha256.as_bytes()) {
+            return Err(Error::InvalidWebhook(VerificationError::BodyMismatch))
+        }
+
+        Ok(())
+    }
+
+}
+
+/// The length of the signature segment of a token, with its leading `.`.
+fn signature_len(token: &str) -> usize {
+    token.rfind('.').map(|n| token.len() - n).unwrap_or(0)
+}
+
+fn decode_segment<T: Decodable>(segment: &str) -> Option<T> {
+    let bytes = match segment.from_base64() { Ok(b) => b, Err(_) => return None };
+    let s = match String::from_utf8(bytes) { Ok(s) => s, Err(_) => return None };
+    json::decode(&s).ok()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() { return false }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+
+    use api::error::Error;
+    use api::webhook::verify::*;
+    use rustc_serialize::base64::{ ToBase64, URL_SAFE };
+    use rustc_serialize::hex::ToHex;
+    use p256::ecdsa::{ Signature, SigningKey };
+    use p256::ecdsa::signature::Signer;
+    use sha2::{ Digest, Sha256 };
+
+    const BODY: &'static [u8] = br#"{"code":2,"access_token":"xxxxx","total_transactions":3}"#;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32].into()).unwrap()
+    }
+
+    fn token(key: &SigningKey, alg: &str, iat: u64, body: &[u8]) -> String {
+        let header = format!(r#"{{"alg":"{}","kid":"testkeyid","typ":"JWT"}}"#, alg).as_bytes().to_base64(URL_SAFE);
+        let claims = format!(r#"{{"iat":{},"request_body_sha256":"{}"}}"#, iat, Sha256::digest(body).to_hex())
+            .as_bytes().to_base64(URL_SAFE);
+        let signed = format!("{}.{}", header, claims);
+        let signature: Signature = key.sign(signed.as_bytes());
+        format!("{}.{}", signed, signature.to_bytes().to_base64(URL_SAFE))
+    }
+
+    fn verifier() -> Verifier<StaticKeyProvider> {
+        let mut keys = StaticKeyProvider::new();
+        keys.insert("testkeyid", *signing_key().verifying_key());
+        Verifier::new(keys)
+    }
+
+    #[test]
+    fn test_verify_valid_token() {
+        let t = token(&signing_key(), "ES256", 1000, BODY);
+        assert!(verifier().verify_at(&t, BODY, 1060).is_ok());
+    }
+
+    #[test]
+    fn test_verify_rejects_modified_body() {
+        let t = token(&signing_key(), "ES256", 1000, BODY);
+        match verifier().verify_at(&t, br#"{"code":3}"#, 1000) {
+            Err(Error::InvalidWebhook(VerificationError::BodyMismatch)) => (),
+            r => panic!("Unexpected result {:?}", r)
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_stale_token() {
+        let t = token(&signing_key(), "ES256", 1000, BODY);
+        match verifier().verify_at(&t, BODY, 1000 + DEFAULT_MAX_AGE + 1) {
+            Err(Error::InvalidWebhook(VerificationError::Stale)) => (),
+            r => panic!("Unexpected result {:?}", r)
+        }
+        assert!(verifier().max_age(3600).verify_at(&t, BODY, 1000 + DEFAULT_MAX_AGE + 1).is_ok());
+    }
+
+    #[test]
+    fn test_verify_rejects_other_keys_and_algorithms() {
+        let other = SigningKey::from_bytes(&[9u8; 32].into()).unwrap();
+        match verifier().verify_at(&token(&other, "ES256", 1000, BODY), BODY, 1000) {
+            Err(Error::InvalidWebhook(VerificationError::BadSignature)) => (),
+            r => panic!("Unexpected result {:?}", r)
+        }
+        match verifier().verify_at(&token(&signing_key(), "none", 1000, BODY), BODY, 1000) {
+            Err(Error::InvalidWebhook(VerificationError::UnsupportedAlgorithm(_))) => (),
+            r => panic!("Unexpected result {:?}", r)
+        }
+        match verifier().verify_at("not-a-token", BODY, 1000) {
+            Err(Error::InvalidWebhook(VerificationError::MalformedToken)) => (),
+            r => panic!("Unexpected result {:?}", r)
+        }
+    }
+
+    #[test]
+    fn test_jwk_to_verifying_key() {
+        let point = signing_key().verifying_key().to_encoded_point(false);
+        let jwk = Jwk {
+            kid: "testkeyid".to_string(),
+            kty: "EC".to_string(),
+            crv: "P-256".to_string(),
+            x: point.x().unwrap().to_base64(URL_SAFE),
+            y: point.y().unwrap().to_base64(URL_SAFE),
+            expired_at: None
+        };
+        assert_eq!(jwk.verifying_key().unwrap(), *signing_key().verifying_key());
+    }
+
+    #[test]
+    fn test_key_cache_expiry() {
+        let key = *signing_key().verifying_key();
+        let mut cache = KeyCache::default();
+        cache.insert("forever", key, None);
+        cache.insert("rotated", key, Some(2000));
+
+        assert_eq!(cache.get("forever", 5000), Some(key));
+        assert_eq!(cache.get("rotated", 1999), Some(key));
+        assert_eq!(cache.get("rotated", 2000), None);
+        assert_eq!(cache.get("rotated", 1000), None);
+        assert_eq!(cache.get("unknown", 1000), None);
+    }
+
+}
@@ -0,0 +1,524 @@
+//! Writes transactions and accounts as CSV, and reads transactions back.
+//!
+//! The columns, their order, the sign of amounts and the format of dates are
+//! configured with [`Options`][Options]. The writers take plain slices, so they
+//! work on the accounts of any product's data, e.g `AuthData` or `BalanceData`.
+//!
+//! [`read_transactions`][read_transactions] reads a file with a header row of
+//! [`Column`][Column] names, which makes it easy to write test data by hand.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! use plaid::api::product::ConnectData;
+//! use plaid::export::csv::{ self, Column, Options };
+//!
+//! let data: ConnectData = json::decode(include_str!("fixtures/post_connect_success.json")).unwrap();
+//! let options = Options { columns: vec![Column::Date, Column::Name, Column::Amount, Column::City],
+//!                         .. Options::default() };
+//!
+//! let written = csv::write_transactions(&data.transactions, &data.accounts, &options);
+//! assert_eq!(written.lines().nth(2), Some("2016-03-12,Golden Crepes,-12.74,New York"));
+//!
+//! let read = csv::read_transactions(&written, &options).unwrap();
+//! assert_eq!(read[1].amount, 12.74);
+//! # }
+//! ```
+//!
+//! [Options]: ./struct.Options.html
+//! [Column]: ./enum.Column.html
+//! [read_transactions]: ./fn.read_transactions.html
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+
+use api::data::{ Address, Amount };
+use api::data::date::Day;
+use api::data::raw::RawObject;
+use api::data::transaction::{ Context, Meta };
+use api::types::{ Account, Transaction };
+use export::{ amount, DateFormat, Sign };
+
+/// A column of a transactions file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+    /// The transaction `_id`.
+    Id,
+    /// The day of the transaction.
+    Date,
+    /// The amount, in the configured `Sign` convention.
+    Amount,
+    /// The name of the transaction.
+    Name,
+    /// The description given by the institution.
+    OriginalDescription,
+    /// The category hierarchy, joined by the configured separator.
+    Categories,
+    /// The category id.
+    CategoryId,
+    /// The context, e.g `place`.
+    Context,
+    /// Whether the transaction is pending.
+    Pending,
+    /// The id of the account.
+    AccountId,
+    /// The `meta.name` of the account.
+    AccountName,
+    /// The last digits of the account number, from `meta.number`.
+    AccountMask,
+    /// The street of the location.
+    Street,
+    /// The city of the location.
+    City,
+    /// The state of the location.
+    State,
+    /// The zip code of the location.
+    Zip,
+    /// The latitude of the location.
+    Latitude,
+    /// The longitude of the location.
+    Longitude
+}
+
+const COLUMNS: [Column; 18] = [Column::Id, Column::Date, Column::Amount, Column::Name, Column::OriginalDescription,
+                               Column::Categories, Column::CategoryId, Column::Context, Column::Pending,
+                               Column::AccountId, Column::AccountName, Column::AccountMask, Column::Street,
+                               Column::City, Column::State, Column::Zip, Column::Latitude, Column::Longitude];
+
+impl Column {
+
+    /// The name of the column in a header row.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Column::Id => "id",
+            Column::Date => "date",
+            Column::Amount => "amount",
+            Column::Name => "name",
+            Column::OriginalDescription => "original_description",
+            Column::Categories => "categories",
+            Column::CategoryId => "category_id",
+            Column::Context => "context",
+            Column::Pending => "pending",
+            Column::AccountId => "account_id",
+            Column::AccountName => "account_name",
+            Column::AccountMask => "account_mask",
+            Column::Street => "street",
+            Column::City => "city",
+            Column::State => "state",
+            Column::Zip => "zip",
+            Column::Latitude => "latitude",
+            Column::Longitude => "longitude"
+        }
+    }
+
+    /// The column with the given name.
+    pub fn parse(name: &str) -> Option<Column> {
+        COLUMNS.iter().find(|c| c.name() == name.trim()).cloned()
+    }
+
+}
+
+/// A column of an accounts file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccountColumn {
+    /// The account `_id`.
+    Id,
+    /// The `meta.name` of the account.
+    Name,
+    /// The last digits of the account number, from `meta.number`.
+    Mask,
+    /// The type, e.g `depository`.
+    Type,
+    /// The subtype, e.g `checking`.
+    Subtype,
+    /// The institution type, e.g `chase`.
+    Institution,
+    /// The current balance.
+    CurrentBalance,
+    /// The available balance.
+    AvailableBalance,
+    /// The limit of a credit account.
+    Limit
+}
+
+impl AccountColumn {
+
+    /// The name of the column in a header row.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            AccountColumn::Id => "id",
+            AccountColumn::Name => "name",
+            AccountColumn::Mask => "mask",
+            AccountColumn::Type => "type",
+            AccountColumn::Subtype => "subtype",
+            AccountColumn::Institution => "institution",
+            AccountColumn::CurrentBalance => "current_balance",
+            AccountColumn::AvailableBalance => "available_balance",
+            AccountColumn::Limit => "limit"
+        }
+    }
+
+}
+
+/// How a file is written and read.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The columns of a transactions file, in order.
+    pub columns: Vec<Column>,
+    /// The columns of an accounts file, in order.
+    pub account_columns: Vec<AccountColumn>,
+    /// The sign convention of transaction amounts.
+    pub sign: Sign,
+    /// The format of dates.
+    pub date_format: DateFormat,
+    /// The character between fields.
+    pub delimiter: char,
+    /// What joins the categories of a transaction.
+    pub category_separator: String,
+    /// Whether a header row is written.
+    pub header: bool
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            columns: vec![Column::Date, Column::Name, Column::Amount, Column::Categories, Column::AccountMask],
+            account_columns: vec![AccountColumn::Name, AccountColumn::Mask, AccountColumn::Type,
+                                  AccountColumn::CurrentBalance, AccountColumn::AvailableBalance],
+            sign: Sign::Natural,
+            date_format: DateFormat::Iso,
+            delimiter: ',',
+            category_separator: ":".to_string(),
+            header: true
+        }
+    }
+
+}
+
+/// # CsvError
+/// The reasons that a file can not be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvError {
+    /// The file has no header row.
+    MissingHeader,
+    /// The header names a column that does not exist.
+    UnknownColumn(String),
+    /// A required column, `date` or `amount`, is missing.
+    MissingColumn(Column),
+    /// A value could not be read, with the line number (starting at 1) and column.
+    InvalidValue(usize, Column),
+    /// A quoted field is not closed.
+    UnclosedQuote
+}
+
+impl fmt::Display for CsvError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CsvError::UnknownColumn(ref name) => write!(f, "Unknown column {}", name),
+            CsvError::MissingColumn(column) => write!(f, "The {} column is required", column.name()),
+            CsvError::InvalidValue(line, column) => write!(f, "Line {}: invalid {}", line, column.name()),
+            _ => f.write_str(self.description())
+        }
+    }
+
+}
+
+impl StdError for CsvError {
+
+    fn description(&self) -> &str {
+        match *self {
+            CsvError::MissingHeader => "The file has no header row",
+            CsvError::UnknownColumn(_) => "Unknown column",
+            CsvError::MissingColumn(_) => "A required column is missing",
+            CsvError::InvalidValue(..) => "A value is invalid",
+            CsvError::UnclosedQuote => "A quoted field is not closed"
+        }
+    }
+
+}
+
+/// Write transactions, looking up the account columns in `accounts`.
+pub fn write_transactions(transactions: &[Transaction], accounts: &[Account], options: &Options) -> String {
+    let accounts: HashMap<&str, &Account> = accounts.iter().map(|a| (a.id.as_str(), a)).collect();
+    let mut out = String::new();
+    if options.header {
+        row(&mut out, options.columns.iter().map(|c| c.name().to_string()), options.delimiter);
+    }
+    for t in transactions {
+        let account = accounts.get(t.account_id.as_str()).cloned();
+        row(&mut out, options.columns.iter().map(|c| value(t, account, *c, options)), options.delimiter);
+    }
+    out
+}
+
+/// Write accounts.
+pub fn write_accounts(accounts: &[Account], options: &Options) -> String {
+    let mut out = String::new();
+    if options.header {
+        row(&mut out, options.account_columns.iter().map(|c| c.name().to_string()), options.delimiter);
+    }
+    for a in accounts {
+        let meta = a.meta.as_ref();
+        row(&mut out, options.account_columns.iter().map(|c| match *c {
+            AccountColumn::Id => a.id.clone(),
+            AccountColumn::Name => meta.and_then(|m| m.name.clone()).unwrap_or_default(),
+            AccountColumn::Mask => meta.and_then(|m| m.number.clone()).unwrap_or_default(),
+            AccountColumn::Type => a.account_type.clone(),
+            AccountColumn::Subtype => a.account_subtype.clone().unwrap_or_default(),
+            AccountColumn::Institution => a.institution.clone(),
+            AccountColumn::CurrentBalance => amount(a.current_balance),
+            AccountColumn::AvailableBalance => a.available_balance.map(amount).unwrap_or_default(),
+            AccountColumn::Limit => meta.and_then(|m| m.limit).map(amount).unwrap_or_default()
+        }), options.delimiter);
+    }
+    out
+}
+
+fn value(t: &Transaction, account: Option<&Account>, column: Column, options: &Options) -> String {
+    let location = t.meta.as_ref().map(|m| &m.location);
+    let account_meta = account.and_then(|a| a.meta.as_ref());
+    match column {
+        Column::Id => t.id.clone(),
+        Column::Date => Day::parse(&t.date).map(|d| options.date_format.format(&d)).unwrap_or_else(|| t.date.clone()),
+        Column::Amount => amount(options.sign.apply(t.amount)),
+        Column::Name => t.name.clone().unwrap_or_default(),
+        Column::OriginalDescription => t.original_description.clone().unwrap_or_default(),
+        Column::Categories => t.categories.join(&options.category_separator),
+        Column::CategoryId => t.category_id.to_string(),
+        Column::Context => context_name(&t.context).to_string(),
+        Column::Pending => t.pending.to_string(),
+        Column::AccountId => t.account_id.clone(),
+        Column::AccountName => account_meta.and_then(|m| m.name.clone()).unwrap_or_default(),
+        Column::AccountMask => account_meta.and_then(|m| m.number.clone()).unwrap_or_default(),
+        Column::Street => location.and_then(|l| l.street.clone()).unwrap_or_default(),
+        Column::City => location.and_then(|l| l.city.clone()).unwrap_or_default(),
+        Column::State => location.and_then(|l| l.state.clone()).unwrap_or_default(),
+        Column::Zip => location.and_then(|l| l.zip.clone()).unwrap_or_default(),
+        Column::Latitude => location.and_then(|l| l.latitude).map(|l| l.to_string()).unwrap_or_default(),
+        Column::Longitude => location.and_then(|l| l.longitude).map(|l| l.to_string()).unwrap_or_default()
+    }
+}
+
+fn context_name(context: &Context) -> &'static str {
+    match *context {
+        Context::Place => "place",
+        Context::Digital => "digital",
+        Context::Special => "special",
+        Context::Unresolved => "unresolved"
+    }
+}
+
+fn row<I: Iterator<Item = String>>(out: &mut String, fields: I, delimiter: char) {
+    let fields: Vec<String> = fields.map(|f| {
+        if f.contains(delimiter) || f.contains('"') || f.contains('\n') || f.contains('\r') {
+            format!("\"{}\"", f.replace('"', "\"\""))
+        } else {
+            f
+        }
+    }).collect();
+    out.push_str(&fields.join(&delimiter.to_string()));
+    out.push('\n');
+}
+
+/// Split a file into rows of fields, with the line number that each row starts on.
+fn parse_rows(input: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, CsvError> {
+    let mut rows = Vec::new();
+    let (mut fields, mut field) = (Vec::new(), String::new());
+    let (mut line, mut start, mut quoted) = (1, 1, false);
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' { line += 1 }
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => { chars.next(); field.push('"') },
+                '"' => quoted = false,
+                _ => field.push(c)
+            }
+        } else if c == '"' && field.is_empty() {
+            quoted = true;
+        } else if c == delimiter {
+            fields.push(::std::mem::take(&mut field));
+        } else if c == '\n' {
+            fields.push(::std::mem::take(&mut field));
+            rows.push((start, ::std::mem::take(&mut fields)));
+            start = line;
+        } else if c != '\r' {
+            field.push(c);
+        }
+    }
+    if quoted { return Err(CsvError::UnclosedQuote) }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        rows.push((start, fields));
+    }
+    Ok(rows.into_iter().filter(|row| !(row.1.len() == 1 && row.1[0].is_empty())).collect())
+}
+
+/// Read transactions from a file with a header row. The `date` and `amount`
+/// columns are required, account name and mask columns are ignored, and a
+/// transaction without an `id` is given one from its line number, e.g `csv-2`.
+pub fn read_transactions(input: &str, options: &Options) -> Result<Vec<Transaction>, CsvError> {
+    let mut rows = try!(parse_rows(input, options.delimiter)).into_iter();
+    let header = match rows.next() {
+        Some((_, header)) => header,
+        None => return Err(CsvError::MissingHeader)
+    };
+    let mut columns = Vec::new();
+    for name in header {
+        match Column::parse(&name) {
+            Some(column) => columns.push(column),
+            None => return Err(CsvError::UnknownColumn(name))
+        }
+    }
+    for required in &[Column::Date, Column::Amount] {
+        if !columns.contains(required) { return Err(CsvError::MissingColumn(*required)) }
+    }
+
+    let mut transactions = Vec::new();
+    for (line, fields) in rows {
+        let get = |column: Column| columns.iter().position(|c| *c == column)
+            .and_then(|i| fields.get(i))
+            .map(|f| f.trim())
+            .filter(|f| !f.is_empty());
+        let text = |column: Column| get(column).map(|f| f.to_string());
+        let invalid = |column: Column| CsvError::InvalidValue(line, column);
+
+        let date = try!(get(Column::Date).and_then(|d| options.date_format.parse(d)).ok_or_else(|| invalid(Column::Date)));
+        let amount: Amount = try!(get(Column::Amount).and_then(|a| a.parse().ok()).ok_or_else(|| invalid(Column::Amount)));
+        let number = |column: Column| -> Result<Option<f64>, CsvError> {
+            match get(column) {
+                Some(v) => v.parse().map(Some).map_err(|_| invalid(column)),
+                None => Ok(None)
+            }
+        };
+        let location = Address {
+            street: text(Column::Street),
+            city: text(Column::City),
+            state: text(Column::State),
+            zip: text(Column::Zip),
+            latitude: try!(number(Column::Latitude)),
+            longitude: try!(number(Column::Longitude)),
+            raw_json: RawObject::default()
+        };
+        let has_location = location.street.is_some() || location.city.is_some() || location.state.is_some() ||
+            location.zip.is_some() || location.latitude.is_some() || location.longitude.is_some();
+
+        transactions.push(Transaction {
+            id: text(Column::Id).unwrap_or_else(|| format!("csv-{}", line)),
+            account_id: text(Column::AccountId).unwrap_or_default(),
+            amount: options.sign.revert(amount),
+            category_id: match get(Column::CategoryId) {
+                Some(id) => try!(id.parse().map_err(|_| invalid(Column::CategoryId))),
+                None => 0
+            },
+            context: match get(Column::Context) {
+                Some("place") => Context::Place,
+                Some("digital") => Context::Digital,
+                Some("special") => Context::Special,
+                _ => Context::Unresolved
+            },
+            categories: get(Column::Categories)
+                .map(|c| c.split(options.category_separator.as_str()).map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default(),
+            pending: match get(Column::Pending) {
+                Some("true") => true,
+                Some("false") | None => false,
+                Some(_) => return Err(invalid(Column::Pending))
+            },
+            date: date.to_date(),
+            name: text(Column::Name),
+            original_description: text(Column::OriginalDescription),
+            score: None,
+            pending_transaction_id: None,
+            meta: if has_location { Some(Meta { location: location }) } else { None },
+            raw_json: RawObject::default()
+        });
+    }
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use export::csv::*;
+    use export::tests::data;
+    use export::{ DateFormat, Sign };
+    use api::product::BalanceData;
+    use rustc_serialize::json;
+
+    #[test]
+    fn test_write_transactions() {
+        let data = data();
+        let written = write_transactions(&data.transactions, &data.accounts, &Options::default());
+        assert_eq!(written, "date,name,amount,categories,account_mask\n\
+                             2016-06-02,ATM Deposit,700.00,Transfer:Deposit:ATM,0655\n\
+                             2016-03-12,Golden Crepes,-12.74,Food and Drink:Restaurants,0655\n");
+
+        let options = Options { columns: vec![Column::Date, Column::Amount, Column::Street, Column::Latitude],
+                                sign: Sign::Plaid, date_format: DateFormat::MonthDayYear, delimiter: ';',
+                                header: false, .. Options::default() };
+        let written = write_transactions(&data.transactions, &data.accounts, &options);
+        assert_eq!(written.lines().nth(1), Some("03/12/2016;12.74;262 W 15th St;40.740352"));
+    }
+
+    #[test]
+    fn test_write_accounts_of_any_product() {
+        let data: BalanceData = json::decode(include_str!("../../fixtures/post_balance_success.json")).unwrap();
+        let written = write_accounts(&data.accounts, &Options::default());
+        assert_eq!(written.lines().next(), Some("name,mask,type,current_balance,available_balance"));
+        assert_eq!(written.lines().count(), data.accounts.len() + 1);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data = data();
+        let options = Options { columns: vec![Column::Id, Column::Date, Column::Amount, Column::Name,
+                                              Column::OriginalDescription, Column::Categories, Column::CategoryId,
+                                              Column::Context, Column::Pending, Column::AccountId, Column::City,
+                                              Column::Latitude],
+                                .. Options::default() };
+        let read = read_transactions(&write_transactions(&data.transactions, &data.accounts, &options), &options).unwrap();
+        assert_eq!(read.len(), 2);
+        for (original, read) in data.transactions.iter().zip(read.iter()) {
+            assert_eq!(read.id, original.id);
+            assert_eq!(read.amount, original.amount);
+            assert_eq!(read.date, original.date);
+            assert_eq!(read.categories, original.categories);
+            assert_eq!(read.category_id, original.category_id);
+            assert_eq!(read.context, original.context);
+            assert_eq!(read.name, original.name);
+            assert_eq!(read.meta.as_ref().unwrap().location.city, original.meta.as_ref().unwrap().location.city);
+        }
+        assert_eq!(read[1].meta.as_ref().unwrap().location.latitude, Some(40.740352));
+    }
+
+    #[test]
+    fn test_read_hand_written() {
+        let input = "date,amount,name,categories\n\
+                     2016-03-01,-1500,\"Payroll, Inc\",Transfer:Payroll\r\n\
+                     \n\
+                     2016-03-02,4.5,\"Joe's \"\"Coffee\"\"\",\n";
+        let read = read_transactions(input, &Options::default()).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].amount, 1500.0);
+        assert_eq!(read[0].name, Some("Payroll, Inc".to_string()));
+        assert_eq!(read[0].categories, vec!["Transfer".to_string(), "Payroll".to_string()]);
+        assert_eq!(read[1].id, "csv-4");
+        assert_eq!(read[1].name, Some("Joe's \"Coffee\"".to_string()));
+        assert!(read[1].categories.is_empty());
+
+        assert_eq!(read_transactions("name\nx\n", &Options::default()).unwrap_err(), CsvError::MissingColumn(Column::Date));
+        assert_eq!(read_transactions("date,amount\n2016-03-01,abc\n", &Options::default()).unwrap_err(),
+                   CsvError::InvalidValue(2, Column::Amount));
+        assert_eq!(read_transactions("date,amount,nope\n", &Options::default()).unwrap_err(),
+                   CsvError::UnknownColumn("nope".to_string()));
+    }
+
+}
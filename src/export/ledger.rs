@@ -0,0 +1,393 @@
+//! Writes `Connect` data as a Ledger, hledger or Beancount journal.
+//!
+//! Every account is mapped to an account path, by default
+//! `Assets:<Institution>:<Name>` (or `Liabilities` for credit and loan accounts)
+//! where the name is the account's `meta.name`, else its subtype or type. Paths
+//! can be overridden per account in [`Options`][Options]. Each transaction is an
+//! entry that moves money between its account and a counter-account made from
+//! its category hierarchy, e.g `Expenses:Food and Drink:Restaurants`, or under
+//! `Income` when money comes in.
+//!
+//! Entries carry the transaction's `_id` as `plaid_id` metadata, balance
+//! assertions the account id and day as `plaid_balance`, and Beancount `open`
+//! directives the account id, or the path of a counter-account, as
+//! `plaid_account`. Use
+//! [`render_new`][render_new] with an existing journal to only write what it does
+//! not have yet, so that exporting after every sync never duplicates entries.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! use plaid::api::product::ConnectData;
+//! use plaid::api::data::date::Day;
+//! use plaid::export::ledger::{ self, Format, Options };
+//!
+//! let data: ConnectData = json::decode(include_str!("fixtures/post_connect_success.json")).unwrap();
+//! let options = Options { as_of: Day::new(2016, 6, 3).unwrap(), .. Options::default() };
+//! let journal = ledger::render(&data, &options);
+//! assert!(journal.contains("Assets:Chase:Total Checking  0.00 USD = 742.93 USD"));
+//!
+//! // Exporting the same data again adds nothing.
+//! assert_eq!(ledger::render_new(&data, &journal, &options), "");
+//!
+//! let beancount = ledger::render(&data, &Options { format: Format::Beancount, .. options });
+//! assert!(beancount.contains("2016-06-04 balance Assets:Chase:TotalChecking  742.93 USD"));
+//! # }
+//! ```
+//!
+//! [Options]: ./struct.Options.html
+//! [render_new]: ./fn.render_new.html
+
+use std::collections::{ HashMap, HashSet };
+use std::fmt::Write;
+
+use api::data::date::Day;
+use api::product::ConnectData;
+use api::types::{ Account, Transaction, UID };
+use export::amount;
+
+/// The syntax of a journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Ledger syntax, which hledger also reads.
+    Ledger,
+    /// Beancount syntax.
+    Beancount
+}
+
+/// How a journal is written.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The syntax of the journal.
+    pub format: Format,
+    /// The commodity of every amount.
+    pub currency: String,
+    /// The day that balances are asserted for. Beancount checks a balance at the
+    /// start of a day, so its assertions are dated the day after.
+    pub as_of: Day,
+    /// Account paths to use instead of the default, by account id.
+    pub accounts: HashMap<UID, String>,
+    /// The root of depository and other accounts.
+    pub assets: String,
+    /// The root of credit and loan accounts.
+    pub liabilities: String,
+    /// The root of counter-accounts that money comes in from.
+    pub income: String,
+    /// The root of counter-accounts that money goes out to.
+    pub expenses: String,
+    /// Whether pending transactions are included, they are flagged with `!`.
+    pub include_pending: bool
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            format: Format::Ledger,
+            currency: "USD".to_string(),
+            as_of: Day::today(),
+            accounts: HashMap::new(),
+            assets: "Assets".to_string(),
+            liabilities: "Liabilities".to_string(),
+            income: "Income".to_string(),
+            expenses: "Expenses".to_string(),
+            include_pending: false
+        }
+    }
+
+}
+
+/// The metadata key that holds the `_id` of a transaction.
+pub const ID_KEY: &'static str = "plaid_id";
+
+/// The metadata key that holds the account id and day of a balance assertion.
+pub const BALANCE_KEY: &'static str = "plaid_balance";
+
+/// The metadata key that holds the account id of a Beancount `open` directive,
+/// or the path of a counter-account.
+pub const ACCOUNT_KEY: &'static str = "plaid_account";
+
+/// Whether money owed on the account is a liability.
+fn is_liability(account: &Account) -> bool {
+    account.account_type == "credit" || account.account_type == "loan"
+}
+
+/// The path of an account in the journal.
+pub fn account_path(account: &Account, options: &Options) -> String {
+    if let Some(path) = options.accounts.get(&account.id) {
+        return path.clone()
+    }
+    let root = if is_liability(account) { &options.liabilities } else { &options.assets };
+    let name = account.meta.as_ref().and_then(|m| m.name.clone())
+        .unwrap_or_else(|| capitalize(account.account_subtype.as_ref().unwrap_or(&account.account_type)));
+    path(options.format, root, &[capitalize(&account.institution), name])
+}
+
+/// The path of the account that a transaction moves money to or from, from its categories.
+pub fn counter_account(transaction: &Transaction, options: &Options) -> String {
+    let root = if transaction.amount < 0.0 { &options.income } else { &options.expenses };
+    if transaction.categories.is_empty() {
+        path(options.format, root, &["Uncategorized".to_string()])
+    } else {
+        path(options.format, root, &transaction.categories)
+    }
+}
+
+fn path(format: Format, root: &str, components: &[String]) -> String {
+    let mut path = root.to_string();
+    for c in components {
+        let c = component(format, c);
+        if !c.is_empty() {
+            path.push(':');
+            path.push_str(&c);
+        }
+    }
+    path
+}
+
+/// A valid component of an account path. Ledger ends an account at two spaces and
+/// separates components with `:`, while Beancount only allows letters, digits and
+/// `-`, starting with a capital letter.
+fn component(format: Format, name: &str) -> String {
+    let words = name.split(|c: char| c.is_whitespace() || c == ':').filter(|w| !w.is_empty());
+    match format {
+        Format::Ledger => words.collect::<Vec<_>>().join(" "),
+        Format::Beancount => {
+            let c: String = words.map(|w| capitalize(&w.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect::<String>()))
+                .collect();
+            match c.chars().next() {
+                Some(first) if !first.is_ascii_alphabetic() => format!("X{}", c),
+                _ => c
+            }
+        }
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new()
+    }
+}
+
+/// Render a journal of every account in `data` and its transactions.
+pub fn render(data: &ConnectData, options: &Options) -> String {
+    render_new(data, "", options)
+}
+
+/// Render only the entries, balance assertions and directives that are not
+/// already in the `existing` journal, so that the result can be appended to it.
+pub fn render_new(data: &ConnectData, existing: &str, options: &Options) -> String {
+    let seen = exported_ids(existing);
+    let accounts: HashMap<&str, &Account> = data.accounts.iter().map(|a| (a.id.as_str(), a)).collect();
+    let account_of = |id: &UID| match accounts.get(id.as_str()) {
+        Some(account) => account_path(account, options),
+        None => options.accounts.get(id).cloned()
+            .unwrap_or_else(|| path(options.format, &options.assets, ::std::slice::from_ref(id)))
+    };
+
+    let mut transactions: Vec<&Transaction> = data.transactions.iter()
+        .filter(|t| options.include_pending || !t.pending)
+        .filter(|t| !seen.contains(&t.id))
+        .filter(|t| Day::parse(&t.date).is_some())
+        .collect();
+    transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+
+    let mut out = String::new();
+    if options.format == Format::Beancount {
+        // Every account that is posted to is opened, keyed by its id or, for
+        // counter-accounts, its path. Transactions are sorted, so each account
+        // is opened on the day it is first used.
+        let first = transactions.iter().filter_map(|t| Day::parse(&t.date)).min().unwrap_or(options.as_of);
+        let mut opens: Vec<(String, String, Day)> = data.accounts.iter()
+            .map(|a| (a.id.clone(), account_path(a, options), first))
+            .collect();
+        for t in &transactions {
+            let day = Day::parse(&t.date).unwrap_or(first);
+            let counter = counter_account(t, options);
+            for (key, path) in [(t.account_id.clone(), account_of(&t.account_id)), (counter.clone(), counter)] {
+                if !opens.iter().any(|o| o.0 == key) { opens.push((key, path, day)) }
+            }
+        }
+        for (key, path, day) in opens {
+            if seen.contains(&format!("{}:{}", ACCOUNT_KEY, key)) { continue }
+            let _ = writeln!(out, "{} open {} {}\n  {}: \"{}\"\n",
+                             day, path, options.currency, ACCOUNT_KEY, quote(&key));
+        }
+    }
+
+    for t in transactions {
+        entry(&mut out, t, &account_of(&t.account_id), options);
+    }
+
+    for account in &data.accounts {
+        let key = format!("{}/{}", account.id, options.as_of);
+        if seen.contains(&key) { continue }
+        let balance = if is_liability(account) { -account.current_balance } else { account.current_balance };
+        let path = account_path(account, options);
+        match options.format {
+            Format::Ledger => {
+                let _ = writeln!(out, "{} * Balance assertion\n    ; {}: {}\n    {}  0.00 {} = {} {}\n",
+                                 options.as_of, BALANCE_KEY, key, path, options.currency, amount(balance), options.currency);
+            },
+            Format::Beancount => {
+                let _ = writeln!(out, "{} balance {}  {} {}\n  {}: \"{}\"\n",
+                                 options.as_of.add_days(1), path, amount(balance), options.currency,
+                                 BALANCE_KEY, quote(&key));
+            }
+        }
+    }
+    out
+}
+
+fn entry(out: &mut String, t: &Transaction, account: &str, options: &Options) {
+    let flag = if t.pending { "!" } else { "*" };
+    let day = Day::parse(&t.date).unwrap_or(options.as_of);
+    let name = t.name.as_ref().or(t.original_description.as_ref()).cloned().unwrap_or_default();
+    let counter = counter_account(t, options);
+    match options.format {
+        Format::Ledger => {
+            let _ = writeln!(out, "{} {} {}\n    ; {}: {}\n    {}  {} {}\n    {}\n",
+                             day, flag, name.replace('\n', " "), ID_KEY, t.id,
+                             account, amount(-t.amount), options.currency, counter);
+        },
+        Format::Beancount => {
+            let _ = writeln!(out, "{} {} \"{}\"\n  {}: \"{}\"\n  {}  {} {}\n  {}\n",
+                             day, flag, quote(&name), ID_KEY, quote(&t.id),
+                             account, amount(-t.amount), options.currency, counter);
+        }
+    }
+}
+
+fn quote(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', " ")
+}
+
+/// The transaction ids, balance assertion keys and opened accounts (as
+/// `plaid_account:<id>`) that are recorded in the metadata of a journal.
+pub fn exported_ids(journal: &str) -> HashSet<String> {
+    let mut ids = HashSet::new();
+    for line in journal.lines() {
+        let line = line.trim_start().trim_start_matches(';').trim();
+        for key in &[ID_KEY, BALANCE_KEY, ACCOUNT_KEY] {
+            if line.starts_with(key) && line[key.len()..].starts_with(':') {
+                let value = line[key.len() + 1..].trim().trim_matches('"').replace("\\\"", "\"").replace("\\\\", "\\");
+                if *key == ACCOUNT_KEY {
+                    ids.insert(format!("{}:{}", ACCOUNT_KEY, value));
+                } else {
+                    ids.insert(value);
+                }
+            }
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+
+    use export::ledger::*;
+    use export::tests::data;
+    use api::data::date::Day;
+
+    fn options(format: Format) -> Options {
+        Options { format: format, as_of: Day::new(2016, 6, 3).unwrap(), .. Options::default() }
+    }
+
+    #[test]
+    fn test_render_ledger() {
+        let journal = render(&data(), &options(Format::Ledger));
+        assert_eq!(journal.split("\n\n").next().unwrap(), "\
+2016-03-12 * Golden Crepes
+    ; plaid_id: testtransactionid2
+    Assets:Chase:Total Checking  -12.74 USD
+    Expenses:Food and Drink:Restaurants");
+        assert!(journal.contains("\
+2016-06-02 * ATM Deposit
+    ; plaid_id: testtransactionid
+    Assets:Chase:Total Checking  700.00 USD
+    Income:Transfer:Deposit:ATM\n"));
+        assert!(journal.contains("\
+2016-06-03 * Balance assertion
+    ; plaid_balance: testaccount2id/2016-06-03
+    Assets:Chase:Depository  0.00 USD = 100030.32 USD\n"));
+    }
+
+    #[test]
+    fn test_render_beancount() {
+        let journal = render(&data(), &options(Format::Beancount));
+        assert!(journal.starts_with("\
+2016-03-12 open Assets:Chase:TotalChecking USD
+  plaid_account: \"testaccountid\"\n"));
+        assert!(journal.contains("\
+2016-03-12 * \"Golden Crepes\"
+  plaid_id: \"testtransactionid2\"
+  Assets:Chase:TotalChecking  -12.74 USD
+  Expenses:FoodAndDrink:Restaurants\n"));
+        assert!(journal.contains("2016-06-04 balance Assets:Chase:Depository  100030.32 USD\n"));
+    }
+
+    #[test]
+    fn test_beancount_opens_every_account() {
+        let mut data = data();
+        data.transactions[0].account_id = "unknownaccountid".to_string();
+        data.transactions[1].categories = vec![];
+        let journal = render(&data, &options(Format::Beancount));
+
+        let opened: Vec<&str> = journal.lines()
+            .filter(|l| l.contains(" open "))
+            .filter_map(|l| l.split_whitespace().nth(2))
+            .collect();
+        let posted: Vec<&str> = journal.lines()
+            .filter(|l| l.starts_with("  "))
+            .filter_map(|l| l.split_whitespace().next())
+            .filter(|a| !a.ends_with(':'))
+            .collect();
+        assert_eq!(posted.len(), 4);
+        for account in posted {
+            assert!(opened.contains(&account), "{} is not opened", account);
+        }
+        assert!(journal.contains("2016-03-12 open Expenses:Uncategorized USD\n  plaid_account: \"Expenses:Uncategorized\""));
+        assert!(journal.contains("2016-06-02 open Assets:Unknownaccountid USD"));
+    }
+
+    #[test]
+    fn test_render_new_is_idempotent() {
+        for format in &[Format::Ledger, Format::Beancount] {
+            let mut data = data();
+            let options = options(*format);
+            let first = render(&data, &options);
+            assert_eq!(render_new(&data, &first, &options), "");
+
+            let mut t = data.transactions[0].clone();
+            t.id = "newtransaction\"id".to_string();
+            data.transactions.push(t);
+            let second = render_new(&data, &first, &options);
+            assert_eq!(second.matches(ID_KEY).count(), 1);
+            assert_eq!(render_new(&data, &format!("{}{}", first, second), &options), "");
+        }
+    }
+
+    #[test]
+    fn test_account_paths() {
+        let mut data = data();
+        let mut options = options(Format::Beancount);
+        data.accounts[0].account_type = "credit".to_string();
+        data.accounts[0].current_balance = 50.0;
+        assert_eq!(account_path(&data.accounts[0], &options), "Liabilities:Chase:TotalChecking");
+        assert!(render(&data, &options).contains("balance Liabilities:Chase:TotalChecking  -50.00 USD"));
+
+        options.accounts.insert("testaccountid".to_string(), "Liabilities:Visa".to_string());
+        assert_eq!(account_path(&data.accounts[0], &options), "Liabilities:Visa");
+
+        data.transactions[0].categories = vec![];
+        data.transactions[0].amount = 3.0;
+        assert_eq!(counter_account(&data.transactions[0], &options), "Expenses:Uncategorized");
+    }
+
+}
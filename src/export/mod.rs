@@ -0,0 +1,122 @@
+//! Exporters that write accounts and transactions in formats understood by
+//! accounting and personal finance tools.
+//!
+//! + **[`ofx`][ofx]** writes OFX 2.x and QFX statements, one per account.
+//! + **[`ledger`][ledger]** writes plain text journals for Ledger, hledger and Beancount.
+//! + **[`csv`][csv]** writes and reads transactions and accounts as CSV, with configurable columns.
+//! + **[`qif`][qif]** writes QIF files for tools that do not read OFX.
+//!
+//! [ofx]: ./ofx/index.html
+//! [ledger]: ./ledger/index.html
+//! [csv]: ./csv/index.html
+//! [qif]: ./qif/index.html
+
+use api::data::Amount;
+use api::data::date::Day;
+
+pub mod ofx;
+pub mod ledger;
+pub mod csv;
+pub mod qif;
+
+/// Which direction of money a positive amount stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sign {
+    /// Plaid's convention, a positive amount is money leaving the account.
+    Plaid,
+    /// A positive amount is money coming into the account, as most tools expect.
+    Natural
+}
+
+impl Sign {
+
+    /// Convert a Plaid amount to this convention.
+    pub fn apply(&self, amount: Amount) -> Amount {
+        match *self {
+            Sign::Plaid => amount,
+            Sign::Natural => -amount
+        }
+    }
+
+    /// Convert an amount in this convention back to a Plaid amount.
+    pub fn revert(&self, amount: Amount) -> Amount {
+        self.apply(amount)
+    }
+
+}
+
+/// How days are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    /// ISO 8601, e.g `2016-06-02`.
+    Iso,
+    /// US style, e.g `06/02/2016`.
+    MonthDayYear,
+    /// e.g `02/06/2016`.
+    DayMonthYear
+}
+
+impl DateFormat {
+
+    /// Format a day.
+    pub fn format(&self, day: &Day) -> String {
+        match *self {
+            DateFormat::Iso => day.to_date(),
+            DateFormat::MonthDayYear => format!("{:02}/{:02}/{:04}", day.month, day.day, day.year),
+            DateFormat::DayMonthYear => format!("{:02}/{:02}/{:04}", day.day, day.month, day.year)
+        }
+    }
+
+    /// Parse a day that was written in this format.
+    pub fn parse(&self, s: &str) -> Option<Day> {
+        let parts: Vec<&str> = s.trim().split('/').collect();
+        let (month, day, year) = match (*self, parts.len()) {
+            (DateFormat::Iso, _) => return Day::parse(s),
+            (DateFormat::MonthDayYear, 3) => (parts[0], parts[1], parts[2]),
+            (DateFormat::DayMonthYear, 3) => (parts[1], parts[0], parts[2]),
+            _ => return None
+        };
+        match (year.parse(), month.parse(), day.parse()) {
+            (Ok(y), Ok(m), Ok(d)) => Day::new(y, m, d),
+            _ => None
+        }
+    }
+
+}
+
+/// Format an amount with two decimals, without a negative zero.
+fn amount(value: Amount) -> String {
+    let s = format!("{:.2}", value);
+    if s == "-0.00" { "0.00".to_string() } else { s }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use export::*;
+    use api::data::date::Day;
+    use api::product::ConnectData;
+    use rustc_serialize::json;
+
+    /// The fixture's transactions refer to an account that it does not include,
+    /// so they are moved to its first account.
+    pub fn data() -> ConnectData {
+        let mut data: ConnectData = json::decode(include_str!("../../fixtures/post_connect_success.json")).unwrap();
+        for t in data.transactions.iter_mut() {
+            t.account_id = "testaccountid".to_string();
+        }
+        data
+    }
+
+    #[test]
+    fn test_date_formats() {
+        let day = Day::new(2016, 6, 2).unwrap();
+        for format in &[DateFormat::Iso, DateFormat::MonthDayYear, DateFormat::DayMonthYear] {
+            assert_eq!(format.parse(&format.format(&day)), Some(day));
+        }
+        assert_eq!(DateFormat::MonthDayYear.format(&day), "06/02/2016");
+        assert_eq!(DateFormat::DayMonthYear.parse("31/02/2016"), None);
+        assert_eq!(Sign::Natural.apply(12.5), -12.5);
+    }
+
+}
@@ -0,0 +1,394 @@
+//! Writes `Connect` data as an OFX 2.x document, or as a QFX document for Quicken.
+//!
+//! Each account becomes a statement: depository and other accounts a bank
+//! statement, and credit accounts a credit card statement. Plaid's amounts are
+//! positive when money leaves an account, so their sign is flipped, and the
+//! balance of a credit account is written as a negative amount owed.
+//!
+//! `BANKID` is required for bank statements. It is the routing number of the
+//! account, as returned by `Auth`, or else the `routing_number` option. As
+//! `Connect` does not return routing numbers, rendering its bank accounts without
+//! the option fails with [`OfxError::MissingBankId`][MissingBankId].
+//!
+//! The `FITID` of each transaction is its Plaid `_id`, so that importing an
+//! overlapping export again does not duplicate transactions. Pending
+//! transactions are left out by default, as their ids change once they post.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! use plaid::api::product::ConnectData;
+//! use plaid::api::data::date::Day;
+//! use plaid::export::ofx::{ self, Options };
+//!
+//! let data: ConnectData = json::decode(include_str!("fixtures/post_connect_success.json")).unwrap();
+//! let options = Options { generated: Day::new(2016, 6, 3).unwrap(),
+//!                         routing_number: Some("021000021".into()),
+//!                         .. Options::default() };
+//! let document = ofx::render(&data, &options).unwrap();
+//!
+//! assert!(document.contains("<ACCTID>0655</ACCTID>"));
+//! assert!(document.contains("<BALAMT>742.93</BALAMT>"));
+//! # }
+//! ```
+//!
+//! [MissingBankId]: ./enum.OfxError.html#variant.MissingBankId
+
+use std::error::Error as StdError;
+use std::fmt::{ self, Write };
+
+use api::data::date::Day;
+use api::product::ConnectData;
+use api::types::{ Account, RoutingNumber, Transaction, UID };
+use api::data::transaction::Context;
+use export::amount;
+
+/// The institution that a document claims to be from, written to the sign on response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinancialInstitution {
+    /// The name of the institution, `ORG`.
+    pub org: String,
+    /// The OFX id of the institution, `FID`.
+    pub fid: String
+}
+
+/// The kind of document to write.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Flavor {
+    /// A standard OFX 2.x document.
+    Ofx,
+    /// A QFX document, with the Intuit bank id that Quicken uses to identify the institution.
+    Qfx(String)
+}
+
+/// How a document is written.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The kind of document.
+    pub flavor: Flavor,
+    /// The ISO 4217 currency of every amount.
+    pub currency: String,
+    /// The day that the document is generated, written as `DTSERVER`.
+    pub generated: Day,
+    /// The institution, which Quicken requires for QFX.
+    pub institution: Option<FinancialInstitution>,
+    /// The routing number of the institution, written as `BANKID` for bank accounts
+    /// whose own routing number is not known.
+    pub routing_number: Option<RoutingNumber>,
+    /// Whether pending transactions are included.
+    pub include_pending: bool
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            flavor: Flavor::Ofx,
+            currency: "USD".to_string(),
+            generated: Day::today(),
+            institution: None,
+            routing_number: None,
+            include_pending: false
+        }
+    }
+
+}
+
+/// # OfxError
+/// The reasons that a document can not be written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OfxError {
+    /// These bank accounts have no routing number to write as `BANKID`, neither
+    /// their own nor the `routing_number` option.
+    MissingBankId(Vec<UID>)
+}
+
+impl fmt::Display for OfxError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OfxError::MissingBankId(ref ids) => write!(f, "No routing number for the accounts {}", ids.join(", "))
+        }
+    }
+
+}
+
+impl StdError for OfxError {
+
+    fn description(&self) -> &str {
+        match *self {
+            OfxError::MissingBankId(_) => "A bank account has no routing number"
+        }
+    }
+
+}
+
+/// The maximum length of a transaction `NAME`, longer names are truncated.
+pub const MAX_NAME_LENGTH: usize = 32;
+
+/// Render every account of `data` and its transactions.
+pub fn render(data: &ConnectData, options: &Options) -> Result<String, OfxError> {
+    render_accounts(&data.accounts, &data.transactions, options)
+}
+
+/// Render the given accounts, each with the transactions that belong to it.
+/// Transactions of other accounts are ignored, so this also works with the
+/// data of products that do not return transactions. Fails if a bank account
+/// has no routing number.
+pub fn render_accounts(accounts: &[Account], transactions: &[Transaction], options: &Options) -> Result<String, OfxError> {
+    let missing: Vec<UID> = accounts.iter()
+        .filter(|a| !is_credit(a) && a.routing_number.is_none() && options.routing_number.is_none())
+        .map(|a| a.id.clone())
+        .collect();
+    if !missing.is_empty() {
+        return Err(OfxError::MissingBankId(missing))
+    }
+
+    let mut bank = String::new();
+    let mut credit = String::new();
+    for account in accounts {
+        let mut own: Vec<&Transaction> = transactions.iter()
+            .filter(|t| t.account_id == account.id && (options.include_pending || !t.pending))
+            .collect();
+        own.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+        if is_credit(account) {
+            statement(&mut credit, account, None, &own, options);
+        } else {
+            let bank_id = account.routing_number.as_ref().or(options.routing_number.as_ref());
+            statement(&mut bank, account, bank_id, &own, options);
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    out.push_str("<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"?>\n");
+    out.push_str("<OFX>\n");
+    out.push_str("  <SIGNONMSGSRSV1>\n    <SONRS>\n");
+    status(&mut out, 6);
+    let _ = writeln!(out, "      <DTSERVER>{}</DTSERVER>", datetime(&options.generated));
+    out.push_str("      <LANGUAGE>ENG</LANGUAGE>\n");
+    if let Some(ref fi) = options.institution {
+        let _ = writeln!(out, "      <FI>\n        <ORG>{}</ORG>\n        <FID>{}</FID>\n      </FI>", escape(&fi.org), escape(&fi.fid));
+    }
+    if let Flavor::Qfx(ref bid) = options.flavor {
+        let _ = writeln!(out, "      <INTU.BID>{}</INTU.BID>", escape(bid));
+    }
+    out.push_str("    </SONRS>\n  </SIGNONMSGSRSV1>\n");
+    if !bank.is_empty() {
+        let _ = write!(out, "  <BANKMSGSRSV1>\n{}  </BANKMSGSRSV1>\n", bank);
+    }
+    if !credit.is_empty() {
+        let _ = write!(out, "  <CREDITCARDMSGSRSV1>\n{}  </CREDITCARDMSGSRSV1>\n", credit);
+    }
+    out.push_str("</OFX>\n");
+    Ok(out)
+}
+
+fn is_credit(account: &Account) -> bool {
+    account.account_type == "credit"
+}
+
+fn statement(out: &mut String, account: &Account, bank_id: Option<&RoutingNumber>,
+             transactions: &[&Transaction], options: &Options) {
+    let credit = is_credit(account);
+    let (trnrs, stmtrs) = if credit { ("CCSTMTTRNRS", "CCSTMTRS") } else { ("STMTTRNRS", "STMTRS") };
+    let _ = writeln!(out, "    <{}>\n      <TRNUID>{}</TRNUID>", trnrs, escape(&account.id));
+    status(out, 6);
+    let _ = writeln!(out, "      <{}>\n        <CURDEF>{}</CURDEF>", stmtrs, escape(&options.currency));
+
+    let acctid = escape(&account_id(account));
+    match bank_id {
+        Some(bank_id) => {
+            let _ = writeln!(out, "        <BANKACCTFROM>\n          <BANKID>{}</BANKID>\n          \
+                                   <ACCTID>{}</ACCTID>\n          <ACCTTYPE>{}</ACCTTYPE>\n        </BANKACCTFROM>",
+                             escape(bank_id.expose()), acctid, account_type(account));
+        },
+        None => {
+            let _ = writeln!(out, "        <CCACCTFROM>\n          <ACCTID>{}</ACCTID>\n        </CCACCTFROM>", acctid);
+        }
+    }
+
+    let days: Vec<Day> = transactions.iter().filter_map(|t| Day::parse(&t.date)).collect();
+    let start = days.iter().min().cloned().unwrap_or(options.generated);
+    let end = days.iter().max().cloned().unwrap_or(options.generated);
+    let _ = writeln!(out, "        <BANKTRANLIST>\n          <DTSTART>{}</DTSTART>\n          <DTEND>{}</DTEND>",
+                     date(&start), date(&end));
+    for t in transactions {
+        transaction(out, t);
+    }
+    out.push_str("        </BANKTRANLIST>\n");
+
+    let sign = if credit { -1.0 } else { 1.0 };
+    let asof = date(&options.generated);
+    let _ = writeln!(out, "        <LEDGERBAL>\n          <BALAMT>{}</BALAMT>\n          <DTASOF>{}</DTASOF>\n        </LEDGERBAL>",
+                     amount(sign * account.current_balance), asof);
+    if let Some(available) = account.available_balance {
+        let _ = writeln!(out, "        <AVAILBAL>\n          <BALAMT>{}</BALAMT>\n          <DTASOF>{}</DTASOF>\n        </AVAILBAL>",
+                         amount(sign * available), asof);
+    }
+    let _ = writeln!(out, "      </{}>\n    </{}>", stmtrs, trnrs);
+}
+
+fn transaction(out: &mut String, t: &Transaction) {
+    let name: String = t.name.as_ref().or(t.original_description.as_ref())
+        .map(|n| n.chars().take(MAX_NAME_LENGTH).collect())
+        .unwrap_or_default();
+    let _ = writeln!(out, "          <STMTTRN>\n            <TRNTYPE>{}</TRNTYPE>\n            <DTPOSTED>{}</DTPOSTED>\n            \
+                           <TRNAMT>{}</TRNAMT>\n            <FITID>{}</FITID>",
+                     transaction_type(t),
+                     Day::parse(&t.date).map(|d| date(&d)).unwrap_or_default(),
+                     amount(-t.amount), escape(&t.id));
+    if !name.is_empty() {
+        let _ = writeln!(out, "            <NAME>{}</NAME>", escape(&name));
+    }
+    let memo = match t.original_description {
+        Some(ref d) => d.clone(),
+        None => t.categories.join(":")
+    };
+    if !memo.is_empty() {
+        let _ = writeln!(out, "            <MEMO>{}</MEMO>", escape(&memo));
+    }
+    out.push_str("          </STMTTRN>\n");
+}
+
+fn status(out: &mut String, indent: usize) {
+    let pad = " ".repeat(indent);
+    let _ = writeln!(out, "{0}<STATUS>\n{0}  <CODE>0</CODE>\n{0}  <SEVERITY>INFO</SEVERITY>\n{0}</STATUS>", pad);
+}
+
+/// The `ACCTID` of an account: its number if it is known, else the last digits
+/// in its meta data, else its Plaid id.
+fn account_id(account: &Account) -> String {
+    if let Some(ref number) = account.account_number {
+        return number.expose().to_string()
+    }
+    account.meta.as_ref().and_then(|m| m.number.clone()).unwrap_or_else(|| account.id.clone())
+}
+
+fn account_type(account: &Account) -> &'static str {
+    match account.account_subtype.as_deref() {
+        Some("savings") => "SAVINGS",
+        Some("cd") => "CD",
+        Some("money market") => "MONEYMRKT",
+        _ if account.account_type == "loan" => "CREDITLINE",
+        _ => "CHECKING"
+    }
+}
+
+/// The `TRNTYPE` of a transaction, from its categories and then its context.
+pub fn transaction_type(t: &Transaction) -> &'static str {
+    let has = |c: &str| t.categories.iter().any(|category| category == c);
+    if has("ATM") { return "ATM" }
+    if has("Bank Fees") { return "FEE" }
+    if has("Interest") { return "INT" }
+    if has("Transfer") { return "XFER" }
+    match t.context {
+        Context::Place => "POS",
+        _ if t.amount < 0.0 => "CREDIT",
+        _ => "DEBIT"
+    }
+}
+
+fn date(day: &Day) -> String {
+    format!("{:04}{:02}{:02}", day.year, day.month, day.day)
+}
+
+fn datetime(day: &Day) -> String {
+    format!("{}000000", date(day))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use export::ofx::*;
+    use export::tests::data;
+    use api::data::date::Day;
+
+    fn options() -> Options {
+        Options {
+            generated: Day::new(2016, 6, 3).unwrap(),
+            routing_number: Some("021000021".into()),
+            .. Options::default()
+        }
+    }
+
+    #[test]
+    fn test_render_ofx_golden() {
+        assert_eq!(render(&data(), &options()).unwrap(), include_str!("../../fixtures/export/connect.ofx"));
+    }
+
+    #[test]
+    fn test_render_qfx_golden() {
+        let options = Options {
+            flavor: Flavor::Qfx("3000".to_string()),
+            institution: Some(FinancialInstitution { org: "Chase".to_string(), fid: "10898".to_string() }),
+            .. options()
+        };
+        assert_eq!(render(&data(), &options).unwrap(), include_str!("../../fixtures/export/connect.qfx"));
+    }
+
+    #[test]
+    fn test_credit_accounts_and_pending() {
+        let mut data = data();
+        data.accounts[0].account_type = "credit".to_string();
+        data.accounts[0].current_balance = 120.5;
+        data.transactions[1].pending = true;
+        data.transactions[0].name = Some("Deposit & <Withdrawal> at a very long location name".to_string());
+
+        let document = render(&data, &options()).unwrap();
+        assert!(document.contains("<CCACCTFROM>\n          <ACCTID>0655</ACCTID>"));
+        assert!(document.contains("<BALAMT>-120.50</BALAMT>"));
+        assert!(!document.contains("testtransactionid2"));
+        assert!(document.contains("<NAME>Deposit &amp; &lt;Withdrawal&gt; at a very</NAME>"));
+
+        let document = render(&data, &Options { include_pending: true, .. options() }).unwrap();
+        assert!(document.contains("<FITID>testtransactionid2</FITID>"));
+    }
+
+    #[test]
+    fn test_bank_id() {
+        let mut data = data();
+        let without = Options { routing_number: None, .. options() };
+        let error = render(&data, &without).unwrap_err();
+        assert_eq!(error, OfxError::MissingBankId(vec!["testaccountid".to_string(), "testaccount2id".to_string()]));
+        assert_eq!(error.to_string(), "No routing number for the accounts testaccountid, testaccount2id");
+
+        data.accounts[0].routing_number = Some("011000015".into());
+        data.accounts[1].account_type = "credit".to_string();
+        let document = render(&data, &without).unwrap();
+        assert!(document.contains("<BANKID>011000015</BANKID>"));
+        assert!(document.contains("<CCACCTFROM>"));
+
+        data.accounts[1].account_type = "depository".to_string();
+        let document = render(&data, &options()).unwrap();
+        assert!(document.contains("<BANKID>011000015</BANKID>"));
+        assert!(document.contains("<BANKID>021000021</BANKID>"));
+    }
+
+    #[test]
+    fn test_account_type() {
+        let mut data = data();
+        data.accounts[0].account_subtype = Some("cd".to_string());
+        let document = render(&data, &options()).unwrap();
+        assert!(document.contains("<ACCTTYPE>CD</ACCTTYPE>"));
+    }
+
+    #[test]
+    fn test_transaction_type_and_sign() {
+        let data = data();
+        assert_eq!(transaction_type(&data.transactions[0]), "ATM");
+        assert_eq!(transaction_type(&data.transactions[1]), "POS");
+        let document = render(&data, &options()).unwrap();
+        assert!(document.contains("<TRNAMT>700.00</TRNAMT>"));
+        assert!(document.contains("<TRNAMT>-12.74</TRNAMT>"));
+    }
+
+}
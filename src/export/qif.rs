@@ -0,0 +1,119 @@
+//! Writes accounts and their transactions as a QIF file.
+//!
+//! Every account is written as an `!Account` block followed by its
+//! transactions, `!Type:CCard` for credit accounts and `!Type:Bank` otherwise.
+//! The category hierarchy of a transaction is written as a QIF category, e.g
+//! `Food and Drink:Restaurants`. Amounts use the `Natural` sign convention and
+//! dates the US format by default, which is what most tools expect of QIF.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! use plaid::api::product::ConnectData;
+//! use plaid::export::qif::{ self, Options };
+//!
+//! let data: ConnectData = json::decode(include_str!("fixtures/post_connect_success.json")).unwrap();
+//! let written = qif::write(&data.accounts, &data.transactions, &Options::default());
+//! assert!(written.starts_with("!Account\nNTotal Checking\nTBank\n"));
+//! # }
+//! ```
+
+use std::fmt::Write;
+
+use api::data::date::Day;
+use api::types::{ Account, Transaction };
+use export::{ amount, DateFormat, Sign };
+
+/// How a file is written.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The sign convention of amounts.
+    pub sign: Sign,
+    /// The format of dates.
+    pub date_format: DateFormat,
+    /// Whether pending transactions are included.
+    pub include_pending: bool
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            sign: Sign::Natural,
+            date_format: DateFormat::MonthDayYear,
+            include_pending: false
+        }
+    }
+
+}
+
+/// Write each account, followed by the transactions that belong to it.
+pub fn write(accounts: &[Account], transactions: &[Transaction], options: &Options) -> String {
+    let mut out = String::new();
+    for account in accounts {
+        let kind = if account.account_type == "credit" { "CCard" } else { "Bank" };
+        let name = account.meta.as_ref().and_then(|m| m.name.clone()).unwrap_or_else(|| account.id.clone());
+        let _ = write!(out, "!Account\nN{}\nT{}\n^\n!Type:{}\n", line(&name), kind, kind);
+
+        let mut own: Vec<&Transaction> = transactions.iter()
+            .filter(|t| t.account_id == account.id && (options.include_pending || !t.pending))
+            .collect();
+        own.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+        for t in own {
+            let date = Day::parse(&t.date).map(|d| options.date_format.format(&d)).unwrap_or_else(|| t.date.clone());
+            let _ = write!(out, "D{}\nT{}\n", date, amount(options.sign.apply(t.amount)));
+            if !t.pending { out.push_str("CX\n") }
+            if let Some(ref name) = t.name {
+                let _ = writeln!(out, "P{}", line(name));
+            }
+            if let Some(ref description) = t.original_description {
+                let _ = writeln!(out, "M{}", line(description));
+            }
+            if !t.categories.is_empty() {
+                let categories: Vec<String> = t.categories.iter().map(|c| line(c).replace(':', " ")).collect();
+                let _ = writeln!(out, "L{}", categories.join(":"));
+            }
+            out.push_str("^\n");
+        }
+    }
+    out
+}
+
+/// A value on a single line, which can not start a new field.
+fn line(s: &str) -> String {
+    s.replace('\r', "").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use export::qif::*;
+    use export::tests::data;
+    use export::{ DateFormat, Sign };
+
+    #[test]
+    fn test_write() {
+        let data = data();
+        assert_eq!(write(&data.accounts, &data.transactions, &Options::default()), "\
+!Account\nNTotal Checking\nTBank\n^\n!Type:Bank
+D03/12/2016\nT-12.74\nCX\nPGolden Crepes\nMGOLDEN CREPES NEW YORK NY\nLFood and Drink:Restaurants\n^
+D06/02/2016\nT700.00\nCX\nPATM Deposit\nLTransfer:Deposit:ATM\n^
+!Account\nNtestaccount2id\nTBank\n^\n!Type:Bank\n");
+    }
+
+    #[test]
+    fn test_options() {
+        let mut data = data();
+        data.accounts[0].account_type = "credit".to_string();
+        data.transactions[1].pending = true;
+        let options = Options { sign: Sign::Plaid, date_format: DateFormat::Iso, include_pending: false };
+        let written = write(&data.accounts, &data.transactions, &options);
+        assert!(written.contains("!Type:CCard\nD2016-06-02\nT-700.00\n"));
+        assert!(!written.contains("Golden Crepes"));
+    }
+
+}
@@ -23,6 +23,8 @@
 //! + **[`Product`][Product]** is a trait that provides the bare minimum definition of a product
 //!   (e.g Connect, Auth, Income) required in order for the [`Client`][Client] to infer
 //!   request/response behavior with.
+//! + **[`builder`][builder]** provides typed builders for every [`Payload`][Payload],
+//!   which validate their options before a request is made.
 //!
 //! *See the [data][data] module for a complete list.*
 //!
@@ -37,6 +39,23 @@
 //! + [Info][info]
 //! + [Income][income]
 //!
+//! # Working with transactions
+//!
+//! Outside of the `api` namespace are modules that work with data once it has been
+//! retrieved from Plaid:
+//!
+//! + **[`reconcile`][reconcile]** compares two fetches of transactions, pairing pending
+//!   transactions with the posted transactions that replace them.
+//! + **[`sync`][sync]** keeps a local copy of each user's accounts and transactions,
+//!   fetching only what has changed since the last sync.
+//! + **[`vault`][vault]** stores the access tokens of users encrypted at rest.
+//! + **[`ach`][ach]** validates `Auth` accounts for ACH payments, and builds NACHA files
+//!   to debit or credit them.
+//! + **[`export`][export]** writes accounts and transactions for accounting tools, as OFX,
+//!   Ledger and Beancount journals, CSV or QIF.
+//! + **[`analytics`][analytics]** analyses transaction history, e.g to detect subscriptions.
+//! + **[`summary`][summary]** totals cash flow per day, week or month and per category.
+//!
 //! # Quick Start
 //!
 //! Add `plaid` as a dependency to `Cargo.toml`:
@@ -79,13 +98,13 @@
 //!     Payload::Authenticate(client,
 //!                           "Chase".to_string(),
 //!                           "username".to_string(),
-//!                           "password".to_string(),
+//!                           "password".into(),
 //!                           None,
 //!                           None));
 //! #
 //! # match response.unwrap() {
 //! #     Response::MFA(ref user, ref challenge) => {
-//! #         assert_eq!(user.access_token, "test".to_string());
+//! #         assert_eq!(user.access_token.expose(), "test");
 //! #         assert_eq!(format!("{:?}", challenge), "Code");
 //! #     },
 //! #     _ => panic!("Unexpected response")
@@ -121,14 +140,14 @@
 //! #     hyper: &hyper
 //! # };
 //! #
-//! let user = User { access_token: "useraccesstoken".to_string() };
+//! let user = User::new("useraccesstoken");
 //! let response = client.request(
 //!     product::Connect,
 //!     Payload::StepMFA(client, user, mfa::Response::Code("1234".to_string())));
 //! #
 //! # match response.unwrap() {
 //! #     Response::Authenticated(ref user, ref data) => {
-//! #         assert_eq!(user.access_token, "test".to_string());
+//! #         assert_eq!(user.access_token.expose(), "test");
 //! #         assert_eq!(data.accounts[0].current_balance, 742.93 as Amount);
 //! #         assert_eq!(data.accounts[1].current_balance, 100030.32 as Amount);
 //! #         assert_eq!(data.transactions[0].amount, -700 as Amount);
@@ -167,7 +186,7 @@
 //! #     hyper: &hyper
 //! # };
 //! #
-//! let user = User { access_token: "useraccesstoken".to_string() };
+//! let user = User::new("useraccesstoken");
 //! let response = client.request(
 //!     product::Connect,
 //!     Payload::FetchData(client, user, Some(FetchDataOptions::default())));
@@ -184,6 +203,62 @@
 //! # }
 //! ```
 //!
+//! Update the webhook or credentials of an existing user:
+//!
+//! ```
+//! # #[macro_use(http_stub)] extern crate plaid;
+//! # #[macro_use] extern crate yup_hyper_mock as hyper_mock;
+//! # extern crate hyper;
+//! #
+//! # fn main() {
+//! #
+//! # http_stub!(StubPolicy, 200, include_str!("fixtures/patch_connect_webhook_success.json"));
+//! # http_stub!(MFAStubPolicy, 201, include_str!("fixtures/post_connect_mfa_code.json"));
+//! #
+//! # let hyper = hyper::Client::with_connector(StubPolicy::default());
+//! # let mfa_hyper = hyper::Client::with_connector(MFAStubPolicy::default());
+//! #
+//! # use plaid::api::product;
+//! # use plaid::api::user::User;
+//! # use plaid::api::client::{ Client, Payload };
+//! # use plaid::api::client::Response;
+//! #
+//! # let client = Client {
+//! #     endpoint: "https://tartan.plaid.com",
+//! #     client_id: "yourclientid",
+//! #     secret: "yourclientsecret",
+//! #     hyper: &hyper
+//! # };
+//! #
+//! let user = User::new("useraccesstoken");
+//! let response = client.request(
+//!     product::Connect,
+//!     Payload::UpdateWebhook(client, user, "https://example.com/webhook".to_string()));
+//! #
+//! # match response.unwrap() {
+//! #     Response::WebhookUpdated(ref user) => assert_eq!(user.access_token.expose(), "test"),
+//! #     _ => panic!("Unexpected response")
+//! # };
+//! #
+//! # let client = Client { hyper: &mfa_hyper, .. client };
+//!
+//! // Changing credentials may result in a new `Response::MFA` challenge.
+//! let user = User::new("useraccesstoken");
+//! let response = client.request(
+//!     product::Connect,
+//!     Payload::PatchCredentials(client,
+//!                               user,
+//!                               "username".to_string(),
+//!                               "newpassword".into(),
+//!                               None));
+//! #
+//! # match response.unwrap() {
+//! #     Response::MFA(ref user, _) => assert_eq!(user.access_token.expose(), "test"),
+//! #     _ => panic!("Unexpected response")
+//! # };
+//! # }
+//! ```
+//!
 //! [Plaid]: https://www.plaid.com
 //! [Hyper]: http://hyper.rs
 //!
@@ -192,6 +267,7 @@
 //! [Payload]: ./api/client/payload/enum.Payload.html
 //! [Client]: ./api/client/struct.Client.html
 //! [client]: ./api/client/index.html
+//! [builder]: ./api/client/builder/index.html
 //! [data]: ./api/data/index.html
 //!
 //! [connect]: ./api/product/connect/index.html
@@ -199,11 +275,35 @@
 //! [balance]: ./api/product/balance/index.html
 //! [info]: ./api/product/info/index.html
 //! [income]: ./api/product/income/index.html
+//!
+//! [reconcile]: ./reconcile/index.html
+//! [sync]: ./sync/index.html
+//! [vault]: ./vault/index.html
+//! [ach]: ./ach/index.html
+//! [export]: ./export/index.html
+//! [analytics]: ./analytics/index.html
+//! [summary]: ./summary/index.html
 
 extern crate rustc_serialize;
 extern crate hyper;
+extern crate p256;
+extern crate sha2;
+extern crate chacha20poly1305;
+extern crate zeroize;
+#[cfg(feature = "serde")] extern crate serde;
+#[cfg(feature = "serde")] #[macro_use] extern crate serde_derive;
+#[cfg(all(test, feature = "serde"))] extern crate serde_json;
+#[cfg(test)] #[macro_use] extern crate yup_hyper_mock;
 
 pub mod api;
+pub mod reconcile;
+pub mod sync;
+pub mod vault;
+pub mod ach;
+pub mod export;
+pub mod analytics;
+pub mod summary;
+#[cfg(test)] mod testing;
 
 /// A helper for testing against stubbed API responses.
 #[doc(hidden)] #[macro_export]
@@ -0,0 +1,354 @@
+//! Reconciliation of transactions between two successive fetches.
+//!
+//! When a pending transaction posts, Plaid removes it and returns a new posted
+//! transaction with a different `id`. Comparing two fetches by `id` alone will
+//! therefore count the same purchase twice. [`reconcile`][reconcile] produces a
+//! [`Diff`][Diff] that pairs these up, either through the explicit
+//! `pending_transaction_id` link or, when it is missing, by matching on the
+//! account, amount, date and name of the transactions.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! # use plaid::api::transaction::Transaction;
+//! # let transaction = |id: &str, pending: bool, date: &str| -> Transaction { json::decode(&format!(r#"
+//! #     {{ "_id": "{}", "_account": "acc", "amount": 12.5, "date": "{}", "name": "Golden Crepes",
+//! #        "pending": {}, "type": {{ "primary": "place" }}, "category": [], "category_id": "13005000" }}
+//! # "#, id, date, pending)).unwrap() };
+//! use plaid::reconcile::{ reconcile, Thresholds };
+//!
+//! let previous = vec![transaction("pending1", true, "2016-03-12")];
+//! let current = vec![transaction("posted1", false, "2016-03-14")];
+//!
+//! let diff = reconcile(&previous, &current, &Thresholds::default());
+//! assert_eq!(diff.posted.len(), 1);
+//! assert_eq!(diff.posted[0].pending.id, "pending1".to_string());
+//! assert_eq!(diff.posted[0].posted.id, "posted1".to_string());
+//! assert!(diff.added.is_empty() && diff.removed.is_empty());
+//! # }
+//! ```
+//!
+//! [reconcile]: ./fn.reconcile.html
+//! [Diff]: ./struct.Diff.html
+
+use std::cmp::Ordering;
+use std::collections::{ HashMap, HashSet };
+
+use api::data::Amount;
+use api::data::date::Day;
+use api::transaction::Transaction;
+
+/// The thresholds used to match a pending transaction with its posted
+/// counterpart when they are not explicitly linked.
+#[derive(Debug, Clone)]
+pub struct Thresholds {
+    /// The largest absolute difference in amount that is still considered a match.
+    pub amount_tolerance: Amount,
+    /// The largest difference in amount, relative to the pending amount, that is still
+    /// considered a match. Useful for tips, e.g `0.2` allows a 20% difference.
+    /// A match needs to satisfy either this or `amount_tolerance`.
+    pub amount_ratio: f64,
+    /// The maximum number of days between the pending and posted dates.
+    pub date_window: i64,
+    /// The minimum similarity between the two names, from `0` to `1`.
+    pub name_similarity: f64
+}
+
+impl Default for Thresholds {
+
+    fn default() -> Thresholds {
+        Thresholds {
+            amount_tolerance: 0.01,
+            amount_ratio: 0.0,
+            date_window: 7,
+            name_similarity: 0.5
+        }
+    }
+
+}
+
+/// How a pending transaction was matched to its posted counterpart.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchReason {
+    /// The posted transaction's `pending_transaction_id` refers to the pending transaction.
+    Linked,
+    /// The transactions were similar enough, with the given score from `0` to `1`.
+    Similar(f64)
+}
+
+/// A pending transaction and the posted transaction that replaced it.
+#[derive(Debug)]
+pub struct PendingMatch<'a> {
+    /// The pending transaction from the previous fetch.
+    pub pending: &'a Transaction,
+    /// The posted transaction from the current fetch.
+    pub posted: &'a Transaction,
+    /// How the two were matched.
+    pub reason: MatchReason
+}
+
+/// A transaction that is present in both fetches, but has changed.
+#[derive(Debug)]
+pub struct Update<'a> {
+    /// The transaction from the previous fetch.
+    pub previous: &'a Transaction,
+    /// The transaction from the current fetch.
+    pub current: &'a Transaction
+}
+
+/// # Diff
+/// The differences between two fetches of transactions.
+#[derive(Debug, Default)]
+pub struct Diff<'a> {
+    /// Transactions that are new in the current fetch.
+    pub added: Vec<&'a Transaction>,
+    /// Transactions from the previous fetch that are gone from the current one.
+    pub removed: Vec<&'a Transaction>,
+    /// Transactions that are present in both fetches, but have changed.
+    pub updated: Vec<Update<'a>>,
+    /// Pending transactions that have been replaced by a posted transaction.
+    pub posted: Vec<PendingMatch<'a>>
+}
+
+impl<'a> Diff<'a> {
+
+    /// Whether or not there are no differences.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty() && self.posted.is_empty()
+    }
+
+}
+
+/// Compare the `previous` and `current` transactions of a user.
+///
+/// Every list in the resulting `Diff` is ordered by transaction `id`, so the
+/// same input always results in the same `Diff`.
+pub fn reconcile<'a>(previous: &'a [Transaction], current: &'a [Transaction], thresholds: &Thresholds) -> Diff<'a> {
+    let previous_ids: HashMap<&str, &Transaction> = previous.iter().map(|t| (t.id.as_ref(), t)).collect();
+    let current_ids: HashSet<&str> = current.iter().map(|t| t.id.as_ref()).collect();
+
+    let mut diff = Diff::default();
+    let mut added: Vec<&Transaction> = Vec::new();
+
+    for c in current {
+        match previous_ids.get(c.id.as_str()) {
+            Some(p) => if has_changed(p, c) { diff.updated.push(Update { previous: p, current: c }) },
+            None => added.push(c)
+        }
+    }
+
+    let mut removed: Vec<&Transaction> = previous.iter()
+        .filter(|p| !current_ids.contains(p.id.as_str()))
+        .collect();
+
+    added.sort_by(|a, b| a.id.cmp(&b.id));
+    removed.sort_by(|a, b| a.id.cmp(&b.id));
+    diff.updated.sort_by(|a, b| a.current.id.cmp(&b.current.id));
+
+    // Explicit links always win over similarity.
+    let mut matched_pending: HashSet<&str> = HashSet::new();
+    let mut matched_posted: HashSet<&str> = HashSet::new();
+    for posted in added.iter().filter(|t| !t.pending) {
+        let link = match posted.pending_transaction_id { Some(ref id) => id, None => continue };
+        if let Some(pending) = removed.iter().find(|p| &p.id == link && !matched_pending.contains(p.id.as_str())) {
+            matched_pending.insert(pending.id.as_ref());
+            matched_posted.insert(posted.id.as_ref());
+            diff.posted.push(PendingMatch { pending: pending, posted: posted, reason: MatchReason::Linked });
+        }
+    }
+
+    // Then every remaining pair is scored, and the best pairs are taken greedily.
+    let mut candidates: Vec<(f64, &Transaction, &Transaction)> = Vec::new();
+    for pending in removed.iter().filter(|t| t.pending && !matched_pending.contains(t.id.as_str())) {
+        for posted in added.iter().filter(|t| !t.pending && !matched_posted.contains(t.id.as_str())) {
+            if let Some(score) = similarity(pending, posted, thresholds) {
+                candidates.push((score, pending, posted));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| {
+        b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal)
+            .then_with(|| a.1.id.cmp(&b.1.id))
+            .then_with(|| a.2.id.cmp(&b.2.id))
+    });
+    for (score, pending, posted) in candidates {
+        if matched_pending.contains(pending.id.as_str()) || matched_posted.contains(posted.id.as_str()) { continue }
+        matched_pending.insert(pending.id.as_ref());
+        matched_posted.insert(posted.id.as_ref());
+        diff.posted.push(PendingMatch { pending: pending, posted: posted, reason: MatchReason::Similar(score) });
+    }
+    diff.posted.sort_by(|a, b| a.pending.id.cmp(&b.pending.id));
+
+    diff.added = added.into_iter().filter(|t| !matched_posted.contains(t.id.as_str())).collect();
+    diff.removed = removed.into_iter().filter(|t| !matched_pending.contains(t.id.as_str())).collect();
+    diff
+}
+
+fn has_changed(a: &Transaction, b: &Transaction) -> bool {
+    a.account_id != b.account_id ||
+        a.amount != b.amount ||
+        a.date != b.date ||
+        a.pending != b.pending ||
+        a.name != b.name ||
+        a.category_id != b.category_id ||
+        a.categories != b.categories
+}
+
+/// Score how likely it is that `posted` replaced `pending`, returning `None`
+/// if any of the `thresholds` are not met.
+pub fn similarity(pending: &Transaction, posted: &Transaction, thresholds: &Thresholds) -> Option<f64> {
+    if pending.account_id != posted.account_id { return None }
+
+    let difference = (pending.amount - posted.amount).abs();
+    let ratio = if pending.amount != 0.0 { difference / pending.amount.abs() } else { ::std::f64::INFINITY };
+    if difference > thresholds.amount_tolerance && ratio > thresholds.amount_ratio { return None }
+
+    let days = match (Day::parse(&pending.date), Day::parse(&posted.date)) {
+        (Some(a), Some(b)) => b.days_since(&a),
+        _ => return None
+    };
+    if days.abs() > thresholds.date_window { return None }
+
+    let name = match (pending.name.as_ref(), posted.name.as_ref()) {
+        (Some(a), Some(b)) => name_similarity(a, b),
+        (None, None) => 1.0,
+        _ => 0.0
+    };
+    if name < thresholds.name_similarity { return None }
+
+    let window = thresholds.date_window as f64 + 1.0;
+    let amount_score = 1.0 / (1.0 + difference);
+    let date_score = 1.0 - days.abs() as f64 / window;
+    Some((name + amount_score + date_score) / 3.0)
+}
+
+/// The Sørensen–Dice coefficient of the character bigrams of both names,
+/// ignoring case and anything other than letters and digits.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    fn bigrams(s: &str) -> Vec<(char, char)> {
+        let chars: Vec<char> = s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(|c| c.to_lowercase())
+            .collect();
+        let mut pairs: Vec<(char, char)> = chars.windows(2).map(|w| (w[0], w[1])).collect();
+        pairs.sort();
+        pairs
+    }
+
+    let (a, b) = (bigrams(a), bigrams(b));
+    if a.is_empty() && b.is_empty() { return 1.0 }
+    if a.is_empty() || b.is_empty() { return 0.0 }
+
+    // Count the bigrams in common, both lists are sorted.
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => { common += 1; i += 1; j += 1; }
+        }
+    }
+    2.0 * common as f64 / (a.len() + b.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+
+    use api::transaction::Transaction;
+    use reconcile::*;
+    use testing::{ transaction, TransactionFixture };
+
+    fn linked(id: &str, pending_id: &str, amount: f64, date: &str) -> Transaction {
+        let mut t = transaction(id, amount, date).named("Something else entirely");
+        t.pending_transaction_id = Some(pending_id.to_string());
+        t
+    }
+
+    fn ids(ts: &[&Transaction]) -> Vec<String> {
+        ts.iter().map(|t| t.id.clone()).collect()
+    }
+
+    #[test]
+    fn test_added_removed_and_updated() {
+        let previous = vec![transaction("a", 10.0, "2016-03-01").named("Coffee"),
+                            transaction("b", 20.0, "2016-03-02").named("Lunch")];
+        let mut changed = transaction("b", 25.0, "2016-03-02").named("Lunch");
+        changed.categories.push("Restaurants".to_string());
+        let current = vec![changed,
+                           transaction("c", 30.0, "2016-03-03").named("Dinner")];
+
+        let diff = reconcile(&previous, &current, &Thresholds::default());
+        assert_eq!(ids(&diff.added), vec!["c".to_string()]);
+        assert_eq!(ids(&diff.removed), vec!["a".to_string()]);
+        assert_eq!(diff.updated.len(), 1);
+        assert_eq!(diff.updated[0].previous.amount, 20.0);
+        assert_eq!(diff.updated[0].current.amount, 25.0);
+        assert!(diff.posted.is_empty());
+        assert!(reconcile(&current, &current, &Thresholds::default()).is_empty());
+    }
+
+    #[test]
+    fn test_explicit_links_take_priority() {
+        let previous = vec![transaction("p1", 12.5, "2016-03-12").named("Golden Crepes").pending(),
+                            transaction("p2", 12.5, "2016-03-12").named("Golden Crepes").pending()];
+        let current = vec![linked("posted2", "p2", 14.0, "2016-03-20"),
+                           transaction("posted1", 12.5, "2016-03-13").named("GOLDEN CREPES #12")];
+
+        let diff = reconcile(&previous, &current, &Thresholds::default());
+        assert_eq!(diff.posted.len(), 2);
+        assert_eq!(diff.posted[0].pending.id, "p1".to_string());
+        assert_eq!(diff.posted[0].posted.id, "posted1".to_string());
+        assert!(match diff.posted[0].reason { MatchReason::Similar(_) => true, _ => false });
+        assert_eq!(diff.posted[1].pending.id, "p2".to_string());
+        assert_eq!(diff.posted[1].posted.id, "posted2".to_string());
+        assert_eq!(diff.posted[1].reason, MatchReason::Linked);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+    }
+
+    #[test]
+    fn test_heuristic_thresholds() {
+        let previous = vec![transaction("p1", 40.0, "2016-03-12").named("Uber").pending()];
+
+        // Different account, amount, date or name are all rejected by default.
+        for posted in [transaction("x", 40.0, "2016-03-13").account("acc2").named("Uber"),
+                       transaction("x", 48.0, "2016-03-13").named("Uber"),
+                       transaction("x", 40.0, "2016-03-25").named("Uber"),
+                       transaction("x", 40.0, "2016-03-13").named("Lyft")] {
+            let current = vec![posted];
+            let diff = reconcile(&previous, &current, &Thresholds::default());
+            assert!(diff.posted.is_empty());
+            assert_eq!(ids(&diff.added), vec!["x".to_string()]);
+            assert_eq!(ids(&diff.removed), vec!["p1".to_string()]);
+        }
+
+        // A 20% tip is accepted once `amount_ratio` allows it.
+        let current = vec![transaction("x", 48.0, "2016-03-13").named("Uber")];
+        let thresholds = Thresholds { amount_ratio: 0.2, .. Thresholds::default() };
+        assert_eq!(reconcile(&previous, &current, &thresholds).posted.len(), 1);
+    }
+
+    #[test]
+    fn test_best_candidate_wins() {
+        let previous = vec![transaction("p1", 5.0, "2016-03-10").named("Starbucks").pending()];
+        let current = vec![transaction("far", 5.0, "2016-03-15").named("Starbucks"),
+                           transaction("near", 5.0, "2016-03-11").named("Starbucks")];
+
+        let diff = reconcile(&previous, &current, &Thresholds::default());
+        assert_eq!(diff.posted.len(), 1);
+        assert_eq!(diff.posted[0].posted.id, "near".to_string());
+        assert_eq!(ids(&diff.added), vec!["far".to_string()]);
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        assert_eq!(name_similarity("Golden Crepes", "GOLDEN CREPES"), 1.0);
+        assert!(name_similarity("Golden Crepes", "Golden Crepes #12") > 0.8);
+        assert!(name_similarity("Uber", "Lyft") < 0.1);
+        assert_eq!(name_similarity("", ""), 1.0);
+    }
+
+}
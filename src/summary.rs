@@ -0,0 +1,466 @@
+//! Cash-flow and spending summaries of transactions, by period and category.
+//!
+//! [`summarize`][summarize] adds up the inflow (money coming in) and outflow
+//! (money going out) of transactions per day, week or month, and per category.
+//! Both are positive amounts, unlike Plaid's signed amounts. Transactions can be
+//! limited to some accounts, and transfers between the user's own accounts are
+//! excluded by default, as they would otherwise count as both income and spending.
+//! Those are the transactions in Plaid's `Internal Account Transfer` category, and
+//! the pairs found by [`analytics::transfers`][transfers].
+//!
+//! A [`Summary`][Summary] is plain data, which can be rendered as a text table
+//! with `to_table()`, or as JSON with `to_json()`.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # extern crate rustc_serialize;
+//! # fn main() {
+//! # use rustc_serialize::json;
+//! use plaid::api::product::ConnectData;
+//! use plaid::summary::{ self, Options, Period };
+//!
+//! let data: ConnectData = json::decode(include_str!("fixtures/post_connect_success.json")).unwrap();
+//! let summary = summary::summarize(&data.transactions, &Options { period: Period::Month, .. Options::default() });
+//!
+//! assert_eq!(summary.periods.len(), 4);
+//! assert_eq!(summary.inflow, 700.0);
+//! assert_eq!(summary.outflow, 12.74);
+//! println!("{}", summary.to_table());
+//! # }
+//! ```
+//!
+//! [summarize]: ./fn.summarize.html
+//! [Summary]: ./struct.Summary.html
+//! [transfers]: ../analytics/transfers/index.html
+
+use std::collections::{ BTreeMap, HashSet };
+use std::fmt::Write;
+
+use analytics::{ round, transfers };
+use api::data::{ Amount, UID };
+use api::data::date::Day;
+use api::types::Transaction;
+
+use rustc_serialize::json::{ Json, ToJson };
+
+/// The length of each period of a summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    /// A day.
+    Day,
+    /// A week, starting on Monday.
+    Week,
+    /// A calendar month.
+    Month
+}
+
+impl Period {
+
+    /// The first day of the period that includes `day`.
+    pub fn start(&self, day: Day) -> Day {
+        match *self {
+            Period::Day => day,
+            Period::Week => day.add_days(-(day.weekday() as i64)),
+            Period::Month => day.first_of_month()
+        }
+    }
+
+    /// The first day of the next period, given the first day of a period.
+    pub fn next(&self, start: Day) -> Day {
+        match *self {
+            Period::Day => start.add_days(1),
+            Period::Week => start.add_days(7),
+            Period::Month => start.add_months(1)
+        }
+    }
+
+    /// The label of the period that starts on `start`, e.g `2016-03` for a month.
+    pub fn label(&self, start: Day) -> String {
+        match *self {
+            Period::Month => format!("{:04}-{:02}", start.year, start.month),
+            _ => start.to_date()
+        }
+    }
+
+}
+
+/// The category that transactions in Plaid's internal transfer category belong to.
+pub const INTERNAL_TRANSFER_CATEGORY: &'static str = "Internal Account Transfer";
+
+/// The category of transactions without categories.
+pub const UNCATEGORIZED: &'static str = "Uncategorized";
+
+/// How transactions are summarized.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The length of each period.
+    pub period: Period,
+    /// Only transactions of these accounts are included, or of every account if it is empty.
+    pub accounts: Vec<UID>,
+    /// Whether internal transfers are excluded: those in the `Internal Account Transfer`
+    /// category, and those detected with the `transfers` options.
+    pub exclude_transfers: bool,
+    /// How internal transfers are detected.
+    pub transfers: transfers::Options,
+    /// Transactions that are excluded, by id.
+    pub exclude: HashSet<UID>,
+    /// How many levels of the category hierarchy are kept, e.g `1` for `Food and Drink`
+    /// and `2` for `Food and Drink:Restaurants`.
+    pub category_depth: usize,
+    /// Whether pending transactions are included.
+    pub include_pending: bool
+}
+
+impl Default for Options {
+
+    fn default() -> Options {
+        Options {
+            period: Period::Month,
+            accounts: Vec::new(),
+            exclude_transfers: true,
+            transfers: transfers::Options::default(),
+            exclude: HashSet::new(),
+            category_depth: 1,
+            include_pending: false
+        }
+    }
+
+}
+
+/// The money that moved in a category.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryTotal {
+    /// The category, with its levels joined by `:`.
+    pub category: String,
+    /// The money that came in.
+    pub inflow: Amount,
+    /// The money that went out.
+    pub outflow: Amount,
+    /// The number of transactions.
+    pub count: usize
+}
+
+impl CategoryTotal {
+
+    /// The inflow minus the outflow.
+    pub fn net(&self) -> Amount {
+        round(self.inflow - self.outflow)
+    }
+
+}
+
+/// The money that moved in a period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodSummary {
+    /// The first day of the period.
+    pub start: Day,
+    /// The money that came in.
+    pub inflow: Amount,
+    /// The money that went out.
+    pub outflow: Amount,
+    /// The number of transactions.
+    pub count: usize,
+    /// The totals of each category in the period, by name.
+    pub categories: Vec<CategoryTotal>
+}
+
+impl PeriodSummary {
+
+    /// The inflow minus the outflow.
+    pub fn net(&self) -> Amount {
+        round(self.inflow - self.outflow)
+    }
+
+}
+
+/// # Summary
+/// The money that moved in every period between the first and last transaction,
+/// including periods without transactions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// The length of each period.
+    pub period: Period,
+    /// Each period, in order.
+    pub periods: Vec<PeriodSummary>,
+    /// The totals of each category over every period, by name.
+    pub categories: Vec<CategoryTotal>,
+    /// The money that came in over every period.
+    pub inflow: Amount,
+    /// The money that went out over every period.
+    pub outflow: Amount
+}
+
+impl Summary {
+
+    /// The inflow minus the outflow, over every period.
+    pub fn net(&self) -> Amount {
+        round(self.inflow - self.outflow)
+    }
+
+    /// Render the periods and then the categories as text tables.
+    pub fn to_table(&self) -> String {
+        let mut rows: Vec<[String; 4]> = self.periods.iter()
+            .map(|p| [self.period.label(p.start), money(p.inflow), money(p.outflow), money(p.net())])
+            .collect();
+        rows.push(["Total".to_string(), money(self.inflow), money(self.outflow), money(self.net())]);
+        let mut out = table(&["Period", "Inflow", "Outflow", "Net"], &rows);
+
+        let rows: Vec<[String; 4]> = self.categories.iter()
+            .map(|c| [c.category.clone(), money(c.inflow), money(c.outflow), money(c.net())])
+            .collect();
+        out.push('\n');
+        out.push_str(&table(&["Category", "Inflow", "Outflow", "Net"], &rows));
+        out
+    }
+
+    /// The summary as JSON, e.g for a dashboard.
+    pub fn to_json(&self) -> Json {
+        ToJson::to_json(self)
+    }
+
+}
+
+impl ToJson for CategoryTotal {
+
+    fn to_json(&self) -> Json {
+        let mut o = BTreeMap::new();
+        o.insert("category".to_string(), self.category.to_json());
+        o.insert("inflow".to_string(), self.inflow.to_json());
+        o.insert("outflow".to_string(), self.outflow.to_json());
+        o.insert("net".to_string(), self.net().to_json());
+        o.insert("count".to_string(), self.count.to_json());
+        Json::Object(o)
+    }
+
+}
+
+impl ToJson for PeriodSummary {
+
+    fn to_json(&self) -> Json {
+        let mut o = BTreeMap::new();
+        o.insert("start".to_string(), self.start.to_date().to_json());
+        o.insert("inflow".to_string(), self.inflow.to_json());
+        o.insert("outflow".to_string(), self.outflow.to_json());
+        o.insert("net".to_string(), self.net().to_json());
+        o.insert("count".to_string(), self.count.to_json());
+        o.insert("categories".to_string(), self.categories.to_json());
+        Json::Object(o)
+    }
+
+}
+
+impl ToJson for Summary {
+
+    fn to_json(&self) -> Json {
+        let mut o = BTreeMap::new();
+        o.insert("period".to_string(), match self.period {
+            Period::Day => "day",
+            Period::Week => "week",
+            Period::Month => "month"
+        }.to_json());
+        o.insert("periods".to_string(), self.periods.to_json());
+        o.insert("categories".to_string(), self.categories.to_json());
+        o.insert("inflow".to_string(), self.inflow.to_json());
+        o.insert("outflow".to_string(), self.outflow.to_json());
+        o.insert("net".to_string(), self.net().to_json());
+        Json::Object(o)
+    }
+
+}
+
+/// Whether a transaction is in Plaid's internal transfer category.
+pub fn is_internal_transfer(transaction: &Transaction) -> bool {
+    transaction.categories.iter().any(|c| c == INTERNAL_TRANSFER_CATEGORY)
+}
+
+/// Whether a transaction is included in a summary with the given options, before
+/// detected transfers are excluded.
+pub fn includes(transaction: &Transaction, options: &Options) -> bool {
+    (options.include_pending || !transaction.pending) &&
+        (options.accounts.is_empty() || options.accounts.contains(&transaction.account_id)) &&
+        !options.exclude.contains(&transaction.id) &&
+        !(options.exclude_transfers && is_internal_transfer(transaction))
+}
+
+/// The category of a transaction, with at most `depth` levels.
+pub fn category(transaction: &Transaction, depth: usize) -> String {
+    if transaction.categories.is_empty() || depth == 0 {
+        UNCATEGORIZED.to_string()
+    } else {
+        transaction.categories.iter().take(depth).cloned().collect::<Vec<_>>().join(":")
+    }
+}
+
+/// Summarize transactions by period and category.
+pub fn summarize(transactions: &[Transaction], options: &Options) -> Summary {
+    // Transfers are detected over every account, as a transfer stays internal
+    // when only one of its sides is summarized.
+    let detected = if options.exclude_transfers {
+        transfers::transaction_ids(&transfers::detect(transactions, &options.transfers))
+    } else {
+        HashSet::new()
+    };
+
+    let mut by_period: BTreeMap<Day, Vec<&Transaction>> = BTreeMap::new();
+    for t in transactions.iter().filter(|t| includes(t, options) && !detected.contains(&t.id)) {
+        if let Some(day) = Day::parse(&t.date) {
+            by_period.entry(options.period.start(day)).or_default().push(t);
+        }
+    }
+
+    let mut periods = Vec::new();
+    if let (Some(&first), Some(&last)) = (by_period.keys().next(), by_period.keys().next_back()) {
+        let mut start = first;
+        while start <= last {
+            let included = by_period.get(&start).map(|ts| ts.as_slice()).unwrap_or(&[]);
+            let categories = totals(included, options.category_depth);
+            periods.push(PeriodSummary {
+                start: start,
+                inflow: round(categories.iter().map(|c| c.inflow).sum()),
+                outflow: round(categories.iter().map(|c| c.outflow).sum()),
+                count: included.len(),
+                categories: categories
+            });
+            start = options.period.next(start);
+        }
+    }
+
+    let all: Vec<&Transaction> = by_period.values().flat_map(|ts| ts.iter().cloned()).collect();
+    Summary {
+        period: options.period,
+        inflow: round(periods.iter().map(|p| p.inflow).sum()),
+        outflow: round(periods.iter().map(|p| p.outflow).sum()),
+        periods: periods,
+        categories: totals(&all, options.category_depth)
+    }
+}
+
+fn totals(transactions: &[&Transaction], depth: usize) -> Vec<CategoryTotal> {
+    let mut totals: BTreeMap<String, CategoryTotal> = BTreeMap::new();
+    for t in transactions {
+        let name = category(t, depth);
+        let total = totals.entry(name.clone()).or_insert_with(|| {
+            CategoryTotal { category: name, inflow: 0.0, outflow: 0.0, count: 0 }
+        });
+        if t.amount < 0.0 { total.inflow -= t.amount } else { total.outflow += t.amount }
+        total.count += 1;
+    }
+    totals.into_values().map(|mut total| {
+        total.inflow = round(total.inflow);
+        total.outflow = round(total.outflow);
+        total
+    }).collect()
+}
+
+fn money(amount: Amount) -> String {
+    format!("{:.2}", amount)
+}
+
+/// A table with the first column aligned left and the others right.
+fn table(header: &[&str; 4], rows: &[[String; 4]]) -> String {
+    let mut widths = [0; 4];
+    for (i, h) in header.iter().enumerate() {
+        widths[i] = rows.iter().map(|r| r[i].chars().count()).chain(Some(h.len())).max().unwrap_or(0);
+    }
+    let mut out = String::new();
+    let mut line = |cells: [&str; 4]| {
+        let _ = writeln!(out, "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}", cells[0], cells[1], cells[2], cells[3],
+                         w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3]);
+    };
+    line(*header);
+    for r in rows {
+        line([&r[0], &r[1], &r[2], &r[3]]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+
+    use api::data::date::Day;
+    use api::types::Transaction;
+    use summary::*;
+    use testing::{ transaction, TransactionFixture };
+
+    fn transactions() -> Vec<Transaction> {
+        vec![transaction("pay", -1500.0, "2016-01-15").account("checking").categories(&["Transfer", "Payroll"]),
+             transaction("crepes", 12.74, "2016-01-16").account("checking").categories(&["Food and Drink", "Restaurants"]),
+             transaction("coffee", 4.5, "2016-03-02").account("credit").categories(&["Food and Drink", "Coffee Shop"]),
+             transaction("refund", -4.5, "2016-03-03").account("credit").categories(&["Food and Drink", "Coffee Shop"]),
+             transaction("out", 200.0, "2016-03-04").account("checking").categories(&["Transfer", "Internal Account Transfer"]),
+             transaction("in", -200.0, "2016-03-04").account("savings").categories(&["Transfer", "Internal Account Transfer"]),
+             transaction("misc", 20.0, "2016-03-31").account("checking")]
+    }
+
+    #[test]
+    fn test_summarize_by_month() {
+        let summary = summarize(&transactions(), &Options::default());
+        let starts: Vec<String> = summary.periods.iter().map(|p| p.start.to_date()).collect();
+        assert_eq!(starts, vec!["2016-01-01", "2016-02-01", "2016-03-01"]);
+        assert_eq!(summary.periods[0].inflow, 1500.0);
+        assert_eq!(summary.periods[0].net(), 1487.26);
+        assert_eq!(summary.periods[1].count, 0);
+        assert_eq!(summary.periods[2].outflow, 24.5);
+        assert_eq!(summary.net(), 1500.0 - 12.74 - 20.0);
+
+        let food = summary.categories.iter().find(|c| c.category == "Food and Drink").unwrap();
+        assert_eq!((food.inflow, food.outflow, food.count), (4.5, 17.24, 3));
+        assert!(summary.categories.iter().any(|c| c.category == UNCATEGORIZED));
+        assert!(summary.categories.iter().all(|c| c.category != "Transfer" || c.inflow == 1500.0));
+    }
+
+    #[test]
+    fn test_filters_and_periods() {
+        let options = Options { period: Period::Week, accounts: vec!["credit".to_string()], category_depth: 2,
+                                .. Options::default() };
+        let summary = summarize(&transactions(), &options);
+        assert_eq!(summary.periods.len(), 1);
+        assert_eq!(summary.periods[0].start, Day::new(2016, 2, 29).unwrap());
+        assert_eq!(summary.categories[0].category, "Food and Drink:Coffee Shop");
+        assert_eq!(summary.net(), 0.0);
+
+        let options = Options { period: Period::Day, exclude_transfers: false, .. Options::default() };
+        let summary = summarize(&transactions()[4..6], &options);
+        assert_eq!((summary.inflow, summary.outflow), (200.0, 200.0));
+    }
+
+    #[test]
+    fn test_excludes_detected_transfers() {
+        let mut transactions = transactions();
+        transactions.push(transaction("sweep", 300.0, "2016-03-10").account("checking").named("Transfer to Savings"));
+        transactions.push(transaction("swept", -300.0, "2016-03-11").account("savings"));
+
+        let options = Options { accounts: vec!["savings".to_string()], .. Options::default() };
+        assert!(summarize(&transactions, &options).periods.is_empty());
+
+        let summary = summarize(&transactions, &Options::default());
+        assert_eq!((summary.inflow, summary.outflow), (1504.5, 37.24));
+    }
+
+    #[test]
+    fn test_keeps_coincidental_pairs() {
+        let mut transactions = transactions();
+        transactions.push(transaction("dinner", 45.0, "2016-03-10").account("credit").named("Golden Crepes"));
+        transactions.push(transaction("repaid", -45.0, "2016-03-11").account("checking").named("Venmo"));
+
+        let summary = summarize(&transactions, &Options::default());
+        assert_eq!((summary.inflow, summary.outflow), (1549.5, 82.24));
+    }
+
+    #[test]
+    fn test_render() {
+        let summary = summarize(&transactions(), &Options::default());
+        let table = summary.to_table();
+        assert_eq!(table.lines().next(), Some("Period    Inflow  Outflow      Net"));
+        assert_eq!(table.lines().nth(1), Some("2016-01  1500.00    12.74  1487.26"));
+        assert_eq!(table.lines().nth(4), Some("Total    1504.50    37.24  1467.26"));
+
+        let json = summary.to_json();
+        assert_eq!(json["period"], Json::String("month".to_string()));
+        assert_eq!(json["periods"][0]["start"], Json::String("2016-01-01".to_string()));
+        assert_eq!(json["net"], Json::F64(1467.26));
+    }
+
+}
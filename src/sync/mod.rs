@@ -0,0 +1,308 @@
+//! Incremental synchronization of a user's `Connect` data into a local store.
+//!
+//! A [`Syncer`][Syncer] remembers what it has seen of each `User` in a
+//! [`Store`][Store]. On every sync only the transactions since the last sync are
+//! fetched, starting a few days earlier (the `overlap`) so that late-posting and
+//! pending transactions are picked up. The fetched window is
+//! [reconciled][reconcile] with the stored transactions, the store is updated,
+//! and the differences are returned as a list of [`Change`][Change]s.
+//!
+//! ## Example
+//!
+//! ```
+//! # #[macro_use(http_stub)] extern crate plaid;
+//! # #[macro_use] extern crate yup_hyper_mock as hyper_mock;
+//! # extern crate hyper;
+//! #
+//! # fn main() {
+//! #
+//! # http_stub!(StubPolicy, 200, include_str!("fixtures/post_connect_success.json"));
+//! #
+//! # let hyper = hyper::Client::with_connector(StubPolicy::default());
+//! #
+//! use plaid::api::client::Client;
+//! use plaid::api::user::User;
+//! use plaid::sync::{ Syncer, Change };
+//! use plaid::sync::store::MemoryStore;
+//!
+//! let client = Client { endpoint:  "https://tartan.plaid.com",
+//!                       client_id: "testclient",
+//!                       secret:    "testsecret",
+//!                       hyper:     &hyper };
+//!
+//! let mut syncer = Syncer::new(client, MemoryStore::new()).overlap(14);
+//! syncer.on_change(|change| println!("{:?}", change));
+//!
+//! let user = User::new("testaccesstoken");
+//! let changes = syncer.sync(&user).unwrap();
+//! assert_eq!(changes.iter().filter(|c| match **c { Change::Added(_) => true, _ => false }).count(), 2);
+//!
+//! // Syncing the same data again results in no transaction changes.
+//! let changes = syncer.sync(&user).unwrap();
+//! assert!(changes.is_empty());
+//! # }
+//! ```
+//!
+//! [Syncer]: ./struct.Syncer.html
+//! [Store]: ./store/trait.Store.html
+//! [Change]: ./enum.Change.html
+//! [reconcile]: ../reconcile/index.html
+
+use std::collections::HashSet;
+
+use api::client::{ Client, Payload, Response };
+use api::client::payload::FetchDataOptions;
+use api::data::Amount;
+use api::data::date::Day;
+use api::error::Error;
+use api::product::{ Connect, ConnectData };
+use api::types::{ Account, Transaction, UID };
+use api::user::User;
+use reconcile::{ self, Thresholds };
+
+pub use self::store::{ Store, State };
+pub mod store;
+
+/// The default number of days before the last sync that are fetched again.
+pub const DEFAULT_OVERLAP_DAYS: u32 = 7;
+
+/// # Change
+/// A single difference between the stored and fetched data of a user.
+#[derive(Debug, Clone)]
+pub enum Change {
+    /// A transaction that was not seen before.
+    Added(Transaction),
+    /// A stored transaction that is no longer returned by Plaid.
+    Removed(Transaction),
+    /// A stored transaction that has changed, given as `(previous, current)`.
+    Updated(Transaction, Transaction),
+    /// A pending transaction that has posted, given as `(pending, posted)`.
+    Posted(Transaction, Transaction),
+    /// The current balance of an account has changed, given as `(account, previous, current)`.
+    /// `previous` is `None` for accounts that were not seen before.
+    Balance(UID, Option<Amount>, Amount),
+    /// A stored account that is no longer returned by Plaid. Its stored transactions are kept.
+    AccountRemoved(Account)
+}
+
+/// A listener that can be registered with a `Syncer`.
+pub type Listener = Box<dyn Fn(&Change)>;
+
+/// # Syncer
+/// Fetches and stores the `Connect` data of users, see the [module](./index.html)
+/// documentation.
+pub struct Syncer<'a, S: Store> {
+    client: Client<'a>,
+    store: S,
+    overlap: u32,
+    thresholds: Thresholds,
+    listeners: Vec<Listener>
+}
+
+impl<'a, S: Store> Syncer<'a, S> {
+
+    /// Create a `Syncer` that keeps its state in the given `Store`.
+    pub fn new(client: Client<'a>, store: S) -> Syncer<'a, S> {
+        Syncer {
+            client: client,
+            store: store,
+            overlap: DEFAULT_OVERLAP_DAYS,
+            thresholds: Thresholds::default(),
+            listeners: Vec::new()
+        }
+    }
+
+    /// The number of days before the last sync that are fetched again.
+    pub fn overlap(mut self, days: u32) -> Syncer<'a, S> {
+        self.overlap = days;
+        self
+    }
+
+    /// The thresholds used to match pending transactions with posted ones.
+    pub fn thresholds(mut self, thresholds: Thresholds) -> Syncer<'a, S> {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Register a listener that is called with every change, in order.
+    pub fn on_change<F>(&mut self, listener: F) -> &mut Syncer<'a, S>
+        where F: Fn(&Change) + 'static {
+        self.listeners.push(Box::new(listener));
+        self
+    }
+
+    /// The underlying store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Sync the given user as of today.
+    pub fn sync(&mut self, user: &User) -> Result<Vec<Change>, Error> {
+        self.sync_at(user, Day::today())
+    }
+
+    /// Sync the given user as if `today` was the current date.
+    ///
+    /// The first sync of a user fetches their entire history. Nothing is stored
+    /// and no listeners are called if the request fails.
+    pub fn sync_at(&mut self, user: &User, today: Day) -> Result<Vec<Change>, Error> {
+        let mut state = try!(self.store.load(user)).unwrap_or_default();
+        let since = window_start(&state, self.overlap);
+
+        let mut options = FetchDataOptions::default().lte(today.to_date());
+        if let Some(since) = since { options = options.gte(since.to_date()) }
+
+        let data = match try!(self.client.request(Connect, Payload::FetchData(self.client, user.clone(), Some(options)))) {
+            Response::ProductData(data) => data,
+            _ => return Err(Error::InternalError)
+        };
+
+        let changes = apply(&mut state, data, since, today, &self.thresholds);
+        try!(self.store.save(user, &state));
+        for change in &changes {
+            for listener in &self.listeners { listener(change) }
+        }
+        Ok(changes)
+    }
+
+}
+
+/// The first day that needs to be fetched for the given state, or `None` if
+/// the entire history should be fetched.
+pub fn window_start(state: &State, overlap: u32) -> Option<Day> {
+    state.last_sync.as_ref()
+        .and_then(|d| Day::parse(d))
+        .map(|d| d.add_days(-(overlap as i64)))
+}
+
+/// Apply freshly fetched data to a stored state, returning the changes.
+///
+/// `since` is the first day of the fetched window, as given by `window_start`.
+/// Stored transactions before it are kept as they are, unless they were fetched
+/// again. Every other stored transaction is reconciled with the fetched transactions.
+pub fn apply(state: &mut State, data: ConnectData, since: Option<Day>, today: Day, thresholds: &Thresholds) -> Vec<Change> {
+    let ConnectData { accounts, transactions } = data;
+    let fetched: HashSet<UID> = transactions.iter().map(|t| t.id.clone()).collect();
+    let in_window = |t: &Transaction| fetched.contains(&t.id) || match (since, Day::parse(&t.date)) {
+        (Some(since), Some(day)) => day >= since,
+        _ => true
+    };
+
+    let (previous, kept): (Vec<Transaction>, Vec<Transaction>) =
+        state.transactions.drain(..).partition(|t| in_window(t));
+
+    let mut changes = Vec::new();
+    {
+        let diff = reconcile::reconcile(&previous, &transactions, thresholds);
+        for m in diff.posted { changes.push(Change::Posted(m.pending.clone(), m.posted.clone())) }
+        for u in diff.updated { changes.push(Change::Updated(u.previous.clone(), u.current.clone())) }
+        for t in diff.removed { changes.push(Change::Removed(t.clone())) }
+        for t in diff.added { changes.push(Change::Added(t.clone())) }
+    }
+    changes.extend(account_changes(&state.accounts, &accounts));
+
+    state.transactions = kept;
+    state.transactions.extend(transactions);
+    state.transactions.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.id.cmp(&b.id)));
+    state.accounts = accounts;
+    state.last_sync = Some(today.to_date());
+    changes
+}
+
+fn account_changes(previous: &[Account], current: &[Account]) -> Vec<Change> {
+    let mut changes: Vec<Change> = current.iter().filter_map(|c| {
+        let before = previous.iter().find(|p| p.id == c.id).map(|p| p.current_balance);
+        if before == Some(c.current_balance) { return None }
+        Some(Change::Balance(c.id.clone(), before, c.current_balance))
+    }).collect();
+    changes.extend(previous.iter()
+        .filter(|p| !current.iter().any(|c| c.id == p.id))
+        .map(|p| Change::AccountRemoved(p.clone())));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+
+    use api::data::date::Day;
+    use api::product::ConnectData;
+    use reconcile::Thresholds;
+    use rustc_serialize::json;
+    use sync::*;
+
+    fn transaction(id: &str, amount: f64, date: &str, pending: bool) -> String {
+        format!(r#"{{ "_id": "{}", "_account": "acc1", "amount": {}, "date": "{}", "name": "Golden Crepes",
+                      "pending": {}, "type": {{ "primary": "place" }}, "category": [], "category_id": "13005000" }}"#,
+                id, amount, date, pending)
+    }
+
+    fn account(id: &str, balance: f64) -> String {
+        format!(r#"{{ "_id": "{}", "_item": "item", "balance": {{ "current": {} }},
+                      "institution_type": "fake_institution", "type": "depository" }}"#, id, balance)
+    }
+
+    fn data(balance: f64, transactions: &[String]) -> ConnectData {
+        accounts_data(&[account("acc1", balance)], transactions)
+    }
+
+    fn accounts_data(accounts: &[String], transactions: &[String]) -> ConnectData {
+        json::decode(&format!(r#"{{ "accounts": [{}], "transactions": [{}] }}"#,
+                              accounts.join(","), transactions.join(","))).unwrap()
+    }
+
+    #[test]
+    fn test_first_sync_adds_everything() {
+        let mut state = State::default();
+        let today = Day::new(2016, 3, 20).unwrap();
+        let changes = apply(&mut state, data(100.0, &[transaction("a", 5.0, "2016-03-01", false)]),
+                            None, today, &Thresholds::default());
+
+        assert_eq!(changes.len(), 2);
+        assert!(match changes[0] { Change::Added(ref t) => t.id == "a", _ => false });
+        assert!(match changes[1] { Change::Balance(ref id, None, 100.0) => id == "acc1", _ => false });
+        assert_eq!(state.last_sync, Some("2016-03-20".to_string()));
+        assert_eq!(window_start(&state, 7), Day::new(2016, 3, 13));
+    }
+
+    #[test]
+    fn test_incremental_sync_only_reconciles_the_window() {
+        let mut state = State::default();
+        let thresholds = Thresholds::default();
+        apply(&mut state, data(100.0, &[transaction("old", 5.0, "2016-03-01", false),
+                                        transaction("pending", 12.5, "2016-03-18", true),
+                                        transaction("gone", 3.0, "2016-03-19", false)]),
+              None, Day::new(2016, 3, 20).unwrap(), &thresholds);
+
+        // The second fetch only covers the window, so `old` is not removed.
+        let since = window_start(&state, 7);
+        let changes = apply(&mut state, data(87.5, &[transaction("posted", 12.5, "2016-03-21", false),
+                                                     transaction("new", 1.0, "2016-03-22", false)]),
+                            since, Day::new(2016, 3, 22).unwrap(), &thresholds);
+
+        assert_eq!(changes.len(), 4);
+        assert!(match changes[0] { Change::Posted(ref p, ref t) => p.id == "pending" && t.id == "posted", _ => false });
+        assert!(match changes[1] { Change::Removed(ref t) => t.id == "gone", _ => false });
+        assert!(match changes[2] { Change::Added(ref t) => t.id == "new", _ => false });
+        assert!(match changes[3] { Change::Balance(_, Some(100.0), 87.5) => true, _ => false });
+
+        let ids: Vec<&str> = state.transactions.iter().map(|t| t.id.as_ref()).collect();
+        assert_eq!(ids, vec!["new", "posted", "old"]);
+    }
+
+    #[test]
+    fn test_removed_account() {
+        let mut state = State::default();
+        let thresholds = Thresholds::default();
+        apply(&mut state, accounts_data(&[account("acc1", 100.0), account("acc2", 20.0)], &[]),
+              None, Day::new(2016, 3, 20).unwrap(), &thresholds);
+
+        let since = window_start(&state, 7);
+        let changes = apply(&mut state, accounts_data(&[account("acc1", 100.0)], &[]),
+                            since, Day::new(2016, 3, 22).unwrap(), &thresholds);
+
+        assert_eq!(changes.len(), 1);
+        assert!(match changes[0] { Change::AccountRemoved(ref a) => a.id == "acc2", _ => false });
+        assert_eq!(state.accounts.len(), 1);
+    }
+
+}
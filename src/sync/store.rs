@@ -0,0 +1,223 @@
+//! Storage of the synchronized data of each user.
+//!
+//! Users are identified in a store by the SHA-256 of their access token (see
+//! [`key`](./fn.key.html)), so that the token itself is never written to disk.
+//! Any other storage, e.g a database, can be used by implementing
+//! [`Store`](./trait.Store.html).
+
+use std::collections::HashMap;
+use std::fs::{ self, File };
+use std::io::{ self, Read, Write };
+use std::path::{ Path, PathBuf };
+
+use api::data::Date;
+use api::data::raw::{ self, AttachRaw };
+use api::error::Error;
+use api::types::{ Account, Transaction };
+use api::user::User;
+
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json::{ self, Json };
+use sha2::{ Digest, Sha256 };
+
+/// # State
+/// Everything that is known about a user as of their last sync.
+#[derive(Debug, Clone, Default, RustcDecodable, RustcEncodable)]
+pub struct State {
+    /// The date of the last sync, if there has been one.
+    pub last_sync: Option<Date>,
+    /// The accounts of the user, as of the last sync.
+    pub accounts: Vec<Account>,
+    /// Every transaction of the user, newest first.
+    pub transactions: Vec<Transaction>
+}
+
+impl AttachRaw for State {
+
+    fn split_raw(json: &mut Json) -> Json {
+        raw::object(vec![
+            ("accounts", raw::split_field::<Vec<Account>>(json, "accounts")),
+            ("transactions", raw::split_field::<Vec<Transaction>>(json, "transactions"))
+        ])
+    }
+
+    fn attach_raw(&mut self, mut extra: Json) {
+        self.accounts.attach_raw(raw::take(&mut extra, "accounts"));
+        self.transactions.attach_raw(raw::take(&mut extra, "transactions"));
+    }
+
+}
+
+/// Anything that implements `Store` can keep the `State` of users between syncs.
+pub trait Store {
+    /// Load the state of the given user, or `None` if they have not been synced.
+    fn load(&self, user: &User) -> Result<Option<State>, Error>;
+    /// Replace the state of the given user.
+    fn save(&mut self, user: &User, state: &State) -> Result<(), Error>;
+    /// Forget the given user, e.g after `Payload::RemoveUser`.
+    fn remove(&mut self, user: &User) -> Result<(), Error>;
+}
+
+/// The key that identifies a user in a store, the hex encoded SHA-256 of their access token.
+pub fn key(user: &User) -> String {
+    Sha256::digest(user.access_token.expose().as_bytes()).to_hex()
+}
+
+/// # MemoryStore
+/// A `Store` that is kept in memory, and lost when dropped.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    states: HashMap<String, State>
+}
+
+impl MemoryStore {
+
+    /// Create an empty `MemoryStore`.
+    pub fn new() -> MemoryStore {
+        MemoryStore { states: HashMap::new() }
+    }
+
+}
+
+impl Store for MemoryStore {
+
+    fn load(&self, user: &User) -> Result<Option<State>, Error> {
+        Ok(self.states.get(&key(user)).cloned())
+    }
+
+    fn save(&mut self, user: &User, state: &State) -> Result<(), Error> {
+        self.states.insert(key(user), state.clone());
+        Ok(())
+    }
+
+    fn remove(&mut self, user: &User) -> Result<(), Error> {
+        self.states.remove(&key(user));
+        Ok(())
+    }
+
+}
+
+/// # FileStore
+/// A `Store` that keeps the state of each user in a JSON file within a directory.
+///
+/// Accounts and transactions are written in the same form that Plaid returns them,
+/// including the fields that are not decoded by this library.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf
+}
+
+impl FileStore {
+
+    /// Create a `FileStore` within the given directory, which is created when
+    /// the first state is saved.
+    pub fn new<P: AsRef<Path>>(dir: P) -> FileStore {
+        FileStore { dir: dir.as_ref().to_path_buf() }
+    }
+
+    /// The path of the file that keeps the state of the given user.
+    pub fn path(&self, user: &User) -> PathBuf {
+        self.dir.join(format!("{}.json", key(user)))
+    }
+
+}
+
+impl Store for FileStore {
+
+    fn load(&self, user: &User) -> Result<Option<State>, Error> {
+        let mut file = match File::open(self.path(user)) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::IO(e))
+        };
+        let mut contents = String::new();
+        try!(file.read_to_string(&mut contents));
+        Ok(Some(try!(raw::decode(&contents))))
+    }
+
+    fn save(&mut self, user: &User, state: &State) -> Result<(), Error> {
+        try!(fs::create_dir_all(&self.dir));
+        let path = self.path(user);
+        let tmp = path.with_extension("json.tmp");
+        {
+            let mut file = try!(File::create(&tmp));
+            try!(file.write_all(try!(json::encode(state)).as_bytes()));
+            try!(file.sync_all());
+        }
+        // Renaming is atomic, so a failed write never leaves a partial state behind.
+        try!(fs::rename(&tmp, &path));
+        Ok(())
+    }
+
+    fn remove(&mut self, user: &User) -> Result<(), Error> {
+        match fs::remove_file(self.path(user)) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            other => other.map_err(Error::IO)
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs;
+
+    use api::client::stream;
+    use api::product::ConnectData;
+    use api::user::User;
+    use rustc_serialize::json::Json;
+    use sync::store::*;
+
+    fn state() -> State {
+        let mut json = Json::from_str(include_str!("../../fixtures/post_connect_success.json")).unwrap();
+        let _ = stream::take_user(&mut json);
+        let data: ConnectData = stream::decode_raw(json).unwrap();
+        State { last_sync: Some("2016-03-20".to_string()), accounts: data.accounts, transactions: data.transactions }
+    }
+
+    fn check<S: Store>(mut store: S) {
+        let user = User::new("testaccesstoken");
+        let other = User::new("otheraccesstoken");
+        assert!(store.load(&user).unwrap().is_none());
+
+        let original = state();
+        store.save(&user, &original).unwrap();
+        let loaded = store.load(&user).unwrap().unwrap();
+        assert!(store.load(&other).unwrap().is_none());
+
+        assert_eq!(loaded.last_sync, original.last_sync);
+        assert_eq!(loaded.accounts.len(), original.accounts.len());
+        assert_eq!(loaded.accounts[0].current_balance, original.accounts[0].current_balance);
+        assert_eq!(loaded.accounts[0].extra(), original.accounts[0].extra());
+        assert_eq!(loaded.transactions.len(), original.transactions.len());
+        for (l, o) in loaded.transactions.iter().zip(original.transactions.iter()) {
+            assert_eq!(l.id, o.id);
+            assert_eq!(l.amount, o.amount);
+            assert_eq!(l.context, o.context);
+            assert_eq!(l.pending_transaction_id, o.pending_transaction_id);
+            assert_eq!(l.extra(), o.extra());
+        }
+
+        store.remove(&user).unwrap();
+        assert!(store.load(&user).unwrap().is_none());
+        store.remove(&user).unwrap();
+    }
+
+    #[test]
+    fn test_memory_store() {
+        check(MemoryStore::new());
+    }
+
+    #[test]
+    fn test_file_store() {
+        let dir = env::temp_dir().join(format!("plaid-file-store-{}", ::std::process::id()));
+        let store = FileStore::new(&dir);
+        let user = User::new("testaccesstoken");
+        assert!(!store.path(&user).to_string_lossy().contains("testaccesstoken"));
+        check(store);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
@@ -0,0 +1,104 @@
+//! Fixtures that are shared by the tests of several modules.
+
+use api::data::Amount;
+use api::data::raw::RawObject;
+use api::data::transaction::{ Context, Meta };
+use api::account;
+use api::types::{ Account, Address, Transaction };
+
+/// A posted transaction in account `acc1`, named after its id and without categories.
+pub fn transaction(id: &str, amount: Amount, date: &str) -> Transaction {
+    Transaction {
+        id: id.to_string(),
+        account_id: "acc1".to_string(),
+        amount: amount,
+        category_id: 13005000,
+        context: Context::Place,
+        categories: Vec::new(),
+        pending: false,
+        date: date.to_string(),
+        name: Some(id.to_string()),
+        original_description: None,
+        score: None,
+        pending_transaction_id: None,
+        meta: None,
+        raw_json: RawObject::default()
+    }
+}
+
+/// Changes to a transaction that was created by `transaction`.
+pub trait TransactionFixture {
+    /// Move the transaction to the given account.
+    fn account(self, id: &str) -> Self;
+    /// Rename the transaction.
+    fn named(self, name: &str) -> Self;
+    /// Set the category hierarchy of the transaction.
+    fn categories(self, categories: &[&str]) -> Self;
+    /// Mark the transaction as pending.
+    fn pending(self) -> Self;
+    /// Locate the transaction in the given state.
+    fn state(self, state: &str) -> Self;
+}
+
+impl TransactionFixture for Transaction {
+
+    fn account(mut self, id: &str) -> Transaction {
+        self.account_id = id.to_string();
+        self
+    }
+
+    fn named(mut self, name: &str) -> Transaction {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    fn categories(mut self, categories: &[&str]) -> Transaction {
+        self.categories = categories.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    fn pending(mut self) -> Transaction {
+        self.pending = true;
+        self
+    }
+
+    fn state(mut self, state: &str) -> Transaction {
+        self.meta = Some(Meta {
+            location: Address {
+                zip: None,
+                state: Some(state.to_string()),
+                city: None,
+                street: None,
+                longitude: None,
+                latitude: None,
+                raw_json: RawObject::default()
+            }
+        });
+        self
+    }
+
+}
+
+/// An account of the given type with a current balance.
+pub fn account(id: &str, account_type: &str, current: Amount) -> Account {
+    Account {
+        id: id.to_string(),
+        item_id: "item".to_string(),
+        current_balance: current,
+        available_balance: None,
+        institution: "fake_institution".to_string(),
+        account_type: account_type.to_string(),
+        account_subtype: None,
+        account_number: None,
+        routing_number: None,
+        wire_routing_number: None,
+        meta: None,
+        raw_json: RawObject::default()
+    }
+}
+
+/// Set the credit limit of an account that was created by `account`.
+pub fn with_limit(mut account: Account, limit: Amount) -> Account {
+    account.meta = Some(account::Meta { name: Some("Card".to_string()), number: Some("1234".to_string()), limit: Some(limit) });
+    account
+}
@@ -0,0 +1,219 @@
+//! A `TokenVault` that is kept in a single JSON file.
+
+use std::collections::BTreeMap;
+use std::fs::{ self, File, OpenOptions };
+use std::io::{ self, Read, Write };
+use std::path::{ Path, PathBuf };
+
+use api::error::Error;
+use api::user::User;
+use vault::{ Entry, VaultKeyProvider, Tags, TokenVault };
+
+use rustc_serialize::json;
+
+/// # FileVault
+/// A `TokenVault` whose entries are kept in a JSON file, which is rewritten
+/// after every change. Only the encrypted tokens are written to the file.
+///
+/// On Unix, the file is only readable and writable by its owner.
+#[derive(Debug)]
+pub struct FileVault<K: VaultKeyProvider> {
+    path: PathBuf,
+    keys: K,
+    entries: BTreeMap<String, Entry>
+}
+
+impl<K: VaultKeyProvider> FileVault<K> {
+
+    /// Open the vault at `path`, which is created when the first entry is inserted.
+    pub fn open<P: AsRef<Path>>(path: P, keys: K) -> Result<FileVault<K>, Error> {
+        let path = path.as_ref().to_path_buf();
+        let entries = match File::open(&path) {
+            Ok(mut file) => {
+                let mut contents = String::new();
+                try!(file.read_to_string(&mut contents));
+                try!(json::decode(&contents))
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(Error::IO(e))
+        };
+        Ok(FileVault { path: path, keys: keys, entries: entries })
+    }
+
+    /// The names of every entry, in order.
+    pub fn names(&self) -> Vec<&str> {
+        self.entries.keys().map(|k| k.as_ref()).collect()
+    }
+
+    /// Write `entries` to the file, and only then keep them, so that the vault
+    /// is unchanged when the file can not be written.
+    fn save(&mut self, entries: BTreeMap<String, Entry>) -> Result<(), Error> {
+        let tmp = self.path.with_extension("tmp");
+        {
+            let mut file = try!(create(&tmp));
+            try!(file.write_all(try!(json::encode(&entries)).as_bytes()));
+            try!(file.sync_all());
+        }
+        try!(fs::rename(&tmp, &self.path));
+        self.entries = entries;
+        Ok(())
+    }
+
+}
+
+#[cfg(unix)]
+fn create(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)
+}
+
+#[cfg(not(unix))]
+fn create(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create(true).truncate(true).open(path)
+}
+
+impl<K: VaultKeyProvider> TokenVault for FileVault<K> {
+
+    fn insert(&mut self, name: &str, user: &User, tags: Tags) -> Result<(), Error> {
+        let entry = try!(Entry::seal(&self.keys, name, user, tags));
+        let mut entries = self.entries.clone();
+        entries.insert(name.to_string(), entry);
+        self.save(entries)
+    }
+
+    fn get(&self, name: &str) -> Result<Option<User>, Error> {
+        match self.entries.get(name) {
+            Some(entry) => entry.open(&self.keys, name).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn tags(&self, name: &str) -> Option<Tags> {
+        self.entries.get(name).map(|e| e.tags.clone())
+    }
+
+    fn find(&self, query: &Tags) -> Result<Vec<(String, User)>, Error> {
+        let mut users = Vec::new();
+        for (name, entry) in self.entries.iter().filter(|&(_, e)| e.tags.matches(query)) {
+            users.push((name.clone(), try!(entry.open(&self.keys, name))));
+        }
+        Ok(users)
+    }
+
+    fn remove(&mut self, name: &str) -> Result<bool, Error> {
+        if !self.entries.contains_key(name) { return Ok(false) }
+        let mut entries = self.entries.clone();
+        entries.remove(name);
+        try!(self.save(entries));
+        Ok(true)
+    }
+
+    fn rotate(&mut self) -> Result<usize, Error> {
+        let (current, _) = try!(self.keys.current());
+        let mut rotated = BTreeMap::new();
+        for (name, entry) in self.entries.iter().filter(|&(_, e)| e.key_id != current) {
+            let user = try!(entry.open(&self.keys, name));
+            rotated.insert(name.clone(), try!(Entry::seal(&self.keys, name, &user, entry.tags.clone())));
+        }
+
+        // Nothing is changed unless every entry could be re-encrypted and saved.
+        let count = rotated.len();
+        if count > 0 {
+            let mut entries = self.entries.clone();
+            entries.extend(rotated);
+            try!(self.save(entries));
+        }
+        Ok(count)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use api::user::User;
+    use vault::*;
+
+    fn path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("plaid-vault-{}-{}.json", name, ::std::process::id()))
+    }
+
+    fn user(token: &str) -> User {
+        User::new(token)
+    }
+
+    #[test]
+    fn test_file_vault_persists_encrypted_entries() {
+        let path = path("persist");
+        let keys = StaticVaultKeyProvider::new("a", generate_key());
+        {
+            let mut vault = FileVault::open(&path, keys.clone()).unwrap();
+            vault.insert("alice", &user("alicetoken"), Tags::default().environment("sandbox")).unwrap();
+            vault.insert("bob", &user("bobtoken"), Tags::default().environment("production")).unwrap();
+        }
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("alicetoken") && !contents.contains("bobtoken"));
+
+        let mut vault = FileVault::open(&path, keys).unwrap();
+        assert_eq!(vault.names(), vec!["alice", "bob"]);
+        assert_eq!(vault.get("bob").unwrap().unwrap().access_token.expose(), "bobtoken");
+        assert!(vault.get("carol").unwrap().is_none());
+        assert_eq!(vault.tags("alice").unwrap().environment, Some("sandbox".to_string()));
+
+        let found = vault.find(&Tags::default().environment("sandbox")).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, "alice".to_string());
+
+        assert!(vault.remove("alice").unwrap());
+        assert!(!vault.remove("alice").unwrap());
+        assert_eq!(FileVault::open(&path, StaticVaultKeyProvider::new("a", [0; 32])).unwrap().names(), vec!["bob"]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_vault_is_unchanged_when_saving_fails() {
+        let path = path("unsaved");
+        let mut vault = FileVault::open(&path, StaticVaultKeyProvider::new("a", generate_key())).unwrap();
+        vault.insert("alice", &user("alicetoken"), Tags::default()).unwrap();
+
+        // The temporary file can not be created over a directory.
+        let tmp = path.with_extension("tmp");
+        fs::create_dir(&tmp).unwrap();
+        assert!(vault.insert("bob", &user("bobtoken"), Tags::default()).is_err());
+        assert!(vault.remove("alice").is_err());
+        assert_eq!(vault.names(), vec!["alice"]);
+        assert_eq!(vault.get("alice").unwrap().unwrap().access_token.expose(), "alicetoken");
+
+        fs::remove_dir(&tmp).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_vault_rotation() {
+        let path = path("rotate");
+        let mut keys = StaticVaultKeyProvider::new("a", generate_key());
+        {
+            let mut vault = FileVault::open(&path, keys.clone()).unwrap();
+            vault.insert("alice", &user("alicetoken"), Tags::default()).unwrap();
+            vault.insert("bob", &user("bobtoken"), Tags::default()).unwrap();
+        }
+
+        keys.rotate("b", generate_key());
+        let mut vault = FileVault::open(&path, keys.clone()).unwrap();
+        assert_eq!(vault.rotate().unwrap(), 2);
+        assert_eq!(vault.rotate().unwrap(), 0);
+
+        // The old key is no longer needed.
+        let mut only_new = keys.clone();
+        only_new.insert("a", [0; 32]);
+        let vault = FileVault::open(&path, only_new).unwrap();
+        assert_eq!(vault.get("alice").unwrap().unwrap().access_token.expose(), "alicetoken");
+        fs::remove_file(&path).unwrap();
+    }
+
+}
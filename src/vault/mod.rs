@@ -0,0 +1,329 @@
+//! Encrypted storage of access tokens.
+//!
+//! A [`TokenVault`][TokenVault] keeps the access token of each `User` under a name
+//! of your choosing, encrypted at rest with ChaCha20-Poly1305. Keys are given by a
+//! [`VaultKeyProvider`][VaultKeyProvider], and every entry records the id of the
+//! key that it was encrypted with, so that keys can be rotated without losing
+//! access to existing entries. Each entry can be tagged with the environment,
+//! product and institution that it belongs to, which are authenticated along with
+//! the token.
+//!
+//! ## Example
+//!
+//! ```
+//! # extern crate plaid;
+//! # fn main() {
+//! # let path = ::std::env::temp_dir().join(format!("plaid-vault-doc-{}.json", ::std::process::id()));
+//! use plaid::api::user::User;
+//! use plaid::vault::{ self, TokenVault, StaticVaultKeyProvider, Tags };
+//! use plaid::vault::file::FileVault;
+//!
+//! let mut keys = StaticVaultKeyProvider::new("2016-03", vault::generate_key());
+//! let mut tokens = FileVault::open(&path, keys.clone()).unwrap();
+//!
+//! let user = User::new("testaccesstoken");
+//! let tags = Tags::default().environment("sandbox").institution("fake_institution");
+//! tokens.insert("alice", &user, tags).unwrap();
+//!
+//! assert_eq!(tokens.get("alice").unwrap().unwrap().access_token.expose(), "testaccesstoken");
+//! assert_eq!(tokens.find(&Tags::default().environment("sandbox")).unwrap().len(), 1);
+//!
+//! // Rotate to a new key, and re-encrypt every entry with it.
+//! keys.rotate("2016-04", vault::generate_key());
+//! let mut tokens = FileVault::open(&path, keys).unwrap();
+//! assert_eq!(tokens.rotate().unwrap(), 1);
+//! # ::std::fs::remove_file(&path).unwrap();
+//! # }
+//! ```
+//!
+//! [TokenVault]: ./trait.TokenVault.html
+//! [VaultKeyProvider]: ./trait.VaultKeyProvider.html
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+
+use api::data::Institution;
+use api::error::Error;
+use api::user::User;
+
+use chacha20poly1305::{ ChaCha20Poly1305, Nonce };
+use chacha20poly1305::Key as CipherKey;
+use chacha20poly1305::aead::{ self, Aead, AeadCore, KeyInit, OsRng };
+use rustc_serialize::base64::{ self, ToBase64, FromBase64 };
+use rustc_serialize::json;
+use zeroize::{ Zeroize, Zeroizing };
+
+pub use self::file::FileVault;
+pub mod file;
+
+/// A 256 bit encryption key, which is zeroed when it is dropped.
+pub type Key = Zeroizing<[u8; 32]>;
+
+/// Generate a new random `Key`.
+pub fn generate_key() -> Key {
+    let mut generated = ChaCha20Poly1305::generate_key(&mut OsRng);
+    let mut key = Zeroizing::new([0; 32]);
+    key.copy_from_slice(&generated);
+    generated.as_mut_slice().zeroize();
+    key
+}
+
+/// # VaultError
+/// The reasons that a vault operation can fail.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VaultError {
+    /// The key that an entry was encrypted with is not known to the `VaultKeyProvider`.
+    UnknownKey(String),
+    /// An entry could not be decrypted, because it or its tags have been tampered
+    /// with, or the key is wrong.
+    Decryption,
+    /// An entry is not stored in the expected format.
+    Malformed
+}
+
+impl fmt::Display for VaultError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.description())
+    }
+
+}
+
+impl StdError for VaultError {
+
+    fn description(&self) -> &str {
+        match *self {
+            VaultError::UnknownKey(_) => "The vault entry was encrypted with an unknown key",
+            VaultError::Decryption => "The vault entry could not be decrypted",
+            VaultError::Malformed => "The vault entry is malformed"
+        }
+    }
+
+}
+
+/// Anything that implements `VaultKeyProvider` can provide the keys of a `TokenVault`.
+pub trait VaultKeyProvider {
+    /// The id and value of the key that new entries are encrypted with.
+    fn current(&self) -> Result<(String, Key), Error>;
+    /// The key with the given id.
+    fn key(&self, id: &str) -> Result<Key, Error>;
+}
+
+/// # StaticVaultKeyProvider
+/// A `VaultKeyProvider` for a set of keys that are known up front,
+/// e.g from configuration or the environment.
+#[derive(Clone)]
+pub struct StaticVaultKeyProvider {
+    current: String,
+    keys: HashMap<String, Key>
+}
+
+impl StaticVaultKeyProvider {
+
+    /// Create a `StaticVaultKeyProvider` that encrypts with the given key.
+    pub fn new<S: Into<String>, K: Into<Key>>(id: S, key: K) -> StaticVaultKeyProvider {
+        let id = id.into();
+        let mut keys = HashMap::new();
+        keys.insert(id.clone(), key.into());
+        StaticVaultKeyProvider { current: id, keys: keys }
+    }
+
+    /// Add a key that can only be used to decrypt existing entries.
+    pub fn insert<S: Into<String>, K: Into<Key>>(&mut self, id: S, key: K) -> &mut StaticVaultKeyProvider {
+        self.keys.insert(id.into(), key.into());
+        self
+    }
+
+    /// Add a key and encrypt new entries with it. Previous keys are kept,
+    /// so that existing entries can still be decrypted.
+    pub fn rotate<S: Into<String>, K: Into<Key>>(&mut self, id: S, key: K) -> &mut StaticVaultKeyProvider {
+        let id = id.into();
+        self.keys.insert(id.clone(), key.into());
+        self.current = id;
+        self
+    }
+
+}
+
+/// Keys are not printed.
+impl fmt::Debug for StaticVaultKeyProvider {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "StaticVaultKeyProvider {{ current: {:?}, keys: {} }}", self.current, self.keys.len())
+    }
+
+}
+
+impl VaultKeyProvider for StaticVaultKeyProvider {
+
+    fn current(&self) -> Result<(String, Key), Error> {
+        let key = try!(self.key(&self.current));
+        Ok((self.current.clone(), key))
+    }
+
+    fn key(&self, id: &str) -> Result<Key, Error> {
+        self.keys.get(id).cloned().ok_or_else(|| Error::Vault(VaultError::UnknownKey(id.to_string())))
+    }
+
+}
+
+/// # Tags
+/// Describes what an access token belongs to. Tags that are not set match anything
+/// when used to `find` entries.
+#[derive(Debug, Clone, Default, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct Tags {
+    /// E.g `production` or `sandbox`.
+    pub environment: Option<String>,
+    /// E.g `connect`.
+    pub product: Option<String>,
+    /// The institution of the user.
+    pub institution: Option<Institution>
+}
+
+impl Tags {
+
+    /// Set the environment.
+    pub fn environment<S: Into<String>>(mut self, environment: S) -> Tags {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    /// Set the product.
+    pub fn product<S: Into<String>>(mut self, product: S) -> Tags {
+        self.product = Some(product.into());
+        self
+    }
+
+    /// Set the institution.
+    pub fn institution<S: Into<Institution>>(mut self, institution: S) -> Tags {
+        self.institution = Some(institution.into());
+        self
+    }
+
+    /// Whether every tag that is set in `query` is the same in `self`.
+    pub fn matches(&self, query: &Tags) -> bool {
+        fn check(a: &Option<String>, q: &Option<String>) -> bool {
+            q.is_none() || a == q
+        }
+        check(&self.environment, &query.environment) &&
+            check(&self.product, &query.product) &&
+            check(&self.institution, &query.institution)
+    }
+
+}
+
+/// # Entry
+/// An encrypted access token, as it is stored.
+#[derive(Debug, Clone, PartialEq, RustcDecodable, RustcEncodable)]
+pub struct Entry {
+    /// The id of the key that the token was encrypted with.
+    pub key_id: String,
+    /// The base64 encoded nonce.
+    pub nonce: String,
+    /// The base64 encoded, encrypted access token.
+    pub ciphertext: String,
+    /// The tags of the token, which are authenticated but not encrypted.
+    pub tags: Tags
+}
+
+impl Entry {
+
+    /// Encrypt the token of `user` with the current key of `keys`. The `name` and
+    /// `tags` are authenticated, so that entries can not be swapped or re-tagged.
+    pub fn seal<K: VaultKeyProvider>(keys: &K, name: &str, user: &User, tags: Tags) -> Result<Entry, Error> {
+        let (key_id, key) = try!(keys.current());
+        let cipher = ChaCha20Poly1305::new(CipherKey::from_slice(&key[..]));
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = try!(associated_data(name, &tags));
+        let ciphertext = try!(cipher.encrypt(&nonce, aead::Payload { msg: user.access_token.expose().as_bytes(), aad: &aad })
+                              .map_err(|_| Error::InternalError));
+        Ok(Entry {
+            key_id: key_id,
+            nonce: nonce.to_base64(base64::STANDARD),
+            ciphertext: ciphertext.to_base64(base64::STANDARD),
+            tags: tags
+        })
+    }
+
+    /// Decrypt the token of this entry, which must have been sealed under `name`.
+    pub fn open<K: VaultKeyProvider>(&self, keys: &K, name: &str) -> Result<User, Error> {
+        let key = try!(keys.key(&self.key_id));
+        let malformed = |_| Error::Vault(VaultError::Malformed);
+        let nonce = try!(self.nonce.from_base64().map_err(malformed));
+        let ciphertext = try!(self.ciphertext.from_base64().map_err(malformed));
+        if nonce.len() != 12 { return Err(Error::Vault(VaultError::Malformed)) }
+
+        let cipher = ChaCha20Poly1305::new(CipherKey::from_slice(&key[..]));
+        let aad = try!(associated_data(name, &self.tags));
+        let token = try!(cipher.decrypt(Nonce::from_slice(&nonce), aead::Payload { msg: &ciphertext, aad: &aad })
+                         .map_err(|_| Error::Vault(VaultError::Decryption)));
+        let token = try!(String::from_utf8(token).map_err(|_| Error::Vault(VaultError::Malformed)));
+        Ok(User::new(token))
+    }
+
+}
+
+fn associated_data(name: &str, tags: &Tags) -> Result<Vec<u8>, Error> {
+    Ok(format!("{}\0{}", name, try!(json::encode(tags))).into_bytes())
+}
+
+/// Anything that implements `TokenVault` can store the access tokens of users.
+pub trait TokenVault {
+    /// Store the token of `user` under `name`, replacing any existing entry.
+    fn insert(&mut self, name: &str, user: &User, tags: Tags) -> Result<(), Error>;
+    /// The user stored under `name`, if any.
+    fn get(&self, name: &str) -> Result<Option<User>, Error>;
+    /// The tags of the entry stored under `name`, if any.
+    fn tags(&self, name: &str) -> Option<Tags>;
+    /// The name and user of every entry whose tags match `query`, ordered by name.
+    fn find(&self, query: &Tags) -> Result<Vec<(String, User)>, Error>;
+    /// Remove the entry stored under `name`, returning whether it existed.
+    fn remove(&mut self, name: &str) -> Result<bool, Error>;
+    /// Re-encrypt every entry that is not encrypted with the current key,
+    /// returning the number of entries that were re-encrypted.
+    fn rotate(&mut self) -> Result<usize, Error>;
+}
+
+#[cfg(test)]
+mod tests {
+
+    use api::error::Error;
+    use api::user::User;
+    use vault::*;
+
+    fn user() -> User {
+        User::new("testaccesstoken")
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let keys = StaticVaultKeyProvider::new("a", [1; 32]);
+        let entry = Entry::seal(&keys, "alice", &user(), Tags::default().product("connect")).unwrap();
+        assert_eq!(entry.key_id, "a".to_string());
+        assert!(!entry.ciphertext.contains("testaccesstoken"));
+        assert_eq!(entry.open(&keys, "alice").unwrap().access_token.expose(), "testaccesstoken");
+
+        // The name and tags are authenticated.
+        assert!(match entry.open(&keys, "bob") { Err(Error::Vault(VaultError::Decryption)) => true, _ => false });
+        let mut retagged = entry.clone();
+        retagged.tags = Tags::default().product("auth");
+        assert!(match retagged.open(&keys, "alice") { Err(Error::Vault(VaultError::Decryption)) => true, _ => false });
+
+        // As is the key.
+        let other = StaticVaultKeyProvider::new("a", [2; 32]);
+        assert!(match entry.open(&other, "alice") { Err(Error::Vault(VaultError::Decryption)) => true, _ => false });
+        let unknown = StaticVaultKeyProvider::new("b", [1; 32]);
+        assert!(match entry.open(&unknown, "alice") { Err(Error::Vault(VaultError::UnknownKey(ref id))) => id == "a", _ => false });
+    }
+
+    #[test]
+    fn test_tags_match() {
+        let tags = Tags::default().environment("sandbox").product("connect").institution("chase");
+        assert!(tags.matches(&Tags::default()));
+        assert!(tags.matches(&Tags::default().environment("sandbox").institution("chase")));
+        assert!(!tags.matches(&Tags::default().environment("production")));
+        assert!(!Tags::default().matches(&Tags::default().product("connect")));
+    }
+
+}